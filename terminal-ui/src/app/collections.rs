@@ -0,0 +1,264 @@
+use std::{fs, io::ErrorKind};
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    buffer::Buffer,
+    layout::{
+        Constraint::{Length, Min},
+        Layout, Rect,
+    },
+    prelude::Stylize,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Paragraph, Widget, Wrap},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::app::http::Request;
+
+// Where collections are loaded from and saved to, relative to the working
+// directory
+pub const COLLECTIONS_FILE: &str = "collections.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    #[serde(default)]
+    pub requests: Vec<SavedRequest>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedRequest {
+    pub name: String,
+    #[serde(flatten)]
+    pub request: Request,
+}
+
+// What the host should do after the sidebar handled a key
+pub enum SidebarEvent {
+    Ignored,
+    Handled,
+    // Put this request into the editor
+    Load(Request),
+    // Load it and send it
+    Send(Request),
+}
+
+// A name being typed for a new or renamed entry
+struct Prompt {
+    label: &'static str,
+    text: String,
+    apply: PromptAction,
+}
+
+enum PromptAction {
+    NewCollection,
+    NewRequest(Request),
+    Rename,
+}
+
+// The left menu: saved requests grouped into collections, written back to
+// the collections file after every change
+pub struct Sidebar {
+    path: String,
+    collections: Vec<Collection>,
+    // Collection index and, on a request row, the request index
+    selected: (usize, Option<usize>),
+    prompt: Option<Prompt>,
+    notice: Option<String>,
+}
+
+impl Sidebar {
+    // A missing file starts with no collections; an unreadable one is
+    // reported and not overwritten until something is saved
+    pub fn load(path: &str) -> Self {
+        let (collections, notice) = match fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str(&text) {
+                Ok(collections) => (collections, None),
+                Err(err) => (Vec::new(), Some(format!("\"{}\" is not a valid collections file: {}", path, err))),
+            },
+            Err(err) if err.kind() == ErrorKind::NotFound => (Vec::new(), None),
+            Err(err) => (Vec::new(), Some(format!("Unable to read \"{}\": {}", path, err))),
+        };
+        Sidebar {
+            path: path.to_string(),
+            collections,
+            selected: (0, None),
+            prompt: None,
+            notice,
+        }
+    }
+
+    // Handles a key press while the sidebar has focus. `current` is the
+    // request in the editor, saved by "new request".
+    pub fn handle_key(&mut self, code: KeyCode, current: &Request) -> SidebarEvent {
+        if let Some(prompt) = &mut self.prompt {
+            match code {
+                KeyCode::Char(c) => prompt.text.push(c),
+                KeyCode::Backspace => {
+                    prompt.text.pop();
+                }
+                KeyCode::Enter => self.finish_prompt(),
+                KeyCode::Esc => self.prompt = None,
+                _ => {}
+            }
+            return SidebarEvent::Handled;
+        }
+
+        match code {
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                let (collection, Some(request)) = self.selected else {
+                    return SidebarEvent::Handled;
+                };
+                let request = self.collections[collection].requests[request].request.clone();
+                return if code == KeyCode::Enter {
+                    SidebarEvent::Send(request)
+                } else {
+                    SidebarEvent::Load(request)
+                };
+            }
+            KeyCode::Char('c') | KeyCode::Char('C') => self.start_prompt("New collection", String::new(), PromptAction::NewCollection),
+            KeyCode::Char('n') | KeyCode::Char('N') => {
+                if self.collections.is_empty() {
+                    self.notice = Some("Create a collection first (<c>)".to_string());
+                } else {
+                    let name = format!("{} {}", current.method, current.url);
+                    self.start_prompt("Save request as", name, PromptAction::NewRequest(current.clone()));
+                }
+            }
+            KeyCode::Char('r') | KeyCode::Char('R') => {
+                if let Some(name) = self.selected_name() {
+                    self.start_prompt("Rename to", name, PromptAction::Rename);
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Char('D') | KeyCode::Delete => self.delete(),
+            _ => return SidebarEvent::Ignored,
+        }
+        SidebarEvent::Handled
+    }
+
+    // Rows in display order, as (collection, request) indexes
+    fn rows(&self) -> Vec<(usize, Option<usize>)> {
+        let mut rows = Vec::new();
+        for (i, collection) in self.collections.iter().enumerate() {
+            rows.push((i, None));
+            rows.extend((0..collection.requests.len()).map(|j| (i, Some(j))));
+        }
+        rows
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let rows = self.rows();
+        let Some(current) = rows.iter().position(|row| *row == self.selected) else {
+            return;
+        };
+        let next = current.saturating_add_signed(delta).min(rows.len() - 1);
+        self.selected = rows[next];
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        let collection = self.collections.get(self.selected.0)?;
+        match self.selected.1 {
+            Some(request) => Some(collection.requests[request].name.clone()),
+            None => Some(collection.name.clone()),
+        }
+    }
+
+    fn start_prompt(&mut self, label: &'static str, text: String, apply: PromptAction) {
+        self.prompt = Some(Prompt { label, text, apply });
+    }
+
+    fn finish_prompt(&mut self) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+        let name = prompt.text.trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+
+        match prompt.apply {
+            PromptAction::NewCollection => {
+                self.collections.push(Collection {
+                    name,
+                    requests: Vec::new(),
+                });
+                self.selected = (self.collections.len() - 1, None);
+            }
+            PromptAction::NewRequest(request) => {
+                let collection = &mut self.collections[self.selected.0];
+                collection.requests.push(SavedRequest { name, request });
+                self.selected = (self.selected.0, Some(collection.requests.len() - 1));
+            }
+            PromptAction::Rename => match self.selected {
+                (collection, Some(request)) => self.collections[collection].requests[request].name = name,
+                (collection, None) => self.collections[collection].name = name,
+            },
+        }
+        self.save();
+    }
+
+    fn delete(&mut self) {
+        match self.selected {
+            (collection, Some(request)) => {
+                let requests = &mut self.collections[collection].requests;
+                requests.remove(request);
+                self.selected.1 = request.checked_sub(1).or((!requests.is_empty()).then_some(0));
+            }
+            (collection, None) if collection < self.collections.len() => {
+                self.collections.remove(collection);
+                self.selected = (collection.saturating_sub(1), None);
+            }
+            _ => return,
+        }
+        self.save();
+    }
+
+    fn save(&mut self) {
+        let result = serde_json::to_string_pretty(&self.collections)
+            .map_err(|err| err.to_string())
+            .and_then(|text| {
+                fs::write(&self.path, text + "\n").map_err(|err| format!("Unable to write \"{}\": {}", self.path, err))
+            });
+        self.notice = result.err();
+    }
+}
+
+impl Widget for &Sidebar {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut lines: Vec<Line> = Vec::new();
+        for (collection, request) in self.rows() {
+            let line = match request {
+                None => Line::from(format!("▾ {}", self.collections[collection].name).bold()),
+                Some(request) => {
+                    let saved = &self.collections[collection].requests[request];
+                    Line::from(vec![
+                        "    ".into(),
+                        format!("{} ", saved.request.method).green(),
+                        saved.name.clone().into(),
+                    ])
+                }
+            };
+            if (collection, request) == self.selected {
+                lines.push(line.style(Style::default().bg(Color::DarkGray)));
+            } else {
+                lines.push(line);
+            }
+        }
+        if self.collections.is_empty() {
+            lines.push("No collections. <c> creates one.".dark_gray().into());
+        }
+
+        let footer = match (&self.prompt, &self.notice) {
+            (Some(prompt), _) => Line::from(vec![format!("{}: ", prompt.label).bold(), format!("{}▏", prompt.text).into()]),
+            (None, Some(notice)) => Line::from(notice.clone().red()),
+            (None, None) => Line::from("<c> collection <n> save <r> rename <d> delete".dark_gray()),
+        };
+
+        let [list_area, footer_area] = Layout::vertical([Min(0), Length(2)]).areas(area);
+        Paragraph::new(lines).render(list_area, buf);
+        Paragraph::new(footer).wrap(Wrap { trim: true }).render(footer_area, buf);
+    }
+}
@@ -0,0 +1,175 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    buffer::Buffer,
+    layout::{
+        Constraint::{self, Length, Min},
+        Layout, Rect,
+    },
+    prelude::Stylize,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Cell, Paragraph, Row, Table, Widget},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Name,
+    Value,
+}
+
+// Checks a cell's text before it is stored
+pub type Validator = fn(&str) -> Result<(), String>;
+
+// An editable name/value table, used for request headers and environment
+// variables
+pub struct KeyValueEditor {
+    rows: Vec<(String, String)>,
+    selected: usize,
+    column: Option<Column>,
+    // The text of the cell being edited, if any
+    editing: Option<String>,
+    error: Option<String>,
+    validate_name: Validator,
+    validate_value: Validator,
+}
+
+impl KeyValueEditor {
+    pub fn new(rows: Vec<(String, String)>, validate_name: Validator, validate_value: Validator) -> Self {
+        KeyValueEditor {
+            rows,
+            selected: 0,
+            column: None,
+            editing: None,
+            error: None,
+            validate_name,
+            validate_value,
+        }
+    }
+
+    // The entries; rows with an empty name are left out
+    pub fn rows(&self) -> Vec<(String, String)> {
+        self.rows
+            .iter()
+            .filter(|(name, _)| !name.is_empty())
+            .cloned()
+            .collect()
+    }
+
+    // Handles a key press, returning false for keys the editor doesn't use
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        if let Some(text) = &mut self.editing {
+            match code {
+                KeyCode::Char(c) => text.push(c),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Enter => self.commit(),
+                KeyCode::Esc => {
+                    self.editing = None;
+                    self.error = None;
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1)),
+            KeyCode::Tab => {
+                self.column = match self.column {
+                    Some(Column::Name) => Some(Column::Value),
+                    _ => Some(Column::Name),
+                }
+            }
+            KeyCode::Char('a') | KeyCode::Char('A') => {
+                self.rows.push((String::new(), String::new()));
+                self.selected = self.rows.len() - 1;
+                self.column = Some(Column::Name);
+                self.editing = Some(String::new());
+            }
+            KeyCode::Char('d') | KeyCode::Char('D') | KeyCode::Delete => {
+                if self.selected < self.rows.len() {
+                    self.rows.remove(self.selected);
+                    self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+                }
+            }
+            KeyCode::Enter => {
+                if let Some((name, value)) = self.rows.get(self.selected) {
+                    let column = *self.column.get_or_insert(Column::Name);
+                    let text = if column == Column::Name { name } else { value };
+                    self.editing = Some(text.clone());
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // Stores the edited text in its cell, unless the validator rejects it
+    fn commit(&mut self) {
+        let Some(text) = self.editing.take() else {
+            return;
+        };
+        let text = text.trim().to_string();
+        let valid = match self.column {
+            // An empty name leaves the row out rather than being an error
+            Some(Column::Name) if text.is_empty() => Ok(()),
+            Some(Column::Name) => (self.validate_name)(&text),
+            _ => (self.validate_value)(&text),
+        };
+        if let Err(err) = valid {
+            self.error = Some(err);
+            self.editing = Some(text);
+            return;
+        }
+
+        self.error = None;
+        let (name, value) = &mut self.rows[self.selected];
+        match self.column {
+            Some(Column::Name) => *name = text,
+            _ => *value = text,
+        }
+    }
+}
+
+impl Widget for &KeyValueEditor {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let editing = |row: usize, column: Column| {
+            (row == self.selected && self.column == Some(column))
+                .then_some(self.editing.as_ref())
+                .flatten()
+        };
+        let cell = |row: usize, column: Column, text: &str| match editing(row, column) {
+            Some(text) => Cell::from(format!("{}▏", text)).style(Style::default().fg(Color::Black).bg(Color::Yellow)),
+            None if row == self.selected && self.column == Some(column) => {
+                Cell::from(text.to_string()).style(Style::default().bg(Color::DarkGray))
+            }
+            None => Cell::from(text.to_string()),
+        };
+
+        let rows = self.rows.iter().enumerate().map(|(i, (name, value))| {
+            let row = Row::new([cell(i, Column::Name, name), cell(i, Column::Value, value)]);
+            if i == self.selected {
+                row.bold()
+            } else {
+                row
+            }
+        });
+
+        let help = match &self.error {
+            Some(err) => Line::from(err.clone().red()),
+            None if self.rows.is_empty() => Line::from("Empty. <a> adds a row.".dark_gray()),
+            None => Line::from(
+                "<a> add  <d> delete  <Tab> column  <Enter> edit/save  <Esc> cancel".dark_gray(),
+            ),
+        };
+
+        let [table_area, help_area] = Layout::vertical([Min(0), Length(1)]).areas(area);
+        Table::new(rows, [Constraint::Percentage(35), Constraint::Percentage(65)])
+            .header(Row::new(["Name", "Value"]).fg(Color::LightBlue).bold())
+            .column_spacing(2)
+            .render(table_area, buf);
+        Paragraph::new(help).render(help_area, buf);
+    }
+}
@@ -0,0 +1,143 @@
+use std::{fs, io::ErrorKind};
+
+use serde_json::{Map, Value};
+
+use crate::app::http::Request;
+
+// Where environments are loaded from and saved to, relative to the working
+// directory. The file maps each environment name to its variables:
+//   {"dev": {"host": "localhost:8080"}, "prod": {"host": "api.example.com"}}
+pub const ENVIRONMENTS_FILE: &str = "environments.json";
+
+pub struct Environment {
+    pub name: String,
+    pub variables: Vec<(String, String)>,
+}
+
+// The environments and which one is substituted into requests
+pub struct Environments {
+    list: Vec<Environment>,
+    active: usize,
+}
+
+impl Environments {
+    // A missing file gives a single empty "default" environment
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Environments::default()),
+            Err(err) => return Err(format!("Unable to read \"{}\": {}", path, err)),
+        };
+        let invalid = |reason: &str| format!("\"{}\" is not a valid environments file: {}", path, reason);
+
+        let root: Value = serde_json::from_str(&text).map_err(|err| invalid(&err.to_string()))?;
+        let Value::Object(environments) = root else {
+            return Err(invalid("expected an object of environments"));
+        };
+        let mut list = Vec::new();
+        for (name, variables) in environments {
+            let Value::Object(variables) = variables else {
+                return Err(invalid(&format!("\"{}\" is not an object of variables", name)));
+            };
+            let variables = variables
+                .into_iter()
+                .map(|(key, value)| match value {
+                    Value::String(text) => Ok((key, text)),
+                    _ => Err(invalid(&format!("\"{}.{}\" is not a string", name, key))),
+                })
+                .collect::<Result<_, _>>()?;
+            list.push(Environment { name, variables });
+        }
+        if list.is_empty() {
+            return Ok(Environments::default());
+        }
+        Ok(Environments { list, active: 0 })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let root: Map<String, Value> = self
+            .list
+            .iter()
+            .map(|environment| {
+                let variables = environment
+                    .variables
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+                    .collect();
+                (environment.name.clone(), Value::Object(variables))
+            })
+            .collect();
+        let text = serde_json::to_string_pretty(&root).map_err(|err| err.to_string())?;
+        fs::write(path, text + "\n").map_err(|err| format!("Unable to write \"{}\": {}", path, err))
+    }
+
+    pub fn active(&self) -> &Environment {
+        &self.list[self.active]
+    }
+
+    pub fn set_variables(&mut self, variables: Vec<(String, String)>) {
+        self.list[self.active].variables = variables;
+    }
+
+    // Makes the next environment active, wrapping around
+    pub fn next(&mut self) {
+        self.active = (self.active + 1) % self.list.len();
+    }
+
+    // The request with every {{name}} in its URL, headers and body replaced by
+    // the active environment's value
+    pub fn apply(&self, request: &Request) -> Result<Request, String> {
+        let mut headers = Vec::new();
+        for (name, value) in &request.headers {
+            headers.push((self.substitute(name)?, self.substitute(value)?));
+        }
+        Ok(Request {
+            method: request.method.clone(),
+            url: self.substitute(&request.url)?,
+            headers,
+            body: self.substitute(&request.body)?,
+        })
+    }
+
+    fn substitute(&self, text: &str) -> Result<String, String> {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| format!("Unterminated \"{{{{\" in \"{}\"", text))?;
+            let name = rest[start + 2..start + end].trim();
+            let environment = self.active();
+            let (_, value) = environment
+                .variables
+                .iter()
+                .find(|(key, _)| key == name)
+                .ok_or_else(|| format!("Variable \"{}\" is not set in environment \"{}\"", name, environment.name))?;
+            out.push_str(&rest[..start]);
+            out.push_str(value);
+            rest = &rest[start + end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+impl Default for Environments {
+    fn default() -> Self {
+        Environments {
+            list: vec![Environment {
+                name: "default".to_string(),
+                variables: Vec::new(),
+            }],
+            active: 0,
+        }
+    }
+}
+
+// Validator for the variables editor
+pub fn check_variable_name(name: &str) -> Result<(), String> {
+    if name.contains(['{', '}']) || name.chars().any(char::is_whitespace) {
+        return Err(format!("\"{}\" is not a valid variable name", name));
+    }
+    Ok(())
+}
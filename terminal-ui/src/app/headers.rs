@@ -0,0 +1,107 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    prelude::Stylize,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Paragraph, Widget},
+};
+
+// At most this many headers are shown at once; the rest scroll
+const MAX_ROWS: usize = 8;
+// Headers that most often explain a response, shown in bold
+const IMPORTANT: [&str; 5] = ["content-type", "content-length", "location", "set-cookie", "www-authenticate"];
+
+// The response headers, in a pane of their own above the body. Folded it is
+// just its title; unfolded it lists the headers, scrolling on its own to keep
+// the selected one (the one copied) in view.
+#[derive(Default)]
+pub struct HeadersPane {
+    rows: Vec<(String, String)>,
+    folded: bool,
+    selected: usize,
+}
+
+impl HeadersPane {
+    // A pane for another response, folded or not like the last one
+    pub fn new(rows: Vec<(String, String)>, folded: bool) -> Self {
+        HeadersPane {
+            rows,
+            folded,
+            selected: 0,
+        }
+    }
+
+    pub fn is_folded(&self) -> bool {
+        self.folded
+    }
+
+    pub fn toggle(&mut self) {
+        self.folded = !self.folded;
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1));
+    }
+
+    // The selected header as "Name: value", while unfolded
+    pub fn selected(&self) -> Option<String> {
+        let (name, value) = self.rows.get(self.selected).filter(|_| !self.folded)?;
+        Some(format!("{}: {}", name, value))
+    }
+
+    // Rows needed with the borders: only the top one, with the title, when folded
+    pub fn height(&self) -> u16 {
+        match self.folded {
+            true => 1,
+            false => self.rows.len().clamp(1, MAX_ROWS) as u16 + 2,
+        }
+    }
+
+    // While the pane has the keys, `focus` is its accent color and a hint
+    // naming them
+    pub fn render(&self, area: Rect, buf: &mut Buffer, title: Line<'static>, focus: Option<(Color, String)>) {
+        let mut block = Block::bordered().title(title);
+        match &focus {
+            // Folded, the top border is the bottom one too
+            Some((accent, _)) if self.folded => block = block.border_style(*accent),
+            Some((accent, hint)) => block = block.border_style(*accent).title_bottom(hint.clone().dark_gray()),
+            None => {}
+        }
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if self.folded {
+            return;
+        }
+        if self.rows.is_empty() {
+            Paragraph::new("(no headers)".dark_gray()).render(inner, buf);
+            return;
+        }
+
+        let height = inner.height as usize;
+        let offset = (self.selected + 1).saturating_sub(height);
+        let lines: Vec<Line> = self.rows[offset..]
+            .iter()
+            .take(height)
+            .enumerate()
+            .map(|(i, (name, value))| {
+                let name = match IMPORTANT.contains(&name.to_ascii_lowercase().as_str()) {
+                    true => format!("{}: ", name).fg(Color::Yellow).bold(),
+                    false => format!("{}: ", name).fg(Color::Cyan),
+                };
+                let line = Line::from(vec![name, value.clone().into()]);
+                // The selection only matters, and is only shown, with focus
+                if offset + i == self.selected && focus.is_some() {
+                    line.style(Style::default().bg(Color::DarkGray))
+                } else {
+                    line
+                }
+            })
+            .collect();
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
@@ -0,0 +1,167 @@
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span, Text},
+};
+
+// Bodies larger than this are shown plain; tokenizing them on every response
+// makes the UI sluggish
+const MAX_HIGHLIGHT_BYTES: usize = 256 * 1024;
+
+const KEY: Style = Style::new().fg(Color::Cyan);
+const STRING: Style = Style::new().fg(Color::Green);
+const NUMBER: Style = Style::new().fg(Color::Yellow);
+const LITERAL: Style = Style::new().fg(Color::Magenta);
+const PUNCTUATION: Style = Style::new().fg(Color::DarkGray);
+const TAG: Style = Style::new().fg(Color::LightBlue);
+const COMMENT: Style = Style::new().fg(Color::DarkGray);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Json,
+    Markup,
+    Plain,
+}
+
+// Where a markup tokenizer is when a line ends, since tags and comments can
+// span lines
+enum Markup {
+    Text,
+    Tag,
+    Comment,
+}
+
+fn syntax(content_type: Option<&str>) -> Syntax {
+    match content_type {
+        // application/json, application/problem+json, ...
+        Some(media) if media.ends_with("json") => Syntax::Json,
+        Some(media) if media.ends_with("xml") || media == "text/html" => Syntax::Markup,
+        _ => Syntax::Plain,
+    }
+}
+
+// Styles a response body according to its Content-Type
+pub fn highlight(content_type: Option<&str>, body: &str, enabled: bool) -> Text<'static> {
+    let syntax = if enabled && body.len() <= MAX_HIGHLIGHT_BYTES {
+        syntax(content_type)
+    } else {
+        Syntax::Plain
+    };
+
+    match syntax {
+        Syntax::Json => Text::from(body.lines().map(json_line).collect::<Vec<_>>()),
+        Syntax::Markup => {
+            let mut state = Markup::Text;
+            Text::from(body.lines().map(|line| markup_line(line, &mut state)).collect::<Vec<_>>())
+        }
+        Syntax::Plain => Text::raw(body.to_string()),
+    }
+}
+
+fn json_line(line: &str) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let (len, style) = match c {
+            '"' => {
+                let len = quoted_len(rest, '"');
+                let key = rest[len..].trim_start().starts_with(':');
+                (len, if key { KEY } else { STRING })
+            }
+            '-' | '0'..='9' => (
+                run_len(rest, |c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')),
+                NUMBER,
+            ),
+            c if c.is_ascii_alphabetic() => (run_len(rest, |c| c.is_ascii_alphabetic()), LITERAL),
+            '{' | '}' | '[' | ']' | ':' | ',' => (1, PUNCTUATION),
+            c => (c.len_utf8(), Style::default()),
+        };
+        push(&mut spans, &rest[..len], style);
+        rest = &rest[len..];
+    }
+    Line::from(spans)
+}
+
+fn markup_line(line: &str, state: &mut Markup) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let len = match state {
+            Markup::Comment => {
+                let len = rest.find("-->").map_or(rest.len(), |end| {
+                    *state = Markup::Text;
+                    end + 3
+                });
+                push(&mut spans, &rest[..len], COMMENT);
+                len
+            }
+            Markup::Text if rest.starts_with("<!--") => {
+                *state = Markup::Comment;
+                0
+            }
+            Markup::Text if c == '<' => {
+                // "<", "</", "<?" or "<!" and then the tag name
+                let open = 1 + run_len(&rest[1..], |c| matches!(c, '/' | '?' | '!'));
+                let name = run_len(&rest[open..], |c| !c.is_whitespace() && !matches!(c, '>' | '/'));
+                push(&mut spans, &rest[..open], PUNCTUATION);
+                push(&mut spans, &rest[open..open + name], TAG);
+                *state = Markup::Tag;
+                open + name
+            }
+            Markup::Text => {
+                let len = rest.find('<').unwrap_or(rest.len());
+                push(&mut spans, &rest[..len], Style::default());
+                len
+            }
+            Markup::Tag => {
+                let (len, style) = match c {
+                    '>' => {
+                        *state = Markup::Text;
+                        (1, PUNCTUATION)
+                    }
+                    '/' | '?' | '=' => (1, PUNCTUATION),
+                    '"' | '\'' => (quoted_len(rest, c), STRING),
+                    c if c.is_whitespace() => (run_len(rest, char::is_whitespace), Style::default()),
+                    _ => (
+                        run_len(rest, |c| !c.is_whitespace() && !matches!(c, '=' | '>' | '/')),
+                        KEY,
+                    ),
+                };
+                push(&mut spans, &rest[..len], style);
+                len
+            }
+        };
+        rest = &rest[len..];
+    }
+    Line::from(spans)
+}
+
+// Length of the quoted string at the start of `text` including both quotes,
+// or the rest of the line when it isn't terminated
+fn quoted_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return i + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+// Length of the leading run of characters matching `accept`
+fn run_len(text: &str, accept: impl Fn(char) -> bool) -> usize {
+    text.find(|c: char| !accept(c)).unwrap_or(text.len())
+}
+
+// Appends `text`, merging it into the previous span when the style matches
+fn push(spans: &mut Vec<Span<'static>>, text: &str, style: Style) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.content.to_mut().push_str(text),
+        _ => spans.push(Span::styled(text.to_string(), style)),
+    }
+}
@@ -0,0 +1,202 @@
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+use crate::app::settings::Settings;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub body: String,
+}
+
+impl Request {
+    pub fn new(url: String) -> Self {
+        Request {
+            method: "GET".to_string(),
+            url,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub reason: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub elapsed: Duration,
+    pub timing: Timing,
+}
+
+// Where the time of a request went. reqwest doesn't report connecting and the
+// TLS handshake on their own, so those are unavailable and counted in ttfb;
+// DNS is unavailable when the host is an IP address.
+#[derive(Clone, Copy, Default)]
+pub struct Timing {
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub tls: Option<Duration>,
+    // From sending until the response head arrived, less the phases above
+    pub ttfb: Duration,
+    pub download: Duration,
+}
+
+impl Timing {
+    // Each phase in order with its name
+    pub fn phases(&self) -> [(&'static str, Option<Duration>); 5] {
+        [
+            ("DNS", self.dns),
+            ("Connect", self.connect),
+            ("TLS", self.tls),
+            ("TTFB", Some(self.ttfb)),
+            ("Download", Some(self.download)),
+        ]
+    }
+}
+
+impl Response {
+    // The media type without parameters, e.g. "application/json"
+    pub fn content_type(&self) -> Option<String> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value.split(';').next().unwrap_or("").trim().to_ascii_lowercase())
+    }
+}
+
+// Validators for the headers editor
+pub fn check_header_name(name: &str) -> Result<(), String> {
+    HeaderName::from_bytes(name.as_bytes())
+        .map(|_| ())
+        .map_err(|_| format!("\"{}\" is not a valid header name", name))
+}
+
+pub fn check_header_value(value: &str) -> Result<(), String> {
+    HeaderValue::from_str(value)
+        .map(|_| ())
+        .map_err(|_| format!("\"{}\" is not a valid header value", value))
+}
+
+// Lets the host abort a request that is still in flight
+#[derive(Default)]
+pub struct Cancel {
+    canceled: AtomicBool,
+    notify: Notify,
+}
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.canceled.store(true, Ordering::Relaxed);
+        // Stores a wakeup if send() isn't waiting yet
+        self.notify.notify_one();
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::Relaxed)
+    }
+}
+
+// Sends a request and reads the whole response; called from a worker thread.
+// Returns None once canceled: the request future is dropped on the spot,
+// which closes its connection mid-request.
+pub fn send(request: &Request, settings: &Settings, cancel: &Cancel) -> Option<Result<Response, String>> {
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(err) => return Some(Err(format!("Unable to start the request: {}", err))),
+    };
+    runtime.block_on(async {
+        tokio::select! {
+            response = perform(request, settings) => Some(response),
+            _ = cancel.notify.notified() => None,
+        }
+    })
+}
+
+// Resolves host names like reqwest does, adding up the time spent (a
+// redirect may look up another host)
+#[derive(Clone, Default)]
+struct TimedResolver {
+    spent: Arc<Mutex<Option<Duration>>>,
+}
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let spent = self.spent.clone();
+        Box::pin(async move {
+            let started = Instant::now();
+            let addrs = tokio::net::lookup_host((name.as_str(), 0)).await;
+            *spent.lock().unwrap().get_or_insert_default() += started.elapsed();
+            let addrs: Addrs = Box::new(addrs?.collect::<Vec<_>>().into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+async fn perform(request: &Request, settings: &Settings) -> Result<Response, String> {
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .map_err(|_| format!("Invalid method \"{}\"", request.method))?;
+    let redirects = if settings.follow_redirects {
+        reqwest::redirect::Policy::default()
+    } else {
+        reqwest::redirect::Policy::none()
+    };
+    let resolver = TimedResolver::default();
+    let mut client = reqwest::Client::builder()
+        .redirect(redirects)
+        .dns_resolver(Arc::new(resolver.clone()));
+    if let Some(timeout) = settings.timeout() {
+        client = client.timeout(timeout);
+    }
+    let client = client
+        .build()
+        .map_err(|err| format!("Unable to create the client: {}", err))?;
+    let mut builder = client.request(method, &request.url);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if !request.body.is_empty() {
+        builder = builder.body(request.body.clone());
+    }
+
+    let started = Instant::now();
+    let response = builder.send().await.map_err(|err| format!("Request failed: {}", err))?;
+    let head_received = started.elapsed();
+    let status = response.status();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+        .collect();
+    let body = response
+        .text()
+        .await
+        .map_err(|err| format!("Unable to read response body: {}", err))?;
+    let elapsed = started.elapsed();
+    let dns = *resolver.spent.lock().unwrap();
+
+    Ok(Response {
+        status: status.as_u16(),
+        reason: status.canonical_reason().unwrap_or("").to_string(),
+        headers,
+        body,
+        elapsed,
+        timing: Timing {
+            dns,
+            connect: None,
+            tls: None,
+            ttfb: head_received.saturating_sub(dns.unwrap_or_default()),
+            download: elapsed - head_received,
+        },
+    })
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, fs, io::ErrorKind};
+
+use crossterm::event::KeyCode;
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
+
+// Where key bindings are read from, relative to the working directory. Each
+// entry binds an action to one key or a list of them, and replaces that
+// action's default keys:
+//   quit = ["q", "Q"]
+//   next_tab = ["Right", "l"]
+pub const KEYBINDINGS_FILE: &str = "keybindings.toml";
+
+// What a global key does. Keys are mapped to an action first and the host
+// applies the action, so anything that can produce actions drives the UI.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, EnumIter, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum Action {
+    Quit,
+    NextTab,
+    PrevTab,
+    RunTask,
+    CancelAll,
+    PauseTasks,
+    Send,
+    ToggleHighlight,
+    ToggleTree,
+    ShrinkRequest,
+    GrowRequest,
+    NextEnvironment,
+    SaveEnvironments,
+    FocusMenu,
+    ResetTimes,
+    // Move and fold in the JSON tree, or in the response headers while they
+    // have focus
+    TreeUp,
+    TreeDown,
+    TreeToggle,
+    FocusHeaders,
+    CopyHeader,
+    ToggleRecording,
+    ReplayMacro,
+    SaveMacro,
+    ConfirmYes,
+    ConfirmNo,
+}
+
+impl Action {
+    fn default_keys(self) -> Vec<KeyCode> {
+        let letter = |c: char| vec![KeyCode::Char(c), KeyCode::Char(c.to_ascii_uppercase())];
+        match self {
+            Action::Quit => letter('q'),
+            Action::NextTab => vec![KeyCode::Right],
+            Action::PrevTab => vec![KeyCode::Left],
+            Action::RunTask => letter('r'),
+            Action::CancelAll => letter('c'),
+            Action::PauseTasks => letter('p'),
+            Action::Send => letter('s'),
+            Action::ToggleHighlight => letter('h'),
+            Action::ToggleTree => letter('t'),
+            Action::ShrinkRequest => vec![KeyCode::Char('[')],
+            Action::GrowRequest => vec![KeyCode::Char(']')],
+            Action::NextEnvironment => letter('e'),
+            Action::SaveEnvironments => letter('w'),
+            Action::FocusMenu => letter('m'),
+            Action::ResetTimes => letter('x'),
+            Action::TreeUp => vec![KeyCode::Up],
+            Action::TreeDown => vec![KeyCode::Down],
+            Action::TreeToggle => vec![KeyCode::Enter, KeyCode::Char(' ')],
+            Action::FocusHeaders => vec![KeyCode::Tab],
+            Action::CopyHeader => letter('y'),
+            Action::ToggleRecording => vec![KeyCode::F(2)],
+            Action::ReplayMacro => vec![KeyCode::F(3)],
+            Action::SaveMacro => vec![KeyCode::F(4)],
+            Action::ConfirmYes => letter('y'),
+            Action::ConfirmNo => letter('n'),
+        }
+    }
+
+    // Whether the action is used by the exit confirmation rather than the
+    // main screen; the two sets may share keys
+    pub fn is_confirm(self) -> bool {
+        matches!(self, Action::ConfirmYes | Action::ConfirmNo)
+    }
+
+    // Whether a macro records the action: not the macro keys themselves, and
+    // not quitting, which would stop a replay at the exit confirmation
+    pub fn is_recordable(self) -> bool {
+        !self.is_confirm()
+            && !matches!(
+                self,
+                Action::Quit | Action::ToggleRecording | Action::ReplayMacro | Action::SaveMacro
+            )
+    }
+}
+
+pub struct KeyBindings {
+    keys: HashMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            keys: Action::iter().map(|action| (action, action.default_keys())).collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    // The defaults, with whatever the file rebinds. A missing file is fine;
+    // unknown actions, unparsable keys and keys bound to two actions are errors.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(KeyBindings::default()),
+            Err(err) => return Err(format!("Unable to read \"{}\": {}", path, err)),
+        };
+        let table: toml::Table = text
+            .parse()
+            .map_err(|err| format!("\"{}\" is not valid TOML: {}", path, err))?;
+
+        let mut bindings = KeyBindings::default();
+        for (name, value) in table {
+            let action: Action = name
+                .parse()
+                .map_err(|_| format!("{}: unknown action \"{}\"", path, name))?;
+            let names = match value {
+                toml::Value::String(key) => vec![key],
+                toml::Value::Array(keys) => keys
+                    .into_iter()
+                    .map(|key| match key {
+                        toml::Value::String(key) => Ok(key),
+                        other => Err(format!("{}: {}: expected a key name, found {}", path, name, other)),
+                    })
+                    .collect::<Result<_, _>>()?,
+                other => return Err(format!("{}: {}: expected a key name or a list, found {}", path, name, other)),
+            };
+            let keys = names
+                .iter()
+                .map(|key| parse_key(key).ok_or_else(|| format!("{}: {}: unknown key \"{}\"", path, name, key)))
+                .collect::<Result<_, _>>()?;
+            bindings.keys.insert(action, keys);
+        }
+        bindings.check_conflicts().map_err(|err| format!("{}: {}", path, err))?;
+        Ok(bindings)
+    }
+
+    fn check_conflicts(&self) -> Result<(), String> {
+        let mut seen: HashMap<(bool, KeyCode), Action> = HashMap::new();
+        // Fixed order, so the same file always reports the same conflict
+        for action in Action::iter() {
+            for key in &self.keys[&action] {
+                if let Some(other) = seen.insert((action.is_confirm(), *key), action) {
+                    return Err(format!("key \"{}\" is bound to both {} and {}", key_name(*key), other, action));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The main-screen action bound to `code`
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        self.find(code, false)
+    }
+
+    // The exit-confirmation action bound to `code`
+    pub fn confirm_action(&self, code: KeyCode) -> Option<Action> {
+        self.find(code, true)
+    }
+
+    fn find(&self, code: KeyCode, confirm: bool) -> Option<Action> {
+        Action::iter().find(|action| action.is_confirm() == confirm && self.keys[action].contains(&code))
+    }
+
+    // The keys of an action for the help line, like "<q/Q>"
+    pub fn label(&self, action: Action) -> String {
+        let names: Vec<String> = self.keys[&action].iter().map(|key| key_name(*key)).collect();
+        format!("<{}>", names.join("/"))
+    }
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let key = match name.to_ascii_lowercase().as_str() {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        function => KeyCode::F(function.strip_prefix('f')?.parse().ok().filter(|n| (1..=12).contains(n))?),
+    };
+    Some(key)
+}
+
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Left => "◄".to_string(),
+        KeyCode::Right => "►".to_string(),
+        KeyCode::Up => "▲".to_string(),
+        KeyCode::Down => "▼".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::VecDeque,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    prelude::Stylize,
+    style::Color,
+    text::Line,
+    widgets::{Paragraph, Widget},
+};
+
+// Older entries are dropped past this many
+const MAX_ENTRIES: usize = 1000;
+const PAGE: usize = 10;
+
+#[derive(Clone, Copy)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+struct Entry {
+    // Seconds since midnight, UTC
+    time: u64,
+    level: Level,
+    message: String,
+}
+
+// Timestamped events of the session, newest last
+#[derive(Default)]
+pub struct Log {
+    entries: VecDeque<Entry>,
+    // How many entries the view is scrolled up from the newest
+    scroll: usize,
+}
+
+impl Log {
+    pub fn push(&mut self, level: Level, message: impl Into<String>) {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs() % 86_400);
+        self.entries.push_back(Entry {
+            time,
+            level,
+            message: message.into(),
+        });
+        // Keep showing the same entries while scrolled back
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.entries.len() - 1);
+        }
+    }
+
+    // Scrolling and clearing, for the logs tab; returns false for other keys
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        let last = self.entries.len().saturating_sub(1);
+        match code {
+            KeyCode::Up => self.scroll = (self.scroll + 1).min(last),
+            KeyCode::Down => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageUp => self.scroll = (self.scroll + PAGE).min(last),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(PAGE),
+            KeyCode::Home => self.scroll = last,
+            KeyCode::End => self.scroll = 0,
+            KeyCode::Char('d') | KeyCode::Char('D') | KeyCode::Delete => {
+                self.entries.clear();
+                self.scroll = 0;
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Widget for &Log {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let end = self.entries.len() - self.scroll;
+        let start = end.saturating_sub(area.height as usize);
+        let lines: Vec<Line> = self
+            .entries
+            .range(start..end)
+            .map(|entry| {
+                let (label, color) = match entry.level {
+                    Level::Info => ("INFO ", Color::Gray),
+                    Level::Warn => ("WARN ", Color::Yellow),
+                    Level::Error => ("ERROR", Color::Red),
+                };
+                Line::from(vec![
+                    format!(
+                        "{:02}:{:02}:{:02} ",
+                        entry.time / 3600,
+                        entry.time / 60 % 60,
+                        entry.time % 60
+                    )
+                    .dark_gray(),
+                    format!("{} ", label).fg(color).bold(),
+                    entry.message.clone().fg(color),
+                ])
+            })
+            .collect();
+
+        if lines.is_empty() {
+            Paragraph::new("No events yet.".dark_gray()).render(area, buf);
+        } else {
+            Paragraph::new(lines).render(area, buf);
+        }
+    }
+}
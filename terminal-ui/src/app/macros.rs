@@ -0,0 +1,74 @@
+use std::{fs, io::ErrorKind};
+
+use crate::app::keys::Action;
+
+// Where a recorded macro is saved and loaded from at startup, relative to the
+// working directory:
+//   actions = ["next_tab", "send", "toggle_tree"]
+pub const MACRO_FILE: &str = "macro.toml";
+
+// A sequence of actions recorded from the keyboard, replayed on demand
+#[derive(Default)]
+pub struct Recorder {
+    actions: Vec<Action>,
+    recording: bool,
+}
+
+impl Recorder {
+    // A missing file gives an empty macro
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Recorder::default()),
+            Err(err) => return Err(format!("Unable to read \"{}\": {}", path, err)),
+        };
+        let table: toml::Table = text
+            .parse()
+            .map_err(|err| format!("\"{}\" is not valid TOML: {}", path, err))?;
+        let actions = match table.get("actions") {
+            None => Vec::new(),
+            Some(toml::Value::Array(names)) => names
+                .iter()
+                .map(|name| {
+                    name.as_str()
+                        .and_then(|name| name.parse().ok())
+                        .ok_or_else(|| format!("{}: unknown action {}", path, name))
+                })
+                .collect::<Result<_, _>>()?,
+            Some(other) => return Err(format!("{}: actions: expected a list, found {}", path, other)),
+        };
+        Ok(Recorder {
+            actions,
+            recording: false,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let names = self.actions.iter().map(|action| toml::Value::String(action.to_string())).collect();
+        let mut table = toml::Table::new();
+        table.insert("actions".to_string(), toml::Value::Array(names));
+        fs::write(path, table.to_string()).map_err(|err| format!("Unable to write \"{}\": {}", path, err))
+    }
+
+    // Starts a new recording, replacing the macro, or stops the current one
+    pub fn toggle(&mut self) {
+        if !self.recording {
+            self.actions.clear();
+        }
+        self.recording = !self.recording;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn record(&mut self, action: Action) {
+        if self.recording && action.is_recordable() {
+            self.actions.push(action);
+        }
+    }
+
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+}
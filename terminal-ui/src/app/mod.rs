@@ -1,3 +1,16 @@
 pub mod state;
+pub mod clipboard;
+pub mod collections;
+pub mod editor;
+pub mod environment;
+pub mod headers;
+pub mod highlight;
+pub mod http;
+pub mod keys;
+pub mod log;
+pub mod macros;
 pub mod render;
-pub mod tasks;
\ No newline at end of file
+pub mod settings;
+pub mod suspend;
+pub mod tasks;
+pub mod tree;
\ No newline at end of file
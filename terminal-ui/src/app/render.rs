@@ -1,5 +1,19 @@
 use crate::app::state;
-use crate::app::tasks::Task;
+use crate::app::tasks::{Task, TaskContext, TaskHandle};
+use crate::app::{
+    clipboard,
+    collections::{self, Sidebar, SidebarEvent},
+    editor::KeyValueEditor,
+    environment::{self, Environments},
+    headers::HeadersPane,
+    highlight, http,
+    keys::{Action, KeyBindings},
+    log::{Level, Log},
+    macros::{self, Recorder},
+    settings::{self, Settings, SettingsEditor},
+    suspend,
+    tree::JsonTree,
+};
 
 use crossterm::event::{KeyCode, KeyEventKind};
 use ratatui::{
@@ -11,135 +25,579 @@ use ratatui::{
     prelude::Stylize,
     style::{Color, Style},
     symbols::{self, border},
-    text::Line,
-    widgets::{Block, Gauge, Padding, Paragraph, Tabs, Widget},
+    text::{Line, Text},
+    widgets::{Block, Gauge, Padding, Paragraph, Sparkline, Tabs, Widget, Wrap},
     DefaultTerminal, Frame,
 };
 use std::{
+    collections::VecDeque,
     io, sync::{
-        atomic::{AtomicBool, Ordering}, mpsc::{channel, Receiver, Sender}, Arc
-    }, thread, time::Duration, vec
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender}, Arc
+    }, thread, time::{Duration, Instant}, vec
 };
 use strum::IntoEnumIterator;
 
+// The request pane's share of the body, in percent
+const SPLIT_STEP: u16 = 5;
+const MIN_SPLIT: u16 = 20;
+// How many response times the footer sparkline keeps
+const LATENCY_SAMPLES: usize = 60;
+// How often input is polled and, while detached, the foreground checked
+const INPUT_POLL: Duration = Duration::from_millis(100);
+// How often time-based parts of the screen may change
+const TICK: Duration = Duration::from_millis(100);
+// The sending spinner, one frame per tick
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+// The waterfall's colors for DNS, connect, TLS, TTFB and download
+const PHASE_COLORS: [Color; 5] = [Color::Cyan, Color::Yellow, Color::Magenta, Color::Green, Color::Blue];
+
 pub struct Host {
     state: state::HostState,
     tab: state::SelectedTab,
     background_progress: f64,
-    cancelation: Arc<AtomicBool>,
+    keys: KeyBindings,
+    task: Box<dyn Task>,
+    // Running background tasks and the id the next one gets
+    tasks: Vec<TaskHandle>,
+    next_task_id: usize,
     tx: Sender<state::Event>,
     rx: Receiver<state::Event>,
+    request: http::Request,
+    headers: KeyValueEditor,
+    sidebar: Sidebar,
+    // Whether keys go to the sidebar first
+    menu_focus: bool,
+    environments: Environments,
+    // Edits the active environment's variables
+    variables: KeyValueEditor,
+    // The outcome of loading or saving environments
+    notice: Option<String>,
+    // Percentage of the body width given to the request pane
+    split: u16,
+    // Set while a request is in flight, to cancel it, with when it was sent
+    sending: Option<(Arc<http::Cancel>, Instant)>,
+    // The spinner's frame while sending, advanced on each tick
+    spinner: usize,
+    response: Option<Result<http::Response, String>>,
+    // The rendered response, rebuilt only when it or the highlighting changes
+    response_text: Text<'static>,
+    highlight: bool,
+    // Set for JSON responses; shown instead of the text unless turned off
+    tree: Option<JsonTree>,
+    tree_view: bool,
+    response_headers: HeadersPane,
+    // Whether the response tab's keys go to the headers rather than the body
+    headers_focus: bool,
+    // Response times in milliseconds, oldest first
+    latencies: VecDeque<u64>,
+    log: Log,
+    recorder: Recorder,
+    settings: SettingsEditor,
+    // Stopped or running as a background job, without the terminal; shared
+    // with the key reader, which leaves the terminal alone meanwhile
+    detached: Arc<AtomicBool>,
+    // Whether the screen is out of date. Every event but a tick sets it; a
+    // tick only does while something on screen moves with time.
+    dirty: bool,
 }
 
 impl Host {
-    pub fn new() -> Self{
+    pub fn new(url: String, keys: KeyBindings, task: Box<dyn Task>) -> Self{
         let (tx, rx) = channel::<state::Event>();
+        let (environments, notice) = match Environments::load(environment::ENVIRONMENTS_FILE) {
+            Ok(environments) => (environments, None),
+            Err(err) => (Environments::default(), Some(err)),
+        };
+        let mut log = Log::default();
+        let recorder = Recorder::load(macros::MACRO_FILE).unwrap_or_else(|err| {
+            log.push(Level::Error, err);
+            Recorder::default()
+        });
+        let settings = Settings::load(settings::SETTINGS_FILE).unwrap_or_else(|err| {
+            log.push(Level::Error, err);
+            Settings::default()
+        });
         Host{
             state: state::HostState::Running,
-            tab: state::SelectedTab::Tab1,
+            tab: state::SelectedTab::Response,
             background_progress: 0_f64,
-            cancelation: Arc::new(AtomicBool::new(false)),
+            keys,
+            task,
+            tasks: Vec::new(),
+            next_task_id: 1,
             tx,
             rx,
+            request: http::Request::new(url),
+            headers: KeyValueEditor::new(Vec::new(), http::check_header_name, http::check_header_value),
+            sidebar: Sidebar::load(collections::COLLECTIONS_FILE),
+            menu_focus: false,
+            variables: variables_editor(&environments),
+            environments,
+            notice,
+            split: 40,
+            sending: None,
+            spinner: 0,
+            response: None,
+            response_text: Text::default(),
+            highlight: true,
+            tree: None,
+            tree_view: true,
+            response_headers: HeadersPane::default(),
+            headers_focus: false,
+            latencies: VecDeque::with_capacity(LATENCY_SAMPLES),
+            log,
+            recorder,
+            settings: SettingsEditor::new(settings),
+            detached: Arc::default(),
+            dirty: true,
         }
     }
 
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
         let input_tx = self.tx.clone();
+        let detached = self.detached.clone();
         thread::spawn(move || {
-            Host::handle_key_input(input_tx);
+            Host::handle_key_input(input_tx, detached);
+        });
+        suspend::listen(self.tx.clone())?;
+        let tick_tx = self.tx.clone();
+        thread::spawn(move || {
+            while tick_tx.send(state::Event::Tick).is_ok() {
+                thread::sleep(TICK);
+            }
         });
 
         while self.state != state::HostState::Completed {
-            match self.rx.recv().unwrap() {
+            let event = if self.detached.load(Ordering::Relaxed) {
+                // "fg" doesn't signal a job that is already running, so check;
+                // ticks keep arriving, so they count as a timeout
+                match self.rx.recv_timeout(INPUT_POLL) {
+                    Ok(state::Event::Tick) | Err(_) if suspend::is_foreground() => state::Event::Continue,
+                    Ok(state::Event::Tick) | Err(_) => continue,
+                    Ok(event) => event,
+                }
+            } else {
+                self.rx.recv().unwrap()
+            };
+            self.dirty |= !matches!(event, state::Event::Tick);
+            match event {
+                state::Event::KeyInput(key_event) if suspend::is_suspend_key(&key_event) => self.suspend()?,
+                state::Event::Suspend => self.suspend()?,
+                state::Event::Continue => self.reattach(terminal)?,
                 state::Event::KeyInput(key_event) => match self.state {
                     state::HostState::Completed => {}
                     state::HostState::Running => self.handle_key_event(key_event)?,
                     state::HostState::ShuttingDown => self.handle_should_exit(key_event)?,
                 },
-                state::Event::BackgroundTask(progress) => self.background_progress = progress,
+                state::Event::BackgroundTask(id, progress) => {
+                    self.background_progress = progress;
+                    self.log_progress(id, progress);
+                }
+                state::Event::Response(response) => {
+                    self.sending = None;
+                    self.show_response(response);
+                }
+                state::Event::Tick => self.tick(),
+            }
+            if self.dirty && !self.detached.load(Ordering::Relaxed) {
+                terminal.draw(|frame| self.draw(frame))?;
+                self.dirty = false;
             }
-            terminal.draw(|frame| self.draw(frame))?;
         }
         Ok(())
     }
 
+    // Returns once the process is continued; the SIGCONT that did it is
+    // handled as its own event
+    fn suspend(&mut self) -> io::Result<()> {
+        self.log.push(Level::Info, "Suspended");
+        self.detached.store(true, Ordering::Relaxed);
+        suspend::suspend()
+    }
+
+    // Takes the terminal back after SIGCONT, unless continued as a background
+    // job. Also runs after a stop the host didn't see coming (SIGSTOP).
+    fn reattach(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        if !suspend::is_foreground() {
+            self.log.push(Level::Info, "Continued in the background");
+            self.detached.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+        if self.detached.swap(false, Ordering::Relaxed) {
+            self.log.push(Level::Info, "Resumed");
+        }
+        suspend::reclaim(terminal)
+    }
+
+    // Advances the spinner and redraws it with the elapsed time while
+    // sending; otherwise nothing on screen moves with time, and ticks cost
+    // nothing
+    fn tick(&mut self) {
+        if self.sending.is_some() {
+            self.spinner = (self.spinner + 1) % SPINNER.len();
+            self.dirty = true;
+        }
+    }
+
     fn draw(&self, frame: &mut Frame) {
         frame.render_widget(self, frame.area());
     }
 
+    fn send_request(&mut self) {
+        if self.sending.is_some() || self.request.url.is_empty() {
+            return;
+        }
+        let request = match self.environments.apply(&self.request) {
+            Ok(request) => request,
+            Err(err) => return self.show_response(Err(err)),
+        };
+        self.log.push(Level::Info, format!("Sent {} {}", request.method, request.url));
+        let cancel = Arc::new(http::Cancel::default());
+        self.sending = Some((cancel.clone(), Instant::now()));
+        self.spinner = 0;
+        let (tx, settings) = (self.tx.clone(), self.settings.settings().clone());
+        thread::spawn(move || {
+            // A canceled request was already shown as canceled
+            let response = http::send(&request, &settings, &cancel).filter(|_| !cancel.is_canceled());
+            if let Some(response) = response {
+                tx.send(state::Event::Response(response)).unwrap();
+            }
+        });
+    }
+
+    // Logs a background task every 10% and when it finishes
+    fn log_progress(&mut self, id: usize, progress: f64) {
+        self.tasks.retain(|task| !task.is_finished());
+        let name = self
+            .tasks
+            .iter()
+            .find(|task| task.id == id)
+            .map_or_else(|| format!("task {}", id), |task| task.name.clone());
+        let percent = (progress * 100_f64).round() as u32;
+        if progress >= 1_f64 {
+            self.log.push(Level::Info, format!("{} completed", name));
+        } else if percent.is_multiple_of(10) {
+            self.log.push(Level::Info, format!("{} at {}%", name, percent));
+        }
+    }
+
+    fn start_task(&mut self) {
+        let ctx = TaskContext {
+            id: self.next_task_id,
+            name: format!("Background task #{}", self.next_task_id),
+            tx: self.tx.clone(),
+            control: Arc::default(),
+        };
+        self.next_task_id += 1;
+        self.log.push(Level::Info, format!("{} started", ctx.name));
+        self.tasks.push(self.task.spawn(ctx));
+    }
+
+    // Every change is written out straight away
+    fn save_settings(&mut self) {
+        match self.settings.settings().save(settings::SETTINGS_FILE) {
+            Ok(()) => self.log.push(Level::Info, format!("Saved settings to {}", settings::SETTINGS_FILE)),
+            Err(err) => self.log.push(Level::Error, err),
+        }
+    }
+
+    fn tasks_paused(&self) -> bool {
+        self.tasks.iter().any(TaskHandle::is_paused)
+    }
+
+    // Puts a saved request into the editor
+    fn load_request(&mut self, request: http::Request) {
+        self.headers = KeyValueEditor::new(request.headers.clone(), http::check_header_name, http::check_header_value);
+        self.request = request;
+    }
+
+    fn show_response(&mut self, response: Result<http::Response, String>) {
+        match &response {
+            Ok(response) if response.status >= 400 => self.log.push(
+                Level::Warn,
+                format!("Response {} {} in {} ms", response.status, response.reason, response.elapsed.as_millis()),
+            ),
+            Ok(response) => self.log.push(
+                Level::Info,
+                format!("Response {} {} in {} ms", response.status, response.reason, response.elapsed.as_millis()),
+            ),
+            Err(err) => self.log.push(Level::Error, err.clone()),
+        }
+        if let Ok(response) = &response {
+            if self.latencies.len() == LATENCY_SAMPLES {
+                self.latencies.pop_front();
+            }
+            self.latencies.push_back(response.elapsed.as_millis() as u64);
+        }
+        self.tree = match &response {
+            Ok(response) if response.content_type().is_some_and(|media| media.ends_with("json")) => {
+                JsonTree::parse(&response.body)
+            }
+            _ => None,
+        };
+        let headers = response.as_ref().map(|response| response.headers.clone()).unwrap_or_default();
+        self.response_headers = HeadersPane::new(headers, self.response_headers.is_folded());
+        self.response = Some(response);
+        self.update_response_text();
+    }
+
+    // The response headers, when they have the response tab's keys
+    fn focused_headers(&mut self) -> Option<&mut HeadersPane> {
+        let shown = matches!(self.response, Some(Ok(_)));
+        (self.tab == state::SelectedTab::Response && self.headers_focus && shown).then_some(&mut self.response_headers)
+    }
+
+    // The JSON tree, when it is what the response tab currently shows
+    fn shown_tree(&mut self) -> Option<&mut JsonTree> {
+        if self.tab == state::SelectedTab::Response && self.tree_view && !self.headers_focus {
+            self.tree.as_mut()
+        } else {
+            None
+        }
+    }
+
+    fn update_response_text(&mut self) {
+        self.response_text = match &self.response {
+            Some(Ok(response)) => response_text(response, self.highlight),
+            Some(Err(err)) => Text::from(err.clone().red()),
+            None => Text::default(),
+        };
+    }
+
     fn handle_should_exit(&mut self, key_event: crossterm::event::KeyEvent) -> io::Result<()> {
-        match key_event.kind {
-            KeyEventKind::Press => match key_event.code {
-                KeyCode::Char('y') | KeyCode::Char('Y') => {
-                    while Arc::weak_count(&self.cancelation) > 0 {
-                        if self.cancelation.load(Ordering::Relaxed) == false {
-                            self.cancelation.store(true, Ordering::Relaxed);
-                        }
-                        thread::sleep(Duration::from_millis(10));
-                    }
-                    self.state = state::HostState::Completed
+        if key_event.kind == KeyEventKind::Press {
+            if let Some(action) = self.keys.confirm_action(key_event.code) {
+                self.apply(action);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key_event: crossterm::event::KeyEvent) -> io::Result<()> {
+        if key_event.kind == KeyEventKind::Press && self.menu_focus {
+            // Keys the sidebar doesn't use fall through to the global ones
+            match self.sidebar.handle_key(key_event.code, &self.request) {
+                SidebarEvent::Ignored if key_event.code == KeyCode::Esc => self.menu_focus = false,
+                SidebarEvent::Ignored => {}
+                SidebarEvent::Handled => return Ok(()),
+                SidebarEvent::Load(request) => {
+                    self.load_request(request);
+                    return Ok(());
+                }
+                SidebarEvent::Send(request) => {
+                    self.load_request(request);
+                    self.send_request();
+                    return Ok(());
                 }
+            }
+        }
 
-                KeyCode::Char('n') | KeyCode::Char('N') => self.state = state::HostState::Running,
-                _ => {}
-            },
-            _ => {}
+        // The headers tab gets first pick, so typing into a cell doesn't
+        // trigger the global keys
+        if key_event.kind == KeyEventKind::Press
+            && self.tab == state::SelectedTab::Headers
+            && self.headers.handle_key(key_event.code)
+        {
+            self.request.headers = self.headers.rows();
+            return Ok(());
+        }
+        if key_event.kind == KeyEventKind::Press
+            && self.tab == state::SelectedTab::Logs
+            && self.log.handle_key(key_event.code)
+        {
+            return Ok(());
+        }
+        if key_event.kind == KeyEventKind::Press
+            && self.tab == state::SelectedTab::Environment
+            && self.variables.handle_key(key_event.code)
+        {
+            self.environments.set_variables(self.variables.rows());
+            return Ok(());
+        }
+        if key_event.kind == KeyEventKind::Press && self.tab == state::SelectedTab::Settings {
+            let before = self.settings.settings().clone();
+            if self.settings.handle_key(key_event.code) {
+                if *self.settings.settings() != before {
+                    self.save_settings();
+                }
+                return Ok(());
+            }
+        }
+
+        if key_event.kind == KeyEventKind::Press {
+            if let Some(action) = self.keys.action(key_event.code) {
+                self.apply(action);
+            }
         }
         Ok(())
     }
 
-    fn handle_key_event(&mut self, key_event: crossterm::event::KeyEvent) -> io::Result<()> {
-        match key_event.kind {
-            KeyEventKind::Press => match key_event.code {
-                KeyCode::Char('q') | KeyCode::Char('Q') => {
-                    self.state = state::HostState::ShuttingDown
+    // Carries out an action. The exit confirmation only takes its yes/no
+    // actions and the main screen only the others; the rest are ignored.
+    pub fn apply(&mut self, action: Action) {
+        if action.is_confirm() != (self.state == state::HostState::ShuttingDown) {
+            return;
+        }
+        self.recorder.record(action);
+
+        match action {
+            Action::Quit => {
+                self.state = state::HostState::ShuttingDown
+            }
+
+            Action::CancelAll => {
+                if let Some((cancel, _)) = self.sending.take() {
+                    cancel.cancel();
+                    self.log.push(Level::Warn, "Request canceled");
+                    self.tree = None;
+                    self.response = Some(Err("Request canceled".to_string()));
+                    self.update_response_text();
+                }
+                // Every task is told first, so they wind down together
+                self.tasks.iter().for_each(TaskHandle::cancel);
+                for task in self.tasks.drain(..) {
+                    self.log.push(Level::Warn, format!("{} canceled", task.name));
+                    task.stop();
                 }
+            }
 
-                KeyCode::Char('c') | KeyCode::Char('C') => {
-                    self.cancelation.store(true, Ordering::Relaxed);
+            Action::PauseTasks => {
+                self.tasks.retain(|task| !task.is_finished());
+                let paused = !self.tasks_paused();
+                for task in &self.tasks {
+                    task.set_paused(paused);
+                    let action = if paused { "paused" } else { "resumed" };
+                    self.log.push(Level::Info, format!("{} {}", task.name, action));
                 }
+            }
 
-                KeyCode::Char('r') | KeyCode::Char('R') => {
-                    if self.cancelation.load(Ordering::Relaxed) == true {
-                        self.cancelation.store(false, Ordering::Relaxed);
-                    }
+            Action::RunTask => self.start_task(),
+
+            Action::NextTab => {
+                let cur = self.tab as usize;
+                let next = cur.saturating_add(1);
+                self.tab = state::SelectedTab::from_repr(next)
+                    .unwrap_or(state::SelectedTab::from_repr(cur).unwrap());
+            }
+
+            Action::PrevTab => {
+                let cur = self.tab as usize;
+                let prev = cur.saturating_sub(1);
+                self.tab = state::SelectedTab::from_repr(prev)
+                    .unwrap_or(state::SelectedTab::from_repr(cur).unwrap());
+            }
+
+            Action::Send => self.send_request(),
+
+            Action::ToggleHighlight => {
+                self.highlight = !self.highlight;
+                self.update_response_text();
+            }
+
+            Action::ToggleTree => self.tree_view = !self.tree_view,
+
+            Action::FocusMenu => self.menu_focus = !self.menu_focus,
+
+            Action::ResetTimes => self.latencies.clear(),
+
+            Action::NextEnvironment => {
+                self.environments.next();
+                self.variables = variables_editor(&self.environments);
+            }
+
+            Action::SaveEnvironments => {
+                self.notice = Some(match self.environments.save(environment::ENVIRONMENTS_FILE) {
+                    Ok(()) => format!("Saved to {}", environment::ENVIRONMENTS_FILE),
+                    Err(err) => err,
+                });
+            }
 
-                    let (background_tx, cancellation_token) =
-                        (self.tx.clone(), Arc::downgrade(&self.cancelation));
+            Action::ShrinkRequest => self.split = self.split.saturating_sub(SPLIT_STEP).max(MIN_SPLIT),
+            Action::GrowRequest => self.split = (self.split + SPLIT_STEP).min(100 - MIN_SPLIT),
 
-                    thread::spawn(move || {
-                        Host::background_task(background_tx, cancellation_token);
-                    });
+            Action::TreeUp => {
+                if let Some(headers) = self.focused_headers() {
+                    headers.up();
+                } else if let Some(tree) = self.shown_tree() {
+                    tree.up();
                 }
+            }
+
+            Action::TreeDown => {
+                if let Some(headers) = self.focused_headers() {
+                    headers.down();
+                } else if let Some(tree) = self.shown_tree() {
+                    tree.down();
+                }
+            }
+
+            Action::TreeToggle => {
+                if let Some(headers) = self.focused_headers() {
+                    headers.toggle();
+                } else if let Some(tree) = self.shown_tree() {
+                    tree.toggle();
+                }
+            }
+
+            Action::FocusHeaders => self.headers_focus = !self.headers_focus,
 
-                KeyCode::Right => {
-                    let cur = self.tab as usize;
-                    let next = cur.saturating_add(1);
-                    self.tab = state::SelectedTab::from_repr(next)
-                        .unwrap_or(state::SelectedTab::from_repr(cur).unwrap());
+            Action::CopyHeader => {
+                if let Some(header) = self.focused_headers().and_then(|headers| headers.selected()) {
+                    match clipboard::copy(&header) {
+                        Ok(()) => self.log.push(Level::Info, format!("Copied \"{}\"", header)),
+                        Err(err) => self.log.push(Level::Error, format!("Unable to copy: {}", err)),
+                    }
+                }
+            }
+
+            Action::ToggleRecording => {
+                self.recorder.toggle();
+                if self.recorder.is_recording() {
+                    self.log.push(Level::Info, "Recording a macro");
+                } else {
+                    self.log.push(Level::Info, format!("Recorded {} actions", self.recorder.actions().len()));
                 }
+            }
 
-                KeyCode::Left => {
-                    let cur = self.tab as usize;
-                    let prev = cur.saturating_sub(1);
-                    self.tab = state::SelectedTab::from_repr(prev)
-                        .unwrap_or(state::SelectedTab::from_repr(cur).unwrap());
+            // A replay while recording would record itself
+            Action::ReplayMacro if self.recorder.is_recording() => {}
+            Action::ReplayMacro => {
+                let actions = self.recorder.actions().to_vec();
+                self.log.push(Level::Info, format!("Replaying {} actions", actions.len()));
+                for action in actions {
+                    self.apply(action);
                 }
-                _ => {}
+            }
+
+            Action::SaveMacro => match self.recorder.save(macros::MACRO_FILE) {
+                Ok(()) => self.log.push(Level::Info, format!("Saved the macro to {}", macros::MACRO_FILE)),
+                Err(err) => self.log.push(Level::Error, err),
             },
-            _ => {}
+
+            Action::ConfirmYes => {
+                self.tasks.iter().for_each(TaskHandle::cancel);
+                self.tasks.drain(..).for_each(TaskHandle::stop);
+                self.state = state::HostState::Completed
+            }
+
+            Action::ConfirmNo => self.state = state::HostState::Running,
         }
-        Ok(())
     }
 
-    fn handle_key_input(tx: Sender<state::Event>) {
+    fn handle_key_input(tx: Sender<state::Event>, detached: Arc<AtomicBool>) {
         loop {
-            match crossterm::event::read().unwrap() {
-                crossterm::event::Event::Key(key_event) => {
-                    tx.send(state::Event::KeyInput(key_event)).unwrap()
-                }
-                _ => {}
+            // A background job reading the terminal would be stopped by
+            // SIGTTIN, so polls are short and skipped while detached
+            if detached.load(Ordering::Relaxed) {
+                thread::sleep(INPUT_POLL);
+                continue;
+            }
+            if !crossterm::event::poll(INPUT_POLL).unwrap() {
+                continue;
+            }
+            if let crossterm::event::Event::Key(key_event) = crossterm::event::read().unwrap() {
+                tx.send(state::Event::KeyInput(key_event)).unwrap()
             }
         }
     }
@@ -151,12 +609,17 @@ impl Widget for &Host {
         Self: Sized,
     {
         let (menu_area, body_area, footer_area) = get_layout_areas(area);
-        render_menu(menu_area, buf);
-        render_body(body_area, buf, self.tab);
-        render_footer(footer_area, buf, self.background_progress);
+        render_menu(menu_area, buf, &self.sidebar, self.menu_focus);
+        render_body(body_area, buf, self);
+        render_footer(footer_area, buf, self);
 
         if self.state == state::HostState::ShuttingDown {
-            render_confirm_message(body_area, buf, "Exit?", "Are you sure you want to exit?");
+            let hint = format!(
+                " {}/{} ",
+                self.keys.label(Action::ConfirmYes),
+                self.keys.label(Action::ConfirmNo)
+            );
+            render_confirm_message(body_area, buf, "Exit?", "Are you sure you want to exit?", &hint);
         }
     }
 }
@@ -170,71 +633,108 @@ fn get_layout_areas(area: ratatui::prelude::Rect) -> (Rect, Rect, Rect) {
     (menu_area, body_area, footer_area)
 }
 
-fn render_menu(area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
+fn render_menu(area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer, sidebar: &Sidebar, focused: bool) {
     let menu_block = Block::bordered()
-        .title(" menu ")
+        .title(" collections ")
         .title_alignment(Alignment::Center)
-        .border_set(border::THICK);
+        .border_set(border::THICK)
+        .border_style(if focused { Color::LightBlue } else { Color::default() });
 
+    let inner = menu_block.inner(area);
     menu_block.render(area, buf);
+    sidebar.render(inner, buf);
 }
 
-fn render_body(
-    area: ratatui::prelude::Rect,
-    buf: &mut ratatui::prelude::Buffer,
-    tab: state::SelectedTab,
-) {
+fn render_body(area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer, host: &Host) {
     let body_block = Block::bordered()
         .title(" TUI Web Client ")
         .title_alignment(Alignment::Center)
         .border_set(border::THICK);
 
-    let tab_area = body_block.inner(area);
-    render_tabs(tab_area, buf, tab);
+    let [request_area, tab_area] =
+        Layout::horizontal(Constraint::from_percentages([host.split, 100 - host.split])).areas(body_block.inner(area));
+    render_request(request_area, buf, &host.request);
+    render_tabs(tab_area, buf, host);
 
     body_block.render(area, buf);
 }
 
-fn render_footer(area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer, progress: f64) {
-    let instructions = Line::from(vec![
-        " Quit:".into(),
-        "<q/Q> ".green().bold(),
-        " Change Tab:".into(),
-        " ◄ ► ".green().bold(),
-        " Run:".into(),
-        " <r/R> ".green().bold(),
-        " Cancel(All):".into(),
-        " <c/C> ".green().bold(),
-    ])
+fn render_footer(area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer, host: &Host) {
+    let keys = &host.keys;
+    let help = [
+        (" Quit:", vec![Action::Quit]),
+        (" Change Tab:", vec![Action::PrevTab, Action::NextTab]),
+        (" Run:", vec![Action::RunTask]),
+        (" Cancel(All):", vec![Action::CancelAll]),
+        (" Pause:", vec![Action::PauseTasks]),
+        (" Send:", vec![Action::Send]),
+        (" Highlight:", vec![Action::ToggleHighlight]),
+        (" Tree/Text:", vec![Action::ToggleTree]),
+        (" Headers:", vec![Action::FocusHeaders]),
+        (" Resize:", vec![Action::ShrinkRequest, Action::GrowRequest]),
+        (" Environment:", vec![Action::NextEnvironment]),
+        (" Save Env:", vec![Action::SaveEnvironments]),
+        (" Collections:", vec![Action::FocusMenu]),
+        (" Reset Times:", vec![Action::ResetTimes]),
+        (" Record:", vec![Action::ToggleRecording]),
+        (" Replay:", vec![Action::ReplayMacro]),
+        (" Save Macro:", vec![Action::SaveMacro]),
+    ];
+    let instructions = Line::from(
+        help.into_iter()
+            .flat_map(|(label, actions)| {
+                let keys: Vec<String> = actions.into_iter().map(|action| keys.label(action)).collect();
+                [label.into(), format!(" {} ", keys.join(" ")).green().bold()]
+            })
+            .collect::<Vec<_>>(),
+    )
     .centered();
 
-    let footer_block = Block::bordered()
-        .title(" Background Processes ")
+    let mut footer_block = Block::bordered().title(" Background Processes ");
+    if host.recorder.is_recording() {
+        let recording = format!(" ● recording ({}) ", host.recorder.actions().len());
+        footer_block = footer_block.title(Line::from(recording.red().bold()).centered());
+    }
+    let footer_block = footer_block
+        .title(Line::from(format!(" Environment: {} ", host.environments.active().name)).right_aligned())
         .title_bottom(instructions)
         .border_set(border::THICK);
 
+    let footer_area = Rect {
+        x: area.left(),
+        y: area.top(),
+        width: area.width,
+        height: area.height.min(3),
+    };
+    let [gauge_area, latency_area] =
+        Layout::horizontal(Constraint::from_percentages([60, 40])).areas(footer_block.inner(footer_area));
+    footer_block.render(footer_area, buf);
+
+    let (progress, latencies) = (host.background_progress, &host.latencies);
+    let (color, state) = if host.tasks_paused() { (Color::Yellow, " (paused)") } else { (Color::Green, "") };
     let progress_bar = Gauge::default()
-        .gauge_style(Style::default().fg(Color::Green))
-        .block(footer_block)
-        .label(format!("Back ground worker: {:.2}%", progress * 100_f64))
+        .gauge_style(Style::default().fg(color))
+        .label(format!("Back ground worker: {:.2}%{}", progress * 100_f64, state))
         .ratio(progress);
+    progress_bar.render(gauge_area, buf);
 
-    progress_bar.render(
-        Rect {
-            x: area.left(),
-            y: area.top(),
-            width: area.width,
-            height: 3,
-        },
-        buf,
-    );
+    // Newest sample on the right
+    let label = match latencies.back() {
+        Some(last) => format!(" {} ms ", last),
+        None => " no requests ".to_string(),
+    };
+    let [label_area, sparkline_area] =
+        Layout::horizontal([Length(label.len() as u16), Min(0)]).areas(latency_area);
+    // A bare string ignores the area height, which is zero on a short terminal
+    Line::from(label).render(label_area, buf);
+    Sparkline::default()
+        .data(latencies.iter().copied().collect::<Vec<_>>())
+        .style(Style::default().fg(Color::Yellow))
+        .render(sparkline_area, buf);
 }
 
-fn render_tabs(
-    area: ratatui::prelude::Rect,
-    buf: &mut ratatui::prelude::Buffer,
-    tab: state::SelectedTab,
-) {
+fn render_tabs(area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer, host: &Host) {
+    let tab = host.tab;
     //split up body area for tabs
     let vertical = Layout::vertical([Length(1), Min(0)]);
     let [header_area, inner_area] = vertical.areas(area);
@@ -243,12 +743,23 @@ fn render_tabs(
 
     "Example Tabbed Data".bold().render(title_area, buf);
 
+    // The response tab shows how the last request went: spinning while in
+    // flight, then a check mark, or a cross for an error or 4xx/5xx status
+    let outcome = match (&host.sending, &host.response) {
+        (Some(_), _) => SPINNER[host.spinner].to_string().fg(Color::Yellow),
+        (None, Some(Ok(response))) if response.status < 400 => "✓".fg(Color::Green),
+        (None, Some(_)) => "✗".fg(Color::Red),
+        (None, None) => " ".into(),
+    };
     let titles = state::SelectedTab::iter().map(|tab| {
-        format!("  {:#}  ", tab)
-            .fg(Color::Gray)
-            .bg(Color::default())
+        let title = format!("  {:#}  ", tab).fg(Color::Gray).bg(Color::default());
+        match tab {
+            state::SelectedTab::Response => Line::from(vec![title, outcome.clone(), " ".into()]),
+            _ => Line::from(title),
+        }
     });
-    let highlight_style = (Color::default(), Color::LightBlue);
+    let accent = host.settings.settings().theme.accent();
+    let highlight_style = (Color::default(), accent);
     let selected_tab_index = tab as usize;
 
     Tabs::new(titles)
@@ -261,58 +772,205 @@ fn render_tabs(
     let tab_block = Block::bordered()
         .border_set(symbols::border::PROPORTIONAL_TALL)
         .padding(Padding::horizontal(1))
-        .border_style(Color::LightBlue);
+        .border_style(accent);
 
     match tab {
-        state::SelectedTab::Tab1 => {
-            Paragraph::new("Hello World")
-                .block(tab_block)
-                .render(inner_area, buf);
+        state::SelectedTab::Response => render_response(inner_area, buf, tab_block, host),
+        state::SelectedTab::Headers => {
+            let inner = tab_block.inner(inner_area);
+            tab_block.render(inner_area, buf);
+            host.headers.render(inner, buf);
         }
-        state::SelectedTab::Tab2 => {
-            Paragraph::new("Welcome to the Ratatui tabs example!")
-                .block(tab_block)
-                .render(inner_area, buf);
+        state::SelectedTab::Environment => {
+            let title = format!(" Variables of \"{}\" ", host.environments.active().name);
+            let tab_block = match &host.notice {
+                Some(notice) => tab_block.title(title).title_bottom(format!(" {} ", notice)),
+                None => tab_block.title(title),
+            };
+            let inner = tab_block.inner(inner_area);
+            tab_block.render(inner_area, buf);
+            host.variables.render(inner, buf);
         }
-        state::SelectedTab::Tab3 => {
-            Paragraph::new("Look! I'm different than others!")
-                .block(tab_block)
-                .render(inner_area, buf);
+        state::SelectedTab::Logs => {
+            let tab_block = tab_block.title_bottom(" ↑↓ PgUp PgDn Home End scroll  <d> clear ".dark_gray());
+            let inner = tab_block.inner(inner_area);
+            tab_block.render(inner_area, buf);
+            host.log.render(inner, buf);
         }
-        state::SelectedTab::Tab4 => {
-            Paragraph::new(
-                "I know, these are some basic changes. But I think you got the main idea.",
-            )
-            .block(tab_block)
-            .render(inner_area, buf);
+        state::SelectedTab::Settings => {
+            let inner = tab_block.inner(inner_area);
+            tab_block.render(inner_area, buf);
+            host.settings.render(inner, buf);
         }
     }
 }
 
+fn render_request(area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer, request: &http::Request) {
+    let block = Block::bordered()
+        .title(" Request ")
+        .border_set(border::PLAIN)
+        .padding(Padding::horizontal(1));
+
+    let url = if request.url.is_empty() {
+        "No URL: start with tui-web-client <url>".dark_gray()
+    } else {
+        request.url.clone().into()
+    };
+    let mut lines = vec![
+        Line::from(vec![request.method.clone().green().bold(), " ".into(), url]),
+        Line::default(),
+        "Headers".bold().into(),
+    ];
+    if request.headers.is_empty() {
+        lines.push("(none)".dark_gray().into());
+    }
+    for (name, value) in &request.headers {
+        lines.push(Line::from(vec![format!("{}: ", name).fg(Color::Cyan), value.clone().into()]));
+    }
+    lines.push(Line::default());
+    lines.push("Body".bold().into());
+    if request.body.is_empty() {
+        lines.push("(empty)".dark_gray().into());
+    }
+    lines.extend(request.body.lines().map(|line| Line::from(line.to_string())));
+
+    Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .render(area, buf);
+}
+
+fn render_response(area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer, block: Block, host: &Host) {
+    let title = match &host.sending {
+        Some((_, sent)) => format!(" Sending {} {:.1} s ", SPINNER[host.spinner], sent.elapsed().as_secs_f64()),
+        None => String::new(),
+    };
+
+    let block = block.title(title);
+    let inner = block.inner(area);
+    block.render(area, buf);
+    // The last request's timing and headers go above the body
+    let Some(Ok(response)) = &host.response else {
+        Paragraph::new(host.response_text.clone())
+            .wrap(Wrap { trim: false })
+            .render(inner, buf);
+        return;
+    };
+    let headers = &host.response_headers;
+    let [waterfall_area, headers_area, body_area] =
+        Layout::vertical([Length(3), Length(headers.height()), Min(0)]).areas(inner);
+    render_waterfall(waterfall_area, buf, &response.timing);
+    let accent = host.settings.settings().theme.accent();
+    let hint = format!(
+        " {} fold  {} copy ",
+        host.keys.label(Action::TreeToggle),
+        host.keys.label(Action::CopyHeader)
+    );
+    let title = headers_title(response, headers.is_folded());
+    headers.render(headers_area, buf, title, host.headers_focus.then_some((accent, hint)));
+
+    if let (Some(tree), true) = (&host.tree, host.tree_view) {
+        tree.render(body_area, buf);
+        return;
+    }
+    Paragraph::new(host.response_text.clone())
+        .wrap(Wrap { trim: false })
+        .render(body_area, buf);
+}
+
+// The phases of a request as one bar, each segment as wide as its share of
+// the total, above a legend with their times. A phase that couldn't be timed
+// is listed as n/a and takes no room.
+fn render_waterfall(area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer, timing: &http::Timing) {
+    let phases = timing.phases();
+    let durations: Vec<Duration> = phases.iter().map(|(_, took)| took.unwrap_or_default()).collect();
+    let widths = segment_widths(&durations, area.width);
+    let bar: Vec<_> = widths
+        .into_iter()
+        .zip(PHASE_COLORS)
+        .map(|(width, color)| symbols::bar::FULL.repeat(width as usize).fg(color))
+        .collect();
+    let legend: Vec<_> = phases
+        .into_iter()
+        .zip(PHASE_COLORS)
+        .flat_map(|((name, took), color)| match took {
+            Some(took) => ["■ ".fg(color), format!("{} {} ms  ", name, took.as_millis()).into()],
+            None => ["■ ".dark_gray(), format!("{} n/a  ", name).dark_gray()],
+        })
+        .collect();
+    Paragraph::new(vec![Line::from(bar), Line::from(legend)])
+        .wrap(Wrap { trim: false })
+        .render(area, buf);
+}
+
+// Splits `width` cells between the durations in proportion. Rounding the
+// running total rather than each share keeps the widths adding up to `width`.
+fn segment_widths(durations: &[Duration], width: u16) -> Vec<u16> {
+    let total: f64 = durations.iter().map(Duration::as_secs_f64).sum();
+    if total == 0_f64 {
+        return vec![0; durations.len()];
+    }
+    let mut elapsed = 0_f64;
+    let mut edge = 0;
+    durations
+        .iter()
+        .map(|took| {
+            elapsed += took.as_secs_f64();
+            let next = (elapsed / total * f64::from(width)).round() as u16;
+            let segment = next - edge;
+            edge = next;
+            segment
+        })
+        .collect()
+}
+
+// The (highlighted) body of a response
+fn response_text(response: &http::Response, highlight: bool) -> Text<'static> {
+    let content_type = response.content_type();
+    highlight::highlight(content_type.as_deref(), &response.body, highlight)
+}
+
+// The headers pane's title: fold marker, status (in its color), time taken
+// and header count
+fn headers_title(response: &http::Response, folded: bool) -> Line<'static> {
+    let status_color = match response.status {
+        200..=299 => Color::Green,
+        300..=399 => Color::Yellow,
+        _ => Color::Red,
+    };
+    Line::from(vec![
+        if folded { " ▸ ".into() } else { " ▾ ".into() },
+        format!("{} {}", response.status, response.reason).fg(status_color).bold(),
+        format!("  {} ms  {} headers ", response.elapsed.as_millis(), response.headers.len()).into(),
+    ])
+}
+
+fn variables_editor(environments: &Environments) -> KeyValueEditor {
+    KeyValueEditor::new(
+        environments.active().variables.clone(),
+        environment::check_variable_name,
+        |_| Ok(()),
+    )
+}
+
 fn render_confirm_message(
     area: ratatui::prelude::Rect,
     buf: &mut ratatui::prelude::Buffer,
     title: &str,
     message: &str,
+    hint: &str,
 ) {
     let popup_block = Block::bordered()
         .title(title)
-        .title_bottom(Line::from(" <y>/<n> ").centered())
+        .title_bottom(Line::from(hint).centered())
         .border_set(border::DOUBLE)
         .style(Style::default().bg(Color::Blue));
 
-    let width = (message.len() + 4) as u16;
-    let height = 3;
-    let x = if (area.width / 2) - (width / 2) + area.x > 0 {
-        (area.width / 2) - (width / 2) + area.x
-    } else {
-        area.x
-    };
-    let y = if (area.height / 2) - (height / 2) + area.y > 0 {
-        (area.height / 2) - (height / 2) + area.y
-    } else {
-        area.y
-    };
+    // Centered, and clipped to the area when it is smaller than the popup
+    let width = ((message.len() + 4) as u16).min(area.width);
+    let height = 3.min(area.height);
+    let x = area.x + (area.width - width) / 2;
+    let y = area.y + (area.height - height) / 2;
 
     Paragraph::new(message).block(popup_block).render(
         Rect {
@@ -324,3 +982,362 @@ fn render_confirm_message(
         buf,
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    // A task that finishes immediately, so tests never wait on threads
+    struct NoTask;
+
+    impl Task for NoTask {
+        fn spawn(&self, ctx: TaskContext) -> TaskHandle {
+            TaskHandle::new(&ctx, thread::spawn(|| {}))
+        }
+    }
+
+    fn host() -> Host {
+        Host::new(String::new(), KeyBindings::default(), Box::new(NoTask))
+    }
+
+    // Renders the host at a fixed size and returns the screen, one line per row
+    fn screen(host: &Host, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| host.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    fn contains(screen: &[String], text: &str) -> bool {
+        screen.iter().any(|line| line.contains(text))
+    }
+
+    #[test]
+    fn renders_tab_titles() {
+        let screen = screen(&host(), 200, 30);
+        for title in ["Response", "Headers", "Environment", "Logs", "Settings"] {
+            assert!(contains(&screen, title), "missing tab {:?}", title);
+        }
+    }
+
+    #[test]
+    fn renders_footer() {
+        let screen = screen(&host(), 300, 30);
+        let footer = &screen[27..];
+        assert!(contains(footer, "Background Processes"));
+        assert!(contains(footer, "Environment: default"));
+        assert!(contains(footer, "Quit: <q/Q>"));
+        assert!(contains(footer, "Back ground worker: 0.00%"));
+    }
+
+    #[test]
+    fn renders_confirm_popup_centered() {
+        let mut host = host();
+        host.apply(Action::Quit);
+        let screen = screen(&host, 120, 30);
+
+        let row = screen
+            .iter()
+            .position(|line| line.contains("Are you sure you want to exit?"))
+            .expect("popup message");
+        assert!(screen[row - 1].contains("Exit?"));
+        assert!(screen[row + 1].contains("<y/Y>/<n/N>"));
+        // The body takes the right 80% of the screen; the popup is centered in it
+        let column = screen[row].chars().position(|c| c == 'A').unwrap();
+        assert_eq!(column, 24 + (96 - 34) / 2 + 1);
+    }
+
+    // The popup used to compute its position with u16 subtraction, which
+    // overflowed once the body was narrower than the message
+    #[test]
+    fn confirm_popup_fits_narrow_terminal() {
+        let mut host = host();
+        host.apply(Action::Quit);
+        let screen = screen(&host, 30, 10);
+        assert!(contains(&screen, "Are you"));
+    }
+
+    #[test]
+    fn tab_actions_stop_at_the_ends() {
+        let mut host = host();
+        host.apply(Action::PrevTab);
+        assert!(host.tab == state::SelectedTab::Response);
+        for _ in 0..10 {
+            host.apply(Action::NextTab);
+        }
+        assert!(host.tab == state::SelectedTab::Settings);
+    }
+
+    #[test]
+    fn confirm_actions_only_apply_while_exiting() {
+        let mut host = host();
+        host.apply(Action::ConfirmYes);
+        assert!(host.state == state::HostState::Running);
+
+        host.apply(Action::Quit);
+        assert!(host.state == state::HostState::ShuttingDown);
+        // Main-screen actions are ignored behind the popup
+        host.apply(Action::NextTab);
+        assert!(host.tab == state::SelectedTab::Response);
+
+        host.apply(Action::ConfirmNo);
+        assert!(host.state == state::HostState::Running);
+        host.apply(Action::Quit);
+        host.apply(Action::ConfirmYes);
+        assert!(host.state == state::HostState::Completed);
+    }
+
+    #[test]
+    fn split_is_clamped() {
+        let mut host = host();
+        for _ in 0..20 {
+            host.apply(Action::ShrinkRequest);
+        }
+        assert_eq!(host.split, MIN_SPLIT);
+        for _ in 0..20 {
+            host.apply(Action::GrowRequest);
+        }
+        assert_eq!(host.split, 100 - MIN_SPLIT);
+    }
+
+    #[test]
+    fn replays_recorded_actions() {
+        let mut host = host();
+        host.apply(Action::ToggleRecording);
+        host.apply(Action::NextTab);
+        host.apply(Action::GrowRequest);
+        // Quitting isn't recorded, so a replay can't stop at the popup
+        host.apply(Action::Quit);
+        host.apply(Action::ConfirmNo);
+        host.apply(Action::ToggleRecording);
+        assert_eq!(host.recorder.actions(), [Action::NextTab, Action::GrowRequest]);
+
+        host.apply(Action::PrevTab);
+        host.apply(Action::ReplayMacro);
+        assert!(host.tab == state::SelectedTab::Headers);
+        assert_eq!(host.split, 40 + 2 * SPLIT_STEP);
+        // Replaying doesn't add to the macro
+        assert_eq!(host.recorder.actions().len(), 2);
+    }
+
+    #[test]
+    fn shows_recording_indicator() {
+        let mut host = host();
+        assert!(!contains(&screen(&host, 300, 30), "recording"));
+        host.apply(Action::ToggleRecording);
+        host.apply(Action::NextTab);
+        assert!(contains(&screen(&host, 300, 30)[27..], "● recording (1)"));
+    }
+
+    // The next progress reported by a task, None if none comes within `wait`
+    fn next_progress(host: &Host, wait: Duration) -> Option<f64> {
+        match host.rx.recv_timeout(wait) {
+            Ok(state::Event::BackgroundTask(_, progress)) => Some(progress),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn paused_tasks_make_no_progress() {
+        let task = crate::ProgressTask {
+            tick: Duration::from_millis(10),
+        };
+        let mut host = Host::new(String::new(), KeyBindings::default(), Box::new(task));
+        host.apply(Action::RunTask);
+        let mut last = next_progress(&host, Duration::from_secs(1)).expect("progress");
+
+        host.apply(Action::PauseTasks);
+        assert!(host.tasks_paused());
+        // A tick already under way may still report, but nothing after it
+        thread::sleep(Duration::from_millis(50));
+        while let Some(progress) = next_progress(&host, Duration::ZERO) {
+            last = progress;
+        }
+        assert_eq!(next_progress(&host, Duration::from_millis(200)), None);
+
+        // Resuming carries on from where the task stopped
+        host.apply(Action::PauseTasks);
+        assert!(!host.tasks_paused());
+        let resumed = next_progress(&host, Duration::from_secs(1)).expect("progress after resuming");
+        assert!(resumed > last && resumed - last < 0.015, "{} after {}", resumed, last);
+
+        host.apply(Action::CancelAll);
+        assert!(host.tasks.is_empty());
+    }
+
+    #[test]
+    fn cancel_aborts_request_in_flight() {
+        use std::{io::Read, net::TcpListener, time::Instant};
+
+        // A server that accepts the connection and never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut host = host();
+        host.request.url = format!("http://{}/", listener.local_addr().unwrap());
+        host.apply(Action::Send);
+        let (mut connection, _) = listener.accept().unwrap();
+        connection.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut request = [0; 1024];
+        assert!(connection.read(&mut request).unwrap() > 0);
+
+        host.apply(Action::CancelAll);
+        let canceled = Instant::now();
+        // The client closes its end as soon as the request is dropped
+        while connection.read(&mut request).unwrap() > 0 {}
+        assert!(canceled.elapsed() < Duration::from_secs(1));
+
+        assert!(host.sending.is_none());
+        assert!(contains(&screen(&host, 200, 30), "Request canceled"));
+        assert!(host.rx.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn waterfall_segments_fill_the_width() {
+        let ms = Duration::from_millis;
+        assert_eq!(segment_widths(&[ms(10), ms(0), ms(0), ms(30), ms(60)], 50), [5, 0, 0, 15, 30]);
+        let widths = segment_widths(&[ms(1), ms(1), ms(1)], 10);
+        assert_eq!(widths.iter().sum::<u16>(), 10);
+        assert_eq!(segment_widths(&[ms(0), ms(0)], 10), [0, 0]);
+    }
+
+    #[test]
+    fn shows_waterfall_of_last_response() {
+        let mut host = host();
+        host.show_response(Ok(http::Response {
+            status: 200,
+            reason: "OK".to_string(),
+            headers: Vec::new(),
+            body: String::new(),
+            elapsed: Duration::from_millis(50),
+            timing: http::Timing {
+                dns: None,
+                connect: None,
+                tls: None,
+                ttfb: Duration::from_millis(40),
+                download: Duration::from_millis(10),
+            },
+        }));
+        let screen = screen(&host, 200, 30);
+        assert!(contains(&screen, "DNS n/a"));
+        assert!(contains(&screen, "TLS n/a"));
+        assert!(contains(&screen, "TTFB 40 ms"));
+        assert!(contains(&screen, "Download 10 ms"));
+        // The bar, above the legend, spans the pane between the tab's borders
+        let legend = screen.iter().position(|line| line.contains("TTFB 40 ms")).unwrap();
+        let cells = screen[legend - 1].matches('█').count() - 2;
+        assert!(cells > 80, "{} cells", cells);
+        assert!(contains(&screen, "200 OK"));
+    }
+
+    fn response_with_headers(count: usize) -> http::Response {
+        let mut headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+        headers.extend((1..count).map(|i| (format!("X-Header-{}", i), format!("value {}", i))));
+        http::Response {
+            status: 200,
+            reason: "OK".to_string(),
+            headers,
+            body: r#"{"a": {"b": 1}}"#.to_string(),
+            elapsed: Duration::from_millis(5),
+            timing: http::Timing::default(),
+        }
+    }
+
+    #[test]
+    fn response_headers_fold_with_focus() {
+        let mut host = host();
+        host.show_response(Ok(response_with_headers(2)));
+        let shown = screen(&host, 200, 40);
+        assert!(contains(&shown, "▾ 200 OK"));
+        assert!(contains(&shown, "Content-Type: application/json"));
+
+        // Without focus, Enter folds the JSON tree, not the headers
+        host.apply(Action::TreeToggle);
+        assert!(!host.response_headers.is_folded());
+        assert!(contains(&screen(&host, 200, 40), "{…} 1 keys"));
+
+        host.apply(Action::FocusHeaders);
+        host.apply(Action::TreeToggle);
+        let shown = screen(&host, 200, 40);
+        assert!(contains(&shown, "▸ 200 OK  5 ms  2 headers"));
+        assert!(!contains(&shown, "Content-Type:"));
+
+        // A new response keeps the headers folded
+        host.show_response(Ok(response_with_headers(3)));
+        assert!(host.response_headers.is_folded());
+    }
+
+    #[test]
+    fn response_headers_scroll_on_their_own() {
+        let mut host = host();
+        host.show_response(Ok(response_with_headers(20)));
+        host.apply(Action::FocusHeaders);
+        for _ in 0..12 {
+            host.apply(Action::TreeDown);
+        }
+        let shown = screen(&host, 200, 40);
+        assert!(contains(&shown, "X-Header-12: value 12"));
+        assert!(!contains(&shown, "Content-Type:"));
+        assert_eq!(host.response_headers.selected().as_deref(), Some("X-Header-12: value 12"));
+        // The body below stays where it was
+        assert!(contains(&shown, "a: {…} 1 keys"));
+    }
+
+    #[test]
+    fn ticks_only_redraw_while_something_moves() {
+        let mut host = host();
+        host.dirty = false;
+        host.tick();
+        assert!(!host.dirty);
+
+        let sent = Instant::now() - Duration::from_millis(1210);
+        host.sending = Some((Arc::default(), sent));
+        host.tick();
+        assert!(host.dirty);
+        host.tick();
+        // Two ticks in: the third spinner frame, and the time so far
+        let shown = screen(&host, 200, 30);
+        assert!(contains(&shown, "Sending ⠹ 1.2 s"));
+        assert!(contains(&shown, "Response  ⠹"));
+    }
+
+    #[test]
+    fn response_tab_shows_outcome() {
+        let mut host = host();
+        assert!(!contains(&screen(&host, 200, 30), "✓"));
+        host.show_response(Ok(response_with_headers(1)));
+        assert!(contains(&screen(&host, 200, 30), "Response  ✓"));
+
+        let mut failed = response_with_headers(1);
+        failed.status = 404;
+        host.show_response(Ok(failed));
+        assert!(contains(&screen(&host, 200, 30), "Response  ✗"));
+        host.show_response(Err("Request failed".to_string()));
+        assert!(contains(&screen(&host, 200, 30), "Response  ✗"));
+    }
+
+    #[test]
+    fn settings_tab_edits_values() {
+        let mut host = host();
+        host.tab = state::SelectedTab::Settings;
+        host.settings.handle_key(KeyCode::Enter);
+        assert!(!host.settings.settings().follow_redirects);
+
+        host.settings.handle_key(KeyCode::Down);
+        host.settings.handle_key(KeyCode::Enter);
+        host.settings.handle_key(KeyCode::Backspace);
+        host.settings.handle_key(KeyCode::Backspace);
+        host.settings.handle_key(KeyCode::Char('5'));
+        host.settings.handle_key(KeyCode::Enter);
+        assert_eq!(host.settings.settings().timeout_secs, 5);
+
+        host.settings.handle_key(KeyCode::Down);
+        host.settings.handle_key(KeyCode::Enter);
+        let screen = screen(&host, 200, 30);
+        let row = |name: &str, value: &str| screen.iter().any(|line| line.contains(name) && line.contains(value));
+        assert!(row("Follow redirects", "off"));
+        assert!(row("Timeout", "5 s"));
+        assert!(row("Theme", "green"));
+    }
+}
@@ -0,0 +1,203 @@
+use std::{fs, io::ErrorKind, time::Duration};
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    buffer::Buffer,
+    layout::{
+        Constraint::{self, Length, Min},
+        Layout, Rect,
+    },
+    prelude::Stylize,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Cell, Paragraph, Row, Table, Widget},
+};
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+// Where settings are loaded from and saved to, relative to the working
+// directory. Every field is optional:
+//   follow_redirects = false
+//   timeout_secs = 10
+//   theme = "green"
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+// How requests are sent and the UI looks; changes apply to the next request
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub follow_redirects: bool,
+    // 0 waits as long as it takes
+    pub timeout_secs: u64,
+    pub theme: Theme,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            follow_redirects: true,
+            timeout_secs: 30,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Settings {
+    // A missing file gives the defaults
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(err) => return Err(format!("Unable to read \"{}\": {}", path, err)),
+        };
+        toml::from_str(&text).map_err(|err| format!("\"{}\" is not a valid settings file: {}", path, err))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = toml::to_string(self).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("Unable to write \"{}\": {}", path, err))
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        (self.timeout_secs > 0).then(|| Duration::from_secs(self.timeout_secs))
+    }
+}
+
+// The accent color of the tabs
+#[derive(Clone, Copy, Default, PartialEq, Eq, Display, EnumIter, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    Blue,
+    Green,
+    Magenta,
+    Yellow,
+}
+
+impl Theme {
+    pub fn accent(self) -> Color {
+        match self {
+            Theme::Blue => Color::LightBlue,
+            Theme::Green => Color::LightGreen,
+            Theme::Magenta => Color::LightMagenta,
+            Theme::Yellow => Color::LightYellow,
+        }
+    }
+
+    fn next(self) -> Theme {
+        Theme::iter().cycle().skip_while(|theme| *theme != self).nth(1).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, EnumIter)]
+enum Field {
+    FollowRedirects,
+    Timeout,
+    Theme,
+}
+
+// The settings tab: a list of settings, each changed in place
+pub struct SettingsEditor {
+    settings: Settings,
+    selected: usize,
+    // The timeout being typed, if any
+    editing: Option<String>,
+    error: Option<String>,
+}
+
+impl SettingsEditor {
+    pub fn new(settings: Settings) -> Self {
+        SettingsEditor {
+            settings,
+            selected: 0,
+            editing: None,
+            error: None,
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    fn field(&self) -> Field {
+        Field::iter().nth(self.selected).unwrap()
+    }
+
+    // Handles a key press, returning false for keys the editor doesn't use
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        if let Some(text) = &mut self.editing {
+            match code {
+                KeyCode::Char(c) if c.is_ascii_digit() => text.push(c),
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Enter => match text.parse() {
+                    Ok(secs) => {
+                        self.settings.timeout_secs = secs;
+                        self.editing = None;
+                        self.error = None;
+                    }
+                    Err(_) => self.error = Some("Enter a number of seconds, 0 for none".to_string()),
+                },
+                KeyCode::Esc => {
+                    self.editing = None;
+                    self.error = None;
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        match code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(Field::iter().count() - 1),
+            KeyCode::Enter | KeyCode::Char(' ') => match self.field() {
+                Field::FollowRedirects => self.settings.follow_redirects = !self.settings.follow_redirects,
+                Field::Timeout => self.editing = Some(self.settings.timeout_secs.to_string()),
+                Field::Theme => self.settings.theme = self.settings.theme.next(),
+            },
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Widget for &SettingsEditor {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let settings = &self.settings;
+        let rows = Field::iter().enumerate().map(|(i, field)| {
+            let (name, value) = match field {
+                Field::FollowRedirects if settings.follow_redirects => ("Follow redirects", "on".to_string()),
+                Field::FollowRedirects => ("Follow redirects", "off".to_string()),
+                Field::Timeout if settings.timeout_secs == 0 => ("Timeout", "none".to_string()),
+                Field::Timeout => ("Timeout", format!("{} s", settings.timeout_secs)),
+                Field::Theme => ("Theme", settings.theme.to_string()),
+            };
+            let value = match (&self.editing, i == self.selected) {
+                (Some(text), true) => {
+                    Cell::from(format!("{}▏", text)).style(Style::default().fg(Color::Black).bg(Color::Yellow))
+                }
+                _ => Cell::from(value),
+            };
+            let row = Row::new([Cell::from(name), value]);
+            if i == self.selected {
+                row.bold().bg(Color::DarkGray)
+            } else {
+                row
+            }
+        });
+
+        let help = match &self.error {
+            Some(err) => Line::from(err.clone().red()),
+            None => Line::from("<▲▼> select  <Enter> change  <Esc> cancel  Saved as you go".dark_gray()),
+        };
+
+        let [table_area, help_area] = Layout::vertical([Min(0), Length(1)]).areas(area);
+        Table::new(rows, [Constraint::Percentage(35), Constraint::Percentage(65)])
+            .header(Row::new(["Setting", "Value"]).fg(settings.theme.accent()).bold())
+            .column_spacing(2)
+            .render(table_area, buf);
+        Paragraph::new(help).render(help_area, buf);
+    }
+}
@@ -1,8 +1,18 @@
 use strum::{Display, EnumIter, FromRepr};
 
+use crate::app::http;
+
 pub enum Event {
     KeyInput(crossterm::event::KeyEvent),
-    BackgroundTask(f64),
+    // A task's id and its progress from 0 to 1
+    BackgroundTask(usize, f64),
+    Response(Result<http::Response, String>),
+    // SIGTSTP: give the terminal back and stop
+    Suspend,
+    // SIGCONT: the process was continued, in the foreground or not
+    Continue,
+    // Sent every TICK, for what changes with time (the sending spinner)
+    Tick,
 }
 
 #[derive(PartialEq, Eq)]
@@ -15,12 +25,14 @@ pub enum HostState {
 #[derive(Default, Display, PartialEq, Eq, FromRepr, Clone, Copy, EnumIter)]
 pub enum SelectedTab {
     #[default]
-    #[strum(to_string = "Tab 1")]
-    Tab1,
-    #[strum(to_string = "Tab 2")]
-    Tab2,
-    #[strum(to_string = "Tab 3")]
-    Tab3,
-    #[strum(to_string = "Tab 4")]
-    Tab4,
+    #[strum(to_string = "Response")]
+    Response,
+    #[strum(to_string = "Headers")]
+    Headers,
+    #[strum(to_string = "Environment")]
+    Environment,
+    #[strum(to_string = "Logs")]
+    Logs,
+    #[strum(to_string = "Settings")]
+    Settings,
 }
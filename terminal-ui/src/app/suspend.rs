@@ -0,0 +1,63 @@
+use std::{io, sync::mpsc::Sender, thread};
+
+use crossterm::{
+    event::{KeyCode, KeyEvent, KeyModifiers},
+    execute,
+    terminal::{enable_raw_mode, EnterAlternateScreen},
+};
+use ratatui::DefaultTerminal;
+use signal_hook::{
+    consts::{SIGCONT, SIGTSTP},
+    iterator::Signals,
+};
+
+use crate::app::state;
+
+// Job control for the TUI. Raw mode turns Ctrl-Z into an ordinary key, and a
+// SIGTSTP from elsewhere would stop the process with the terminal still in
+// raw mode, so both are caught: the terminal is handed back first and then
+// the process stops itself. On SIGCONT it takes the terminal again, unless
+// it was continued in the background ("bg"), where background tasks keep
+// running and nothing is drawn until "fg".
+
+// Turns SIGTSTP and SIGCONT into host events
+pub fn listen(tx: Sender<state::Event>) -> io::Result<()> {
+    let mut signals = Signals::new([SIGTSTP, SIGCONT])?;
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            let event = if signal == SIGTSTP {
+                state::Event::Suspend
+            } else {
+                state::Event::Continue
+            };
+            if tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+pub fn is_suspend_key(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+// Restores the terminal and stops the process; returns once it is continued
+pub fn suspend() -> io::Result<()> {
+    ratatui::restore();
+    signal_hook::low_level::emulate_default_handler(SIGTSTP)
+}
+
+// Whether the process owns the terminal, i.e. isn't a background job
+pub fn is_foreground() -> bool {
+    // SAFETY: both calls only query the process group ids
+    unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() }
+}
+
+// Puts the terminal back the way ratatui::init left it. The shell may have
+// reset its modes while the process was stopped, so everything is redrawn.
+pub fn reclaim(terminal: &mut DefaultTerminal) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    terminal.clear()
+}
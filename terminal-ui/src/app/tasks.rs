@@ -1,7 +1,80 @@
-use std::sync::{atomic::AtomicBool, mpsc::Sender, Weak};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc, Weak,
+    },
+    thread::JoinHandle,
+};
 
 use crate::app::state::Event;
 
+// Flags the host sets and a running background task polls
+#[derive(Default)]
+pub struct TaskControl {
+    pub cancel: AtomicBool,
+    // While set, the task keeps running but makes no progress
+    pub paused: AtomicBool,
+}
+
+// What a task is given when it starts
+pub struct TaskContext {
+    pub id: usize,
+    pub name: String,
+    pub tx: Sender<Event>,
+    pub control: Arc<TaskControl>,
+}
+
+impl TaskContext {
+    // The task's own view of its control flags; it doesn't keep them alive
+    pub fn control(&self) -> Weak<TaskControl> {
+        Arc::downgrade(&self.control)
+    }
+}
+
+// The host's side of a running task
+pub struct TaskHandle {
+    pub id: usize,
+    pub name: String,
+    control: Arc<TaskControl>,
+    thread: JoinHandle<()>,
+}
+
+impl TaskHandle {
+    pub fn new(ctx: &TaskContext, thread: JoinHandle<()>) -> Self {
+        TaskHandle {
+            id: ctx.id,
+            name: ctx.name.clone(),
+            control: Arc::clone(&ctx.control),
+            thread,
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.control.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.control.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.control.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    // Cancels the task and waits for its thread to end
+    pub fn stop(self) {
+        self.cancel();
+        let _ = self.thread.join();
+    }
+}
+
+// A background job the host can start; each call to `spawn` runs one
+// instance on its own thread and reports through `ctx.tx`
 pub trait Task {
-    fn background_task(tx: Sender<Event>, cancelation_token: Weak<AtomicBool>);
-}
\ No newline at end of file
+    fn spawn(&self, ctx: TaskContext) -> TaskHandle;
+}
@@ -0,0 +1,164 @@
+use std::collections::HashSet;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    prelude::Stylize,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+};
+use serde_json::Value;
+
+// Long strings are cut in the tree; the raw view still shows them whole
+const MAX_VALUE_CHARS: usize = 200;
+
+// A collapsible view of a JSON document. Only the children of expanded nodes
+// are listed and only the rows in view are drawn, so large documents stay
+// cheap as long as most of them is collapsed.
+pub struct JsonTree {
+    root: Value,
+    // JSON pointers ("/items/0") of the expanded nodes; "" is the root
+    expanded: HashSet<String>,
+    rows: Vec<Row>,
+    selected: usize,
+}
+
+// One visible line of the tree
+struct Row {
+    pointer: String,
+    depth: usize,
+    key: Option<String>,
+}
+
+impl JsonTree {
+    // None when `body` isn't JSON, so the caller can fall back to text
+    pub fn parse(body: &str) -> Option<Self> {
+        let root = serde_json::from_str(body).ok()?;
+        let mut tree = JsonTree {
+            root,
+            expanded: HashSet::from([String::new()]),
+            rows: Vec::new(),
+            selected: 0,
+        };
+        tree.rebuild();
+        Some(tree)
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1).min(self.rows.len() - 1);
+    }
+
+    // Expands or collapses the selected node
+    pub fn toggle(&mut self) {
+        let pointer = &self.rows[self.selected].pointer;
+        if !is_container(self.root.pointer(pointer)) {
+            return;
+        }
+        if !self.expanded.remove(pointer) {
+            self.expanded.insert(pointer.clone());
+        }
+        self.rebuild();
+    }
+
+    fn rebuild(&mut self) {
+        self.rows.clear();
+        push_rows(&self.root, &self.expanded, &mut self.rows, String::new(), 0, None);
+        self.selected = self.selected.min(self.rows.len() - 1);
+    }
+
+    fn line(&self, row: &Row, selected: bool) -> Line<'static> {
+        let value = self.root.pointer(&row.pointer);
+        let marker = match value {
+            Some(Value::Object(_) | Value::Array(_)) if self.expanded.contains(&row.pointer) => "▾ ",
+            Some(Value::Object(_) | Value::Array(_)) => "▸ ",
+            _ => "  ",
+        };
+
+        let mut spans = vec![Span::raw(format!("{}{}", "  ".repeat(row.depth), marker))];
+        if let Some(key) = &row.key {
+            spans.push(key.clone().fg(Color::Cyan));
+            spans.push(": ".fg(Color::DarkGray));
+        }
+        spans.push(summary(value));
+
+        let line = Line::from(spans);
+        if selected {
+            line.style(Style::default().bg(Color::DarkGray))
+        } else {
+            line
+        }
+    }
+}
+
+impl Widget for &JsonTree {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // Scroll just far enough to keep the selection in view
+        let height = area.height as usize;
+        let offset = (self.selected + 1).saturating_sub(height);
+        let lines: Vec<Line> = self.rows[offset..]
+            .iter()
+            .take(height)
+            .enumerate()
+            .map(|(i, row)| self.line(row, offset + i == self.selected))
+            .collect();
+        Paragraph::new(lines).render(area, buf);
+    }
+}
+
+fn push_rows(
+    value: &Value,
+    expanded: &HashSet<String>,
+    rows: &mut Vec<Row>,
+    pointer: String,
+    depth: usize,
+    key: Option<String>,
+) {
+    let open = expanded.contains(&pointer);
+    rows.push(Row {
+        pointer: pointer.clone(),
+        depth,
+        key,
+    });
+    if !open {
+        return;
+    }
+    match value {
+        Value::Object(map) => {
+            for (name, child) in map {
+                let child_pointer = format!("{}/{}", pointer, name.replace('~', "~0").replace('/', "~1"));
+                push_rows(child, expanded, rows, child_pointer, depth + 1, Some(name.clone()));
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                let child_pointer = format!("{}/{}", pointer, index);
+                push_rows(child, expanded, rows, child_pointer, depth + 1, Some(index.to_string()));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_container(value: Option<&Value>) -> bool {
+    matches!(value, Some(Value::Object(_) | Value::Array(_)))
+}
+
+// A scalar's value, or the size of an object or array
+fn summary(value: Option<&Value>) -> Span<'static> {
+    match value {
+        Some(Value::Object(map)) => format!("{{…}} {} keys", map.len()).fg(Color::DarkGray),
+        Some(Value::Array(items)) => format!("[…] {} items", items.len()).fg(Color::DarkGray),
+        Some(Value::String(text)) if text.chars().count() > MAX_VALUE_CHARS => {
+            format!("\"{}…\"", text.chars().take(MAX_VALUE_CHARS).collect::<String>()).fg(Color::Green)
+        }
+        Some(Value::String(text)) => format!("\"{}\"", text).fg(Color::Green),
+        Some(Value::Number(number)) => number.to_string().fg(Color::Yellow),
+        Some(other) => other.to_string().fg(Color::Magenta),
+        None => Span::raw(""),
+    }
+}
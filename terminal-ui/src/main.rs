@@ -1,20 +1,20 @@
-use std::{
-    io,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        mpsc::Sender,
-        Weak,
-    },
-    thread,
-    time::Duration,
-};
+use std::{io, sync::atomic::Ordering, thread, time::Duration};
 
 mod app;
+use app::keys::{KeyBindings, KEYBINDINGS_FILE};
 use app::render::Host;
-use app::tasks::Task;
+use app::tasks::{Task, TaskContext, TaskHandle};
 
 fn main() -> io::Result<()> {
-    let mut host = Host::new();
+    let url = std::env::args().nth(1).unwrap_or_default();
+    let keys = match KeyBindings::load(KEYBINDINGS_FILE) {
+        Ok(keys) => keys,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let mut host = Host::new(url, keys, Box::new(ProgressTask { tick: PROGRESS_TICK }));
     let mut terminal = ratatui::init();
     let app_result = host.run(&mut terminal);
     ratatui::restore();
@@ -22,17 +22,38 @@ fn main() -> io::Result<()> {
     app_result
 }
 
-impl Task for Host {
-    fn background_task(tx: Sender<app::state::Event>, cancel: Weak<AtomicBool>) {
-        let mut progress = 0_f64;
-        let increment = 0.01_f64;
+// How often the demo task advances
+const PROGRESS_TICK: Duration = Duration::from_millis(500);
 
-        while !cancel.upgrade().unwrap().load(Ordering::Relaxed) && progress < 1_f64 {
-            thread::sleep(Duration::from_millis(500));
-            progress += increment;
-            progress = progress.min(1_f64);
-            tx.send(app::state::Event::BackgroundTask(progress))
-                .unwrap();
-        }
+// The demo task: advances a progress bar by 1% every tick
+struct ProgressTask {
+    tick: Duration,
+}
+
+impl Task for ProgressTask {
+    fn spawn(&self, ctx: TaskContext) -> TaskHandle {
+        let (id, tx, control, tick) = (ctx.id, ctx.tx.clone(), ctx.control(), self.tick);
+        let thread = thread::spawn(move || {
+            let mut progress = 0_f64;
+            let increment = 0.01_f64;
+
+            while progress < 1_f64 {
+                thread::sleep(tick);
+                // A host that dropped the handle has let the task go, as good as canceled
+                let Some(control) = control.upgrade().filter(|control| !control.cancel.load(Ordering::Relaxed))
+                else {
+                    break;
+                };
+                if control.paused.load(Ordering::Relaxed) {
+                    continue;
+                }
+                progress += increment;
+                progress = progress.min(1_f64);
+                if tx.send(app::state::Event::BackgroundTask(id, progress)).is_err() {
+                    break;
+                }
+            }
+        });
+        TaskHandle::new(&ctx, thread)
     }
 }
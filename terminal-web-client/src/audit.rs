@@ -0,0 +1,89 @@
+use crate::json;
+use crate::sigv4;
+use reqwest::{Method, StatusCode, Url};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// The --log-requests file: one JSON object per line (NDJSON) for every request
+// sent, each retry its own line, appended so a file can collect several runs:
+//   {"timestamp":"2026-10-16T09:30:00.125Z","method":"GET","url":"https://host/path",
+//    "status":200|null,"bytes":1234|null,"duration_ms":12.5,"error":"..."|null}
+// The timestamp is when the request was sent, in UTC, and the URL the one
+// requested, before any redirects. status and bytes (the body as received,
+// after any decoding) are null when no response came back, and error then
+// says why. A password in the URL is left out.
+pub struct RequestLog {
+    // Each line goes out in a single write under the lock, so lines from
+    // concurrent requests never interleave
+    file: Mutex<File>,
+    path: String,
+}
+
+// What one request came to, for the log
+pub struct Entry<'a> {
+    pub sent: SystemTime,
+    pub method: &'a Method,
+    pub url: &'a Url,
+    pub duration: Duration,
+    pub outcome: Result<(StatusCode, usize), &'a str>,
+}
+
+impl RequestLog {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| format!("Unable to open request log \"{}\": {}", path, err))?;
+        Ok(RequestLog {
+            file: Mutex::new(file),
+            path: path.to_string(),
+        })
+    }
+
+    // A failed write is reported but doesn't fail the request it describes
+    pub fn write(&self, entry: &Entry) {
+        let line = format!("{}\n", line(entry));
+        if let Err(err) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            eprintln!("Unable to write to request log \"{}\": {}", self.path, err);
+        }
+    }
+}
+
+fn line(entry: &Entry) -> String {
+    let mut url = entry.url.clone();
+    let _ = url.set_password(None);
+    let (status, bytes, error) = match entry.outcome {
+        Ok((status, bytes)) => (status.as_u16().to_string(), bytes.to_string(), "null".to_string()),
+        Err(err) => ("null".to_string(), "null".to_string(), json::string(err)),
+    };
+    json::object([
+        ("timestamp", json::string(&timestamp(entry.sent))),
+        ("method", json::string(entry.method.as_str())),
+        ("url", json::string(url.as_str())),
+        ("status", status),
+        ("bytes", bytes),
+        ("duration_ms", format!("{:.1}", entry.duration.as_secs_f64() * 1000_f64)),
+        ("error", error),
+    ])
+}
+
+// RFC 3339 in UTC, to the millisecond: "2026-10-16T09:30:00.125Z"
+fn timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = sigv4::civil_date(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60,
+        since.subsec_millis()
+    )
+}
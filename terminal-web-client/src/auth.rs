@@ -0,0 +1,28 @@
+use tokio::process::Command;
+
+// Runs a credential helper (e.g. `aws ... | jq -r .token`) through the shell and
+// returns its trimmed stdout as the token. Called once per invocation, so the
+// token is shared by every request of a --repeat or multi-URL run.
+pub async fn token_from_command(command: &str) -> Result<String, String> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output().await
+    } else {
+        Command::new("sh").args(["-c", command]).output().await
+    }
+    .map_err(|err| format!("Unable to run token command \"{}\": {}", command, err))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Token command \"{}\" failed ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if token.is_empty() {
+        return Err(format!("Token command \"{}\" printed no token", command));
+    }
+    Ok(token)
+}
@@ -0,0 +1,167 @@
+use crate::metrics;
+use crate::progress::ProgressMode;
+use crate::request::{self, Prepared};
+use clap::ArgMatches;
+use reqwest::{StatusCode, Url};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+// One request of a benchmark run
+struct Sample {
+    latency: Duration,
+    status: Option<StatusCode>,
+}
+
+// The `bench` subcommand: sends the request -n times with -c requests in
+// flight, then prints throughput and latency percentiles. Requests are spread
+// evenly over the URLs, and response bodies are discarded.
+//
+// Each host also has its own limit (--limit-concurrency, -c by default). A
+// host gets that many workers of its own, and every send takes one of the -c
+// global slots, so at most -c requests are in flight overall and at most
+// --limit-concurrency to any one host. A worker only holds a global slot while
+// its request is in flight, which means a slow host ties up no more than its
+// own limit and the other hosts keep the rest.
+pub async fn run(matches: &ArgMatches) {
+    let Prepared {
+        urls,
+        request,
+        mut transport,
+        ..
+    } = match request::prepare(matches).await {
+        Ok(prepared) => prepared,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(request::USAGE_ERROR);
+        }
+    };
+    if request.try_clone().is_none() {
+        eprintln!("A streamed body can't be benchmarked, use --no-chunked");
+        std::process::exit(request::USAGE_ERROR);
+    }
+
+    let count = *matches.get_one::<usize>("requests").unwrap();
+    let concurrency = (*matches.get_one::<u64>("concurrency").unwrap() as usize).min(count.max(1));
+    let per_host = matches
+        .get_one::<u64>("limit-concurrency")
+        .map_or(concurrency, |&limit| limit as usize);
+    transport.progress = ProgressMode::None;
+    transport.dedup = None;
+    // Connection reuse can't be told apart per request with requests in parallel
+    transport.verbose = false;
+    let transport = Arc::new(transport);
+    let global = Arc::new(Semaphore::new(concurrency));
+
+    // Request i goes to URL i % len, grouped by host
+    let mut hosts: BTreeMap<String, Vec<Url>> = BTreeMap::new();
+    for i in 0..count {
+        let url = &urls[i % urls.len()].url;
+        hosts.entry(host_key(url)).or_default().push(url.clone());
+    }
+
+    // Each worker keeps its own copy of the request and clones it per send
+    let started = Instant::now();
+    let mut workers = Vec::new();
+    for queue in hosts.into_values() {
+        let queue = Arc::new(queue);
+        let next = Arc::new(AtomicUsize::new(0));
+        for _ in 0..per_host.min(queue.len()) {
+            let (transport, global, queue, next) = (transport.clone(), global.clone(), queue.clone(), next.clone());
+            let template = request.try_clone().expect("checked above");
+            workers.push(tokio::spawn(async move {
+                let mut samples = Vec::new();
+                while let Some(url) = queue.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let _slot = global.acquire().await.expect("never closed");
+                    let mut attempt = template.try_clone().expect("checked above");
+                    *attempt.url_mut() = url.clone();
+                    let sent = Instant::now();
+                    let signed = match &transport.aws_sigv4 {
+                        Some(signer) => signer.sign(&mut attempt),
+                        None => Ok(()),
+                    };
+                    let status = match signed {
+                        Ok(()) => transport.send(attempt).await.ok().map(|exchange| exchange.status),
+                        Err(_) => None,
+                    };
+                    samples.push(Sample {
+                        latency: sent.elapsed(),
+                        status,
+                    });
+                }
+                samples
+            }));
+        }
+    }
+
+    let mut samples = Vec::new();
+    for worker in workers {
+        match worker.await {
+            Ok(mut results) => samples.append(&mut results),
+            Err(err) => eprintln!("Benchmark worker failed: {}", err),
+        }
+    }
+    let urls: Vec<&str> = urls.iter().map(|target| target.url.as_str()).collect();
+    print_report(&urls, concurrency, per_host, started.elapsed(), &mut samples);
+    if let Some(path) = matches.get_one::<String>("metrics-prometheus") {
+        let samples: Vec<_> = samples.iter().map(|sample| (sample.latency, sample.status)).collect();
+        metrics::write(path, &samples);
+    }
+}
+
+// Requests to the same host and port share a limit
+fn host_key(url: &Url) -> String {
+    format!(
+        "{}:{}",
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    )
+}
+
+fn print_report(urls: &[&str], concurrency: usize, per_host: usize, elapsed: Duration, samples: &mut [Sample]) {
+    if samples.is_empty() {
+        println!("No requests were sent");
+        return;
+    }
+    samples.sort_by_key(|sample| sample.latency);
+
+    let errors = samples.iter().filter(|sample| sample.status.is_none()).count();
+    let mut statuses: BTreeMap<u16, usize> = BTreeMap::new();
+    for status in samples.iter().filter_map(|sample| sample.status) {
+        *statuses.entry(status.as_u16()).or_default() += 1;
+    }
+    let total: Duration = samples.iter().map(|sample| sample.latency).sum();
+    let percentile = |p: f64| {
+        let index = ((samples.len() as f64 * p).ceil() as usize).clamp(1, samples.len()) - 1;
+        samples[index].latency
+    };
+
+    println!("URL:           {}", urls.join(", "));
+    println!("Requests:      {} ({} errors)", samples.len(), errors);
+    if per_host < concurrency {
+        println!("Concurrency:   {} ({} per host)", concurrency, per_host);
+    } else {
+        println!("Concurrency:   {}", concurrency);
+    }
+    println!("Total time:    {:.2?}", elapsed);
+    println!(
+        "Requests/sec:  {:.1}",
+        samples.len() as f64 / elapsed.as_secs_f64().max(0.001)
+    );
+    println!(
+        "Latency:       min {:.2?}  avg {:.2?}  p50 {:.2?}  p95 {:.2?}  p99 {:.2?}  max {:.2?}",
+        samples[0].latency,
+        total / samples.len() as u32,
+        percentile(0.50),
+        percentile(0.95),
+        percentile(0.99),
+        samples[samples.len() - 1].latency
+    );
+    let statuses: Vec<String> = statuses
+        .iter()
+        .map(|(status, count)| format!("{} x{}", status, count))
+        .collect();
+    println!("Status codes:  {}", statuses.join(", "));
+}
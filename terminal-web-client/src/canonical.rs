@@ -0,0 +1,72 @@
+// A canonical form for JSON bodies (--json-normalize), so responses that mean
+// the same thing print and compare the same: object keys are sorted, numbers
+// lose formatting that doesn't change their value (1.0 and 1e0 become 1), and
+// the whole document is re-indented.
+
+use serde_json::{Map, Number, Value};
+
+pub fn is_json(content_type: Option<&str>) -> bool {
+    content_type
+        .and_then(|value| value.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase())
+        .is_some_and(|mime| mime == "application/json" || mime.ends_with("+json"))
+}
+
+// None if the body isn't valid JSON
+pub fn json(body: &[u8]) -> Option<String> {
+    let value: Value = serde_json::from_slice(body).ok()?;
+    serde_json::to_string_pretty(&normalize(value)).ok()
+}
+
+fn normalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(entries.into_iter().map(|(key, value)| (key, normalize(value))).collect::<Map<_, _>>())
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(normalize).collect()),
+        Value::Number(number) => Value::Number(whole(&number).unwrap_or(number)),
+        other => other,
+    }
+}
+
+// A float with nothing after the decimal point, as an integer
+fn whole(number: &Number) -> Option<Number> {
+    let float = number.as_f64().filter(|_| number.is_f64())?;
+    if float.fract() != 0.0 || float.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    Some(Number::from(float as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_order_doesnt_matter() {
+        let a = json(br#"{"b":1,"a":{"y":[1,{"d":2,"c":3}],"x":null}}"#).unwrap();
+        let b = json(br#"{ "a": { "x": null, "y": [1, {"c": 3, "d": 2}] }, "b": 1 }"#).unwrap();
+        assert_eq!(a, b);
+        assert!(a.find("\"a\"").unwrap() < a.find("\"b\"").unwrap());
+    }
+
+    // Array order is part of the value
+    #[test]
+    fn array_order_matters() {
+        assert_ne!(json(b"[1,2]").unwrap(), json(b"[2,1]").unwrap());
+    }
+
+    #[test]
+    fn whole_floats_become_integers() {
+        assert_eq!(json(b"[1.0, 1e0, 1, 1.5, -2.0]").unwrap(), json(b"[1,1,1,1.5,-2]").unwrap());
+    }
+
+    #[test]
+    fn invalid_json_is_none() {
+        assert_eq!(json(b"{\"a\":"), None);
+        assert!(is_json(Some("application/problem+json; charset=utf-8")));
+        assert!(!is_json(Some("text/plain")) && !is_json(None));
+    }
+}
@@ -0,0 +1,693 @@
+use crate::connect;
+use crate::dns;
+use crate::expect;
+use crate::limits;
+use crate::resume;
+use crate::sigv4;
+use crate::template;
+use crate::tunnel;
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use std::ffi::OsString;
+
+const SUBCOMMANDS: [&str; 6] = ["request", "import", "bench", "serve", "run", "help"];
+
+// The command-line interface of the client
+pub fn command() -> Command {
+    Command::new("Rust cURL")
+        .version("1.0")
+        .author("rclid780 <youremail@example.com>")
+        .about("Rust equivalent of cURL")
+        .subcommand_required(true)
+        .subcommand(request_args(Command::new("request")
+            .about("Send an HTTP request (the default when no subcommand is given)")))
+        .subcommand(Command::new("import")
+            .about("Run a curl command line with this client")
+            .arg(Arg::new("curl")
+                .help("The curl command, either quoted as one argument or as separate arguments")
+                .required(true)
+                .num_args(1..)
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true))
+            .arg(Arg::new("print")
+                .help("Print the equivalent request command instead of running it")
+                .long("print")
+                .action(ArgAction::SetTrue)))
+        .subcommand(request_args(Command::new("bench")
+            .about("Send the request many times concurrently and report latency statistics"))
+            .arg(Arg::new("requests")
+                .help("Total number of requests to send")
+                .short('n')
+                .long("requests")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("100"))
+            .arg(Arg::new("concurrency")
+                .help("Number of requests in flight at once")
+                .short('c')
+                .long("concurrency")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("10"))
+            .arg(Arg::new("limit-concurrency")
+                .help("Most requests in flight to any one host, within --concurrency [default: --concurrency]")
+                .long("limit-concurrency")
+                .value_name("n")
+                .value_parser(clap::value_parser!(u64).range(1..))))
+        .subcommand(Command::new("run")
+            .about("Send a sequence of requests from a file in order, sharing cookies between them")
+            .arg(Arg::new("from-file")
+                .help("File with one JSON request per line: {\"method\", \"url\", \"headers\", \"body\"}")
+                .long("from-file")
+                .value_name("path")
+                .required(true))
+            .arg(Arg::new("continue")
+                .help("Run every step even after one fails, to see every failure, the default")
+                .long("continue")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("fail-early")
+                .help("Stop at the first failed step, for flows whose later steps depend on earlier ones")
+                .long("fail-early")
+                .conflicts_with("continue")
+                .action(ArgAction::SetTrue)))
+        .subcommand(Command::new("serve")
+            .about("Run a local server that echoes each request back as JSON")
+            .arg(Arg::new("port")
+                .help("Port to listen on (0 picks a free one)")
+                .short('p')
+                .long("port")
+                .value_parser(clap::value_parser!(u16))
+                .default_value("8080"))
+            .arg(Arg::new("bind")
+                .help("Address to listen on")
+                .long("bind")
+                .value_name("addr")
+                .value_parser(clap::value_parser!(std::net::IpAddr))
+                .default_value("127.0.0.1"))
+            .arg(Arg::new("status")
+                .help("Status code to answer every request with")
+                .long("status")
+                .value_name("code")
+                .value_parser(clap::value_parser!(u16).range(100..1000))
+                .default_value("200")))
+}
+
+// Parses the process arguments. `rustcurl <url> ...` is shorthand for
+// `rustcurl request <url> ...`, so "request" is filled in when the first
+// argument isn't a subcommand or a top-level --help/--version.
+pub fn parse() -> ArgMatches {
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    let explicit = args.get(1).and_then(|arg| arg.to_str()).is_some_and(|arg| {
+        SUBCOMMANDS.contains(&arg) || ["-h", "--help", "-V", "--version"].contains(&arg)
+    });
+    if !explicit && args.len() > 1 {
+        args.insert(1, OsString::from("request"));
+    }
+    command().get_matches_from(args)
+}
+
+// The options describing a request, shared by `request` and `bench`
+fn request_args(command: Command) -> Command {
+    command
+        .arg(Arg::new("url")
+            .help("The URL(s) to make the request to; \"{a,b}\" and \"[1-10]\" patterns expand to several URLs")
+            .required(true)
+            .num_args(1..)
+            .index(1))
+        .arg(Arg::new("globoff")
+            .help("Don't expand {a,b} and [1-10] patterns in URLs")
+            .short('g')
+            .long("globoff")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("method")
+            .help("The HTTP method (GET, POST, etc.)")
+            .required_unless_present("stdin-headers")
+            .short('X')
+            .long("method"))
+        .arg(Arg::new("headers")
+            .help("The headers to include in the request, in key:value format")
+            .long("headers")
+            .action(ArgAction::Append))
+        .arg(Arg::new("body")
+            .help("The body of the request (for POST, PUT, etc.), \"@-\" to stream it from stdin")
+            .long("body"))
+        .arg(Arg::new("body-template")
+            .help("Send the contents of a file as the body, with each ${NAME} replaced by a --var or an \
+                   environment variable")
+            .long("body-template")
+            .value_name("file")
+            .conflicts_with_all(["body", "form", "form-string", "form-json"]))
+        .arg(Arg::new("body-from-editor")
+            .help("Write the body in $VISUAL or $EDITOR first, like a git commit message; an empty body \
+                   sends nothing")
+            .long("body-from-editor")
+            .conflicts_with_all(["body", "body-template", "form", "form-string", "form-json"])
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("var")
+            .help("A variable for --body-template; takes precedence over an environment variable of the same name. \
+                   Repeatable")
+            .long("var")
+            .value_name("name=value")
+            .requires("body-template")
+            .value_parser(template::parse_var)
+            .action(ArgAction::Append))
+        .arg(Arg::new("allow-missing-vars")
+            .help("Send --body-template placeholders that have no value as they are instead of failing")
+            .long("allow-missing-vars")
+            .requires("body-template")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("no-chunked")
+            .help("Buffer a stdin body to send a Content-Length instead of chunked transfer-encoding")
+            .long("no-chunked")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("form")
+            .help("Add a multipart form field, name=value (\"@path\" uploads a file, \"<path\" sends its contents)")
+            .short('F')
+            .long("form")
+            .value_name("name=value")
+            .conflicts_with("body")
+            .action(ArgAction::Append))
+        .arg(Arg::new("form-json")
+            .help("Add multipart form fields from a JSON object, {\"name\": \"value\", \"upload\": \"@path\"}: \
+                   \"@path\" uploads a file, and an array repeats its field")
+            .long("form-json")
+            .value_name("json")
+            .conflicts_with("body")
+            .action(ArgAction::Append))
+        .arg(Arg::new("form-string")
+            .help("Add a multipart form field whose value is sent literally, even if it starts with @ or <")
+            .long("form-string")
+            .value_name("name=value")
+            .conflicts_with("body")
+            .action(ArgAction::Append))
+        .arg(Arg::new("trace-ascii")
+            .help("Write a hex+ascii dump of the request and response to a file (\"-\" for stderr)")
+            .long("trace-ascii")
+            .value_name("file"))
+        .arg(Arg::new("log-requests")
+            .help("Append a JSON line for every request sent to a file: timestamp, method, URL, status, \
+                   body bytes, duration_ms, and an error if no response came back")
+            .long("log-requests")
+            .value_name("file"))
+        .arg(Arg::new("trace-time")
+            .help("Prefix each --trace-ascii and --verbose line with a timestamp: \"wall\" for the UTC time of day \
+                   (the default), \"relative\" for milliseconds since the run started")
+            .long("trace-time")
+            .value_name("format")
+            .num_args(0..=1)
+            .default_missing_value("wall")
+            .value_parser(["wall", "relative"]))
+        .arg(Arg::new("header-out")
+            .help("Print only the value(s) of the named response header, one per line")
+            .long("header-out")
+            .value_name("name"))
+        .arg(Arg::new("dry-run")
+            .help("Print the request that would be sent and exit without sending it")
+            .long("dry-run")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("redact-header")
+            .help("Additional header name to redact in dry-run and trace output")
+            .long("redact-header")
+            .value_name("name")
+            .action(ArgAction::Append))
+        .arg(Arg::new("no-redact")
+            .help("Show sensitive header values (Authorization, Cookie, ...) unredacted")
+            .long("no-redact")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("output")
+            .help("Write the response body to a file instead of stdout. An existing file is only overwritten \
+                   after asking, or with --force")
+            .short('o')
+            .long("output")
+            .value_name("file"))
+        .arg(Arg::new("remote-name")
+            .help("Write the response body to a file named after the last segment of the URL's path, \
+                   in the current directory")
+            .short('O')
+            .long("remote-name")
+            .conflicts_with("output")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("remote-header-name")
+            .help("With -O, take the file name from the response's Content-Disposition header instead, when it \
+                   has one. Directories are stripped from it, and an existing file is never overwritten")
+            .short('J')
+            .long("remote-header-name")
+            .requires("remote-name")
+            .action(ArgAction::SetTrue))
+        .group(ArgGroup::new("save")
+            .args(["output", "remote-name"]))
+        .arg(Arg::new("force")
+            .help("Overwrite an existing -o/-O file without asking. Otherwise the terminal asks first, and \
+                   without one (in a script) an existing file is an error")
+            .long("force")
+            .requires("save")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("stdout-tee")
+            .help("Also write the body saved with -o or -O to stdout, exactly as saved and as it arrives")
+            .long("stdout-tee")
+            .requires("save")
+            .conflicts_with("json-normalize")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("append")
+            .help("Add the response body to the end of the -o file instead of overwriting it")
+            .long("append")
+            .visible_alias("output-append")
+            .requires("save")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("url-encode")
+            .help("Percent-encode characters that aren't allowed in the URL's path and query \
+                   (spaces, non-ASCII, a stray \"%\", ...). Existing %XX escapes are kept as they are")
+            .long("url-encode")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("output-error-to")
+            .help("Write the body of a 4xx/5xx response to this file instead of -o or stdout, \
+                   even without --fail-with-body (the request still fails unless --expect-status allows it)")
+            .long("output-error-to")
+            .value_name("file"))
+        .arg(Arg::new("load-cookies")
+            .help("Send cookies from this Netscape/curl cookie jar file with matching requests \
+                   (not over raw HTTP/1.1 connections)")
+            .long("load-cookies")
+            .value_name("file"))
+        .arg(Arg::new("junk-session-cookies")
+            .help("Leave out the session cookies (those without an expiry) of --load-cookies, \
+                   as if a new browser session had started")
+            .short('j')
+            .long("junk-session-cookies")
+            .visible_alias("cookie-session")
+            .requires("load-cookies")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("compressed")
+            .help("Request a compressed response (gzip, br, zstd) and decompress it")
+            .long("compressed")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("accept-encoding")
+            .help("Send exactly this Accept-Encoding list (gzip, br, identity, with optional ;q=) \
+                   and decode only those encodings")
+            .long("accept-encoding")
+            .value_name("list")
+            .value_parser(parse_accept_encoding)
+            .conflicts_with("compressed"))
+        .arg(Arg::new("compressed-output")
+            .help("With -o, save the body exactly as received without decompressing it. \
+                   Combined with --compressed the encodings are still requested, but not decoded")
+            .long("compressed-output")
+            .requires("save")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("raw")
+            .help("Write the response body exactly as the server sent it, to stdout or -o: no decompression \
+                   (overriding --compressed, whose encodings are still requested), no --pretty formatting, no \
+                   character set conversion, and on stdout no \"Response: \" label or trailing newline. \
+                   HTTP/1.1 chunked framing is still removed, as it belongs to the connection, not the body")
+            .long("raw")
+            .conflicts_with_all(["json-normalize", "output-format"])
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("interface")
+            .help("Bind outgoing connections to a local interface name or IP address")
+            .long("interface")
+            .value_name("name"))
+        .arg(Arg::new("repeat")
+            .help("Send the request this many times, reusing pooled connections")
+            .long("repeat")
+            .value_name("n")
+            .value_parser(clap::value_parser!(u32).range(1..))
+            .default_value("1"))
+        .arg(Arg::new("repeat-interval")
+            .help("With --repeat, wait this many seconds between requests, polling the URL")
+            .long("repeat-interval")
+            .value_name("secs")
+            .value_parser(clap::value_parser!(f64))
+            .requires("repeat"))
+        .arg(Arg::new("metrics-prometheus")
+            .help("After a --repeat or bench run, print request counts by status and a latency histogram \
+                   in the Prometheus text format, or write them to this file")
+            .long("metrics-prometheus")
+            .value_name("file")
+            .num_args(0..=1)
+            .default_missing_value("-"))
+        .arg(Arg::new("keepalive")
+            .help("Send TCP keep-alive probes on pooled connections after this many idle seconds, so a \
+                   connection a NAT or firewall silently dropped is noticed (default: off)")
+            .long("keepalive")
+            .visible_alias("keepalive-time")
+            .value_name("secs")
+            .value_parser(clap::value_parser!(u64)))
+        .arg(Arg::new("pool-max-idle")
+            .help("Maximum idle connections kept per host (default: unlimited)")
+            .long("pool-max-idle")
+            .value_name("n")
+            .value_parser(clap::value_parser!(usize)))
+        .arg(Arg::new("pool-idle-timeout")
+            .help("Close pooled connections that have been idle this many seconds instead of reusing them \
+                   (default: 90). Set it below the server's keep-alive timeout when polling")
+            .long("pool-idle-timeout")
+            .value_name("secs")
+            .value_parser(clap::value_parser!(f64)))
+        .arg(Arg::new("no-keepalive")
+            .help("Open a fresh connection for every request instead of reusing pooled ones. \
+                   Each request pays for a new TCP (and TLS) handshake, so this is slower")
+            .long("no-keepalive")
+            .conflicts_with_all(["keepalive", "pool-max-idle", "pool-idle-timeout"])
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("expect100-timeout")
+            .help("Send \"Expect: 100-continue\" with the body and wait up to this many seconds \
+                   for the server before sending it anyway (HTTP/1.1 only, bypasses connection pooling)")
+            .long("expect100-timeout")
+            .value_name("secs")
+            .value_parser(clap::value_parser!(f64)))
+        .arg(Arg::new("max-headers")
+            .help("Fail if the response has more headers than this")
+            .long("max-headers")
+            .value_name("n")
+            .value_parser(clap::value_parser!(usize))
+            .default_value("100"))
+        .arg(Arg::new("max-header-bytes")
+            .help("Fail if the response header section is larger than this many bytes")
+            .long("max-header-bytes")
+            .value_name("bytes")
+            .value_parser(clap::value_parser!(usize))
+            .default_value("102400"))
+        .arg(Arg::new("retry")
+            .help("Resend a request this many times when the server answers 429 Too Many Requests or \
+                   503 Service Unavailable, backing off up to 1s, 2s, 4s, ... in between")
+            .long("retry")
+            .value_name("n")
+            .value_parser(clap::value_parser!(u32))
+            .default_value("0"))
+        .arg(Arg::new("retry-after")
+            .help("When retrying, wait as long as the response's Retry-After header says \
+                   (seconds or an HTTP-date) instead of the fixed backoff")
+            .long("retry-after")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("retry-max-wait")
+            .help("Never wait longer than this many seconds before a retry, whatever Retry-After says")
+            .long("retry-max-wait")
+            .value_name("secs")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("300"))
+        .arg(Arg::new("retry-jitter")
+            .help("How to randomize the backoff between retries: none waits exactly 1s, 2s, 4s, ...; \
+                   full waits anywhere up to it; equal waits at least half of it")
+            .long("retry-jitter")
+            .value_name("strategy")
+            .value_parser(["none", "full", "equal"])
+            .default_value("full"))
+        .arg(Arg::new("continue-at")
+            .help("Resume a download from this byte offset, or \"-\" for the size of the -o file, \
+                   by sending a Range header; a partial (206) response is appended to -o")
+            .short('C')
+            .long("continue-at")
+            .value_name("offset")
+            .allow_hyphen_values(true)
+            .value_parser(resume::parse)
+            .conflicts_with_all(["compressed", "accept-encoding"]))
+        .arg(Arg::new("continue")
+            .help("With --retry, when a transfer breaks off midway, retry with a Range header for \
+                   the bytes not yet received and join the parts instead of starting over")
+            .long("continue")
+            .requires("retry")
+            .conflicts_with_all(["compressed", "accept-encoding", "no-buffer"])
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("speed-limit")
+            .help("Abort a response body that arrives slower than this many bytes per second for \
+                   --speed-time seconds (default: 30), e.g. a stalled or half-open connection")
+            .short('Y')
+            .long("speed-limit")
+            .value_name("bytes/s")
+            .value_parser(clap::value_parser!(u64).range(1..)))
+        .arg(Arg::new("speed-time")
+            .help("How long a response body may stay below --speed-limit (default: 1 byte/s) before it is aborted")
+            .short('y')
+            .long("speed-time")
+            .value_name("secs")
+            .value_parser(clap::value_parser!(f64)))
+        .arg(Arg::new("limit-rate")
+            .help("Cap how fast response bodies are received, in bytes per second (K, M and G suffixes allowed). \
+                   The cap is for all transfers together: concurrent bench requests share it")
+            .long("limit-rate")
+            .visible_alias("max-recv-speed")
+            .value_name("bytes/s")
+            .value_parser(limits::parse_rate))
+        .arg(Arg::new("request-target")
+            .help("Expert option: send this request-target on the request line instead of the URL's path \
+                   (\"/path\", \"http://host/path\" or \"*\"). Uses a raw HTTP/1.1 connection to the URL's host")
+            .long("request-target")
+            .value_name("target"))
+        .arg(Arg::new("path-as-is")
+            .help("Send the URL's path exactly as written, without resolving \"/./\" and \"/../\" segments. \
+                   A path that would change uses a raw HTTP/1.1 connection")
+            .long("path-as-is")
+            .conflicts_with("request-target")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("stdin-headers")
+            .help("Expert option: read a whole raw request (request line, headers, blank line, body) from stdin \
+                   and send it byte for byte over a raw HTTP/1.1 connection. Only the request line is checked; \
+                   the URL's scheme, host and port still decide where to connect")
+            .long("stdin-headers")
+            .conflicts_with_all([
+                "method",
+                "headers",
+                "body",
+                "body-template",
+                "body-from-editor",
+                "request-target",
+                "expect100-timeout",
+                "aws-sigv4",
+            ])
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("host-header")
+            .help("Send this Host header while still connecting to the URL's address (TLS SNI uses the URL too)")
+            .long("host-header")
+            .value_name("host[:port]"))
+        .arg(Arg::new("connect-to")
+            .help("Connect to HOST2:PORT2 whenever the URL is HOST1:PORT1, keeping the URL's Host header and TLS SNI. \
+                   Empty fields match any host/port or keep the URL's. Uses a raw HTTP/1.1 connection; repeatable")
+            .long("connect-to")
+            .value_name("HOST1:PORT1:HOST2:PORT2")
+            .value_parser(connect::parse)
+            .action(ArgAction::Append))
+        .arg(Arg::new("dns-servers")
+            .help("Resolve host names with these DNS servers instead of the system's, e.g. 1.1.1.1,8.8.8.8 \
+                   (port 53 unless given). If they can't resolve a name the system resolver is tried, with a warning")
+            .long("dns-servers")
+            .value_name("ip[:port],...")
+            .conflicts_with_all(["socks5", "socks5-hostname"])
+            .value_parser(dns::parse_servers))
+        .arg(Arg::new("doh-url")
+            .help("Resolve host names with DNS-over-HTTPS (RFC 8484) at this https:// URL, e.g. \
+                   https://cloudflare-dns.com/dns-query. A failed lookup fails the request rather than falling \
+                   back to plain DNS")
+            .long("doh-url")
+            .value_name("url")
+            .conflicts_with_all(["dns-servers", "socks5", "socks5-hostname"])
+            .value_parser(dns::parse_doh_url))
+        .arg(Arg::new("proxy-tunnel")
+            .help("Reach the host through an HTTP CONNECT tunnel opened by this proxy; TLS and the request itself \
+                   run end to end through the tunnel. Uses a raw HTTP/1.1 connection")
+            .long("proxy-tunnel")
+            .value_name("host:port")
+            .value_parser(tunnel::parse_address))
+        .arg(Arg::new("socks5")
+            .help("Send requests through this SOCKS5 proxy, resolving host names locally")
+            .long("socks5")
+            .value_name("host:port")
+            .value_parser(tunnel::parse_address))
+        .arg(Arg::new("socks5-hostname")
+            .help("Send requests through this SOCKS5 proxy and let it resolve host names (remote DNS, as Tor needs)")
+            .long("socks5-hostname")
+            .value_name("host:port")
+            .value_parser(tunnel::parse_address))
+        .group(ArgGroup::new("proxy")
+            .args(["proxy-tunnel", "socks5", "socks5-hostname"]))
+        .arg(Arg::new("proxy-user")
+            .help("Credentials for the proxy: Basic Proxy-Authorization on the CONNECT request of --proxy-tunnel, \
+                   or username/password authentication with a SOCKS5 proxy")
+            .long("proxy-user")
+            .value_name("user:password")
+            .requires("proxy")
+            .value_parser(tunnel::parse_user))
+        .arg(Arg::new("location-trusted")
+            .help("Keep sending Authorization, cookies and other credentials when a redirect goes to another \
+                   host. By default they are dropped so they can't leak to a host they weren't meant for")
+            .long("location-trusted")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("verbose")
+            .help("Print details of how the response was reached: the proxy's answer to CONNECT, each redirect \
+                   followed, whether each request opened a connection or reused one, and with --compressed or \
+                   --accept-encoding, the Content-Encoding the server chose and whether it was decoded")
+            .long("verbose")
+            .short('v')
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("dedup")
+            .help("Fetch a URL that is listed (or globbed) more than once only once and reuse its response. \
+                   Every --repeat round is still sent. Only applies to GET and HEAD, since repeating other \
+                   methods may be intended")
+            .long("dedup")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("http1.1")
+            .help("Only speak HTTP/1.1, offering nothing else over TLS")
+            .long("http1.1")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("http2")
+            .help("Only speak HTTP/2, assuming the server does (h2c over cleartext); a server that doesn't \
+                   makes the request fail rather than quietly answer in HTTP/1.1. See --http-version-fallback")
+            .long("http2")
+            .conflicts_with_all(["socks5", "socks5-hostname"])
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("http3")
+            .help("Experimental: try HTTP/3 over QUIC first and fall back to HTTP/2 or 1.1 over TCP if it fails \
+                   (see --http-version-fallback), printing the protocol used. Only in builds with the \"http3\" \
+                   Cargo feature; reqwest's QUIC support is unstable and may change or misbehave")
+            .long("http3")
+            .conflicts_with_all(["socks5", "socks5-hostname", "proxy-tunnel"])
+            .action(ArgAction::SetTrue))
+        .group(ArgGroup::new("http-version")
+            .args(["http1.1", "http2", "http3"]))
+        .arg(Arg::new("http-version-fallback")
+            .help("Whether a request the forced HTTP version fails is sent again letting the version be \
+                   negotiated, with a message saying so. Defaults to off for --http1.1 and --http2, so they \
+                   assert the version, and to on for --http3")
+            .long("http-version-fallback")
+            .value_name("on|off")
+            .value_parser(["on", "off"])
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("on")
+            .requires("http-version"))
+        .arg(Arg::new("show-protocol")
+            .help("Print the HTTP version used for each response and the ALPN protocol TLS negotiated")
+            .long("show-protocol")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("abort-on-error")
+            .help("With several URLs, stop at the first failed request instead of reporting all of them")
+            .long("abort-on-error")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("expect-status")
+            .help("Fail unless the response status is this code or range (e.g. 301, 200-299); repeatable")
+            .long("expect-status")
+            .visible_alias("assert-status")
+            .value_name("code")
+            .value_parser(expect::parse_status_range)
+            .action(ArgAction::Append))
+        .arg(Arg::new("fail-with-body")
+            .help("When the response status counts as a failure, still print or save its body before exiting non-zero")
+            .long("fail-with-body")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("expect-header")
+            .help("Fail unless a response header holds: \"name==value\" (equals), \"name~=text\" (contains) \
+                   or \"name=~regex\" (matches); repeatable. Every failed --expect-*/--assert-* is reported, \
+                   not just the first")
+            .long("expect-header")
+            .visible_alias("assert-header")
+            .value_name("assertion")
+            .value_parser(expect::parse_header_assertion)
+            .action(ArgAction::Append))
+        .arg(Arg::new("expect-body-contains")
+            .help("Fail unless the response body contains this text; repeatable")
+            .long("expect-body-contains")
+            .visible_alias("assert-body-contains")
+            .value_name("text")
+            .action(ArgAction::Append))
+        .arg(Arg::new("expect-body-matches")
+            .help("Fail unless the response body matches this regular expression; repeatable")
+            .long("expect-body-matches")
+            .value_name("regex")
+            .action(ArgAction::Append))
+        .arg(Arg::new("expect-ignore-case")
+            .help("Match --expect-header and --expect-body-contains/--expect-body-matches case-insensitively")
+            .long("expect-ignore-case")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("json-normalize")
+            .help("Sort object keys and reformat JSON response bodies before printing, saving or checking them")
+            .long("json-normalize")
+            .conflicts_with("no-buffer")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("diff-against")
+            .help("Fail and print a unified diff unless the response body matches this saved file")
+            .long("diff-against")
+            .value_name("file"))
+        .arg(Arg::new("diff-normalize")
+            .help("With --diff-against, compare JSON bodies by value, ignoring whitespace and key order")
+            .long("diff-normalize")
+            .requires("diff-against")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("progress-json")
+            .help("Report download progress as newline-delimited JSON on stderr instead of a progress bar")
+            .long("progress-json")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("compressed-request")
+            .help("Gzip the request body and send it with Content-Encoding: gzip (the server must support this)")
+            .long("compressed-request")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("oauth2-bearer")
+            .help("Send an \"Authorization: Bearer\" header with this token")
+            .long("oauth2-bearer")
+            .value_name("token"))
+        .arg(Arg::new("oauth2-bearer-cmd")
+            .help("Run this shell command once and use its output as the bearer token")
+            .long("oauth2-bearer-cmd")
+            .value_name("command")
+            .conflicts_with("oauth2-bearer"))
+        .arg(Arg::new("aws-sigv4")
+            .help("Sign requests with AWS Signature Version 4, e.g. \"aws:amz:us-east-1:s3\". Credentials come from \
+                   AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY or ~/.aws/credentials (AWS_PROFILE)")
+            .long("aws-sigv4")
+            .value_name("provider1[:provider2[:region[:service]]]")
+            .value_parser(sigv4::parse))
+        .arg(Arg::new("pre-request-cmd")
+            .help("Run this shell command before each request with the request as JSON on stdin; \
+                   \"Name: value\" lines it prints set headers (\"Name:\" removes one)")
+            .long("pre-request-cmd")
+            .value_name("command"))
+        .arg(Arg::new("post-response-cmd")
+            .help("Run this shell command after each response with the response as JSON on stdin \
+                   (the --output-format json document)")
+            .long("post-response-cmd")
+            .value_name("command"))
+        .arg(Arg::new("output-format")
+            .help("How to print the response: \"text\" prints the body, \"json\" prints one JSON object \
+                   with the final URL, status, headers, timing and body")
+            .long("output-format")
+            .value_name("format")
+            .value_parser(["text", "json"])
+            .default_value("text")
+            .conflicts_with("header-out"))
+        .arg(Arg::new("no-buffer")
+            .help("Write a response body to stdout as it arrives, flushing after every chunk, \
+                   instead of once it is complete (for streaming responses; disables --pretty)")
+            .short('N')
+            .long("no-buffer")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("pretty")
+            .help("Format JSON and XML bodies with indentation and CSV as a table. \
+                   \"auto\" (the default for a bare --pretty) only formats when stdout is a terminal")
+            .long("pretty")
+            .value_name("when")
+            .value_parser(["auto", "always", "never"])
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("auto")
+            .default_value("never"))
+        .arg(Arg::new("no-color")
+            .help("Never color the output, even on a terminal (so does setting NO_COLOR)")
+            .long("no-color")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("no-body")
+            .help("With --output-format json, leave the response body out of the JSON")
+            .long("no-body")
+            .action(ArgAction::SetTrue))
+}
+
+// The encodings --accept-encoding may list: the ones the client can decode
+const DECODABLE_ENCODINGS: [&str; 4] = ["gzip", "br", "zstd", "identity"];
+
+fn parse_accept_encoding(value: &str) -> Result<String, String> {
+    for item in value.split(',') {
+        let encoding = item.split(';').next().unwrap_or_default().trim();
+        if !DECODABLE_ENCODINGS.contains(&encoding) {
+            return Err(format!(
+                "unsupported encoding \"{}\" (supported: {})",
+                encoding,
+                DECODABLE_ENCODINGS.join(", ")
+            ));
+        }
+    }
+    Ok(value.to_string())
+}
@@ -0,0 +1,18 @@
+use clap::ArgMatches;
+use std::io::IsTerminal;
+
+// SGR parameters for the parts of the output that are colored
+pub const KEY: &str = "1;34";
+pub const STRING: &str = "32";
+
+// Whether stdout may carry ANSI colors: only when it is a terminal, and never
+// with --no-color or a non-empty NO_COLOR in the environment (no-color.org).
+// Every colored output asks here rather than checking on its own.
+pub fn enabled(matches: &ArgMatches) -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    !matches.get_flag("no-color") && !no_color && std::io::stdout().is_terminal()
+}
+
+pub fn paint(text: &str, style: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", style, text)
+}
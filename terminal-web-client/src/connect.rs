@@ -0,0 +1,121 @@
+use reqwest::Url;
+
+// A --connect-to rule, "HOST1:PORT1:HOST2:PORT2": connections meant for
+// HOST1:PORT1 go to HOST2:PORT2 instead, while the URL (and so the Host header
+// and TLS SNI) stays the same. An empty field matches any host/port on the
+// left and keeps the URL's host/port on the right. IPv6 hosts go in brackets.
+#[derive(Clone, Debug)]
+pub struct ConnectTo {
+    host: Option<String>,
+    port: Option<u16>,
+    to_host: Option<String>,
+    to_port: Option<u16>,
+}
+
+// clap value parser for --connect-to
+pub fn parse(value: &str) -> Result<ConnectTo, String> {
+    let fields = split(value);
+    let [host, port, to_host, to_port] = fields.as_slice() else {
+        return Err(format!(
+            "expected HOST1:PORT1:HOST2:PORT2 (fields may be empty), found {} field(s)",
+            fields.len()
+        ));
+    };
+    let port_field = |field: &str| -> Result<Option<u16>, String> {
+        match field {
+            "" => Ok(None),
+            port => port
+                .parse::<u16>()
+                .map(Some)
+                .map_err(|_| format!("invalid port \"{}\"", port)),
+        }
+    };
+    let host_field = |field: &str| (!field.is_empty()).then(|| field.trim_matches(['[', ']']).to_ascii_lowercase());
+
+    Ok(ConnectTo {
+        host: host_field(host),
+        port: port_field(port)?,
+        to_host: host_field(to_host),
+        to_port: port_field(to_port)?,
+    })
+}
+
+// The host and port to connect to for `url`, from the first matching rule
+pub fn address(rules: &[ConnectTo], url: &Url) -> Option<(String, u16)> {
+    let host = url.host_str()?.trim_matches(['[', ']']).to_ascii_lowercase();
+    let port = url.port_or_known_default()?;
+    rules
+        .iter()
+        .find(|rule| rule.host.as_ref().is_none_or(|h| *h == host) && rule.port.is_none_or(|p| p == port))
+        .map(|rule| (rule.to_host.clone().unwrap_or(host), rule.to_port.unwrap_or(port)))
+}
+
+// Splits on colons that aren't inside [IPv6] brackets
+fn split(value: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut bracketed = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            '[' => bracketed = true,
+            ']' => bracketed = false,
+            ':' if !bracketed => {
+                fields.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&value[start..]);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(text: &str) -> Url {
+        Url::parse(text).unwrap()
+    }
+
+    #[test]
+    fn needs_four_fields_and_valid_ports() {
+        assert!(parse("a:80:b:8080").is_ok());
+        assert!(parse(":::").is_ok());
+        for value in ["a:80:b", "a:80:b:8080:x", "a", "a:http:b:80", "a:80:b:70000"] {
+            assert!(parse(value).is_err(), "{}", value);
+        }
+        let err = parse("a:80:b").unwrap_err();
+        assert_eq!(err, "expected HOST1:PORT1:HOST2:PORT2 (fields may be empty), found 3 field(s)");
+    }
+
+    #[test]
+    fn redirects_the_matching_host_and_port() {
+        let rules = [parse("Example.com:443:backend:8443").unwrap()];
+        assert_eq!(address(&rules, &url("https://example.com/")), Some(("backend".to_string(), 8443)));
+        assert_eq!(address(&rules, &url("http://example.com/")), None);
+        assert_eq!(address(&rules, &url("https://other.com/")), None);
+    }
+
+    // Empty fields match any host or port, and keep the URL's
+    #[test]
+    fn empty_fields() {
+        let rules = [parse("::127.0.0.1:").unwrap()];
+        assert_eq!(address(&rules, &url("http://a.test:81/")), Some(("127.0.0.1".to_string(), 81)));
+        let rules = [parse("a.test:::9000").unwrap()];
+        assert_eq!(address(&rules, &url("https://a.test/")), Some(("a.test".to_string(), 9000)));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = [parse("a.test:80:first:1").unwrap(), parse("::second:2").unwrap()];
+        assert_eq!(address(&rules, &url("http://a.test/")), Some(("first".to_string(), 1)));
+        assert_eq!(address(&rules, &url("http://b.test/")), Some(("second".to_string(), 2)));
+    }
+
+    #[test]
+    fn ipv6_hosts_in_brackets() {
+        let rules = [parse("[::1]:80:[fe80::2]:8080").unwrap()];
+        assert_eq!(address(&rules, &url("http://[::1]/")), Some(("fe80::2".to_string(), 8080)));
+    }
+}
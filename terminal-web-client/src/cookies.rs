@@ -0,0 +1,158 @@
+use reqwest::cookie::Jar;
+use reqwest::Url;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// One line of a Netscape/curl cookie jar:
+//   domain  include-subdomains  path  secure  expires  name  value
+// separated by tabs. `expires` is seconds since the epoch, 0 for a session
+// cookie. curl marks HttpOnly cookies by prefixing the domain with "#HttpOnly_".
+struct JarLine {
+    domain: String,
+    include_subdomains: bool,
+    path: String,
+    secure: bool,
+    expires: u64,
+    name: String,
+    value: String,
+}
+
+// Loads a cookie jar file for the client to send from (--load-cookies).
+// Expired cookies are skipped, and so are session cookies when
+// `junk_session` is set, like a browser starting a new session
+// (--junk-session-cookies).
+pub fn load_jar(path: &str, junk_session: bool) -> Result<Jar, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Unable to read cookie jar \"{}\": {}", path, err))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+
+    let jar = Jar::default();
+    for (number, line) in text.lines().enumerate() {
+        let cookie = match parse_line(line) {
+            Ok(Some(cookie)) => cookie,
+            Ok(None) => continue,
+            Err(reason) => return Err(format!("{}:{}: {}", path, number + 1, reason)),
+        };
+        let session = cookie.expires == 0;
+        if (session && junk_session) || (!session && cookie.expires <= now) {
+            continue;
+        }
+
+        // A host-only cookie carries no Domain attribute, so it only goes back
+        // to the exact host it came from
+        let mut attributes = format!("{}={}; Path={}", cookie.name, cookie.value, cookie.path);
+        if cookie.include_subdomains {
+            attributes += &format!("; Domain={}", cookie.domain);
+        }
+        if cookie.secure {
+            attributes += "; Secure";
+        }
+        if !session {
+            attributes += &format!("; Max-Age={}", cookie.expires - now);
+        }
+        let scheme = if cookie.secure { "https" } else { "http" };
+        let url = Url::parse(&format!("{}://{}{}", scheme, cookie.domain, cookie.path))
+            .map_err(|err| format!("{}:{}: invalid domain or path: {}", path, number + 1, err))?;
+        jar.add_cookie_str(&attributes, &url);
+    }
+    Ok(jar)
+}
+
+// Ok(None) for blank lines and comments
+fn parse_line(line: &str) -> Result<Option<JarLine>, String> {
+    let line = line.trim_end_matches('\r');
+    let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+    if line.trim().is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let [domain, include_subdomains, path, secure, expires, name, value] = line.split('\t').collect::<Vec<_>>()[..]
+    else {
+        return Err("expected 7 tab-separated fields".to_string());
+    };
+    let flag = |field: &str| match field {
+        "TRUE" => Ok(true),
+        "FALSE" => Ok(false),
+        other => Err(format!("expected TRUE or FALSE, found \"{}\"", other)),
+    };
+    Ok(Some(JarLine {
+        domain: domain.trim_start_matches('.').to_string(),
+        include_subdomains: flag(include_subdomains)?,
+        path: path.to_string(),
+        secure: flag(secure)?,
+        expires: expires
+            .parse()
+            .map_err(|_| format!("invalid expiry \"{}\"", expires))?,
+        name: name.to_string(),
+        value: value.to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::cookie::CookieStore;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Writes `text` as a jar file and loads it back
+    fn load(text: &str, junk_session: bool) -> Result<Jar, String> {
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "terminal-web-client-cookies-{}-{}.txt",
+            std::process::id(),
+            FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&path, text).unwrap();
+        let jar = load_jar(path.to_str().unwrap(), junk_session);
+        fs::remove_file(&path).unwrap();
+        jar
+    }
+
+    // The cookies the client would send to `url`, sorted
+    fn sent(jar: &Jar, url: &str) -> Vec<String> {
+        let header = jar.cookies(&Url::parse(url).unwrap());
+        let header = header.as_ref().map_or("", |value| value.to_str().unwrap());
+        let mut cookies: Vec<String> = header.split("; ").filter(|cookie| !cookie.is_empty()).map(String::from).collect();
+        cookies.sort();
+        cookies
+    }
+
+    const JAR: &str = "# Netscape HTTP Cookie File\n\
+                       example.com\tFALSE\t/\tFALSE\t0\tsession\ts1\n\
+                       example.com\tFALSE\t/\tFALSE\t4102444800\tpersistent\tp1\n\
+                       #HttpOnly_example.com\tFALSE\t/\tFALSE\t4102444800\thttponly\th1\n\
+                       example.com\tFALSE\t/\tFALSE\t1\texpired\te1\n";
+
+    #[test]
+    fn junk_session_drops_session_cookies() {
+        let jar = load(JAR, true).unwrap();
+        assert_eq!(sent(&jar, "http://example.com/"), ["httponly=h1", "persistent=p1"]);
+    }
+
+    #[test]
+    fn keeps_session_cookies_by_default() {
+        let jar = load(JAR, false).unwrap();
+        assert_eq!(sent(&jar, "http://example.com/"), ["httponly=h1", "persistent=p1", "session=s1"]);
+    }
+
+    #[test]
+    fn domains_paths_and_secure() {
+        let jar = load(
+            ".example.com\tTRUE\t/\tFALSE\t0\twide\tw\n\
+             example.com\tFALSE\t/api\tTRUE\t0\tsecure\ts\n",
+            false,
+        )
+        .unwrap();
+        assert_eq!(sent(&jar, "http://www.example.com/"), ["wide=w"]);
+        assert_eq!(sent(&jar, "http://example.com/api/v1"), ["wide=w"]);
+        assert_eq!(sent(&jar, "https://example.com/api/v1"), ["secure=s", "wide=w"]);
+        assert!(sent(&jar, "http://other.com/").is_empty());
+    }
+
+    #[test]
+    fn malformed_lines_fail_with_their_number() {
+        let err = load("# comment\n\nexample.com\tFALSE\t/\n", false).err().unwrap();
+        assert!(err.ends_with(":3: expected 7 tab-separated fields"), "{}", err);
+        let err = load("example.com\tYES\t/\tFALSE\t0\tn\tv\n", false).err().unwrap();
+        assert!(err.ends_with(":1: expected TRUE or FALSE, found \"YES\""), "{}", err);
+    }
+}
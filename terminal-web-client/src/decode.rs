@@ -0,0 +1,69 @@
+use flate2::write::GzDecoder;
+use std::io::{self, Write};
+
+// Size of the brotli decoder's internal buffer
+const BROTLI_BUFFER: usize = 4096;
+
+// The content codings the transport can decode, as Accept-Encoding names them
+pub const ENCODINGS: [&str; 3] = ["gzip", "br", "zstd"];
+
+// Decodes one Content-Encoding as the body streams in, chunk by chunk
+pub enum Decoder {
+    Gzip(GzDecoder<Vec<u8>>),
+    Brotli(Box<brotli_decompressor::DecompressorWriter<Vec<u8>>>),
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+}
+
+impl Decoder {
+    // A decoder for a Content-Encoding value; None for anything but a single
+    // coding from ENCODINGS
+    pub fn new(encoding: &str) -> Option<io::Result<Decoder>> {
+        match encoding.trim() {
+            "gzip" => Some(Ok(Decoder::Gzip(GzDecoder::new(Vec::new())))),
+            "br" => Some(Ok(Decoder::Brotli(Box::new(brotli_decompressor::DecompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER,
+            ))))),
+            "zstd" => Some(zstd::stream::write::Decoder::new(Vec::new()).map(Decoder::Zstd)),
+            _ => None,
+        }
+    }
+
+    // Decodes a chunk, returning what it decoded to so far
+    pub fn feed(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        let out = match self {
+            Decoder::Gzip(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+            Decoder::Brotli(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+            Decoder::Zstd(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.flush()?;
+                decoder.get_mut()
+            }
+        };
+        Ok(std::mem::take(out))
+    }
+
+    // Checks the body ended where the encoding says it should (gzip's trailer,
+    // brotli's last block) and returns anything still held back
+    pub fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Decoder::Gzip(decoder) => decoder.finish(),
+            Decoder::Brotli(mut decoder) => {
+                decoder.close()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            Decoder::Zstd(mut decoder) => {
+                decoder.flush()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+}
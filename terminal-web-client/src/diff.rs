@@ -0,0 +1,36 @@
+use crate::canonical;
+use similar::TextDiff;
+use std::fs;
+
+// Compares a response body with a saved golden file (--diff-against).
+// `normalize` compares the canonical form of JSON bodies instead of their
+// text, so whitespace and key order don't count (--diff-normalize or
+// --json-normalize); anything that doesn't parse as JSON on both sides is
+// compared as it is. A mismatch comes back as a unified diff from the file to
+// the response.
+pub fn check(path: &str, content_type: Option<&str>, body: &[u8], normalize: bool) -> Result<(), String> {
+    let expected = fs::read(path).map_err(|err| format!("Unable to read \"{}\": {}", path, err))?;
+    if expected == body {
+        return Ok(());
+    }
+
+    let canonical = (normalize && canonical::is_json(content_type))
+        .then(|| (canonical::json(&expected), canonical::json(body)));
+    let (expected, actual) = match canonical {
+        Some((Some(expected), Some(actual))) => (expected + "\n", actual + "\n"),
+        _ => (
+            String::from_utf8_lossy(&expected).into_owned(),
+            String::from_utf8_lossy(body).into_owned(),
+        ),
+    };
+    if expected == actual {
+        return Ok(());
+    }
+
+    let diff = TextDiff::from_lines(&expected, &actual)
+        .unified_diff()
+        .header(path, "response")
+        .to_string();
+    Err(format!("Response body differs from \"{}\":\n{}", path, diff.trim_end()))
+}
+
@@ -0,0 +1,231 @@
+use crate::trace;
+use hickory_resolver::config::{NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_resolver::proto::rr::{Name as DnsName, RData, RecordType};
+use hickory_resolver::TokioAsyncResolver;
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::{Client, Url};
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::net::{self, TcpStream};
+
+const DNS_PORT: u16 = 53;
+const DNS_MESSAGE: &str = "application/dns-message";
+const DOH_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The --dns-servers or --doh-url resolver, for connections opened outside reqwest
+static RESOLVER: OnceLock<Arc<Resolver>> = OnceLock::new();
+
+// Looks host names up with the --dns-servers or --doh-url instead of the
+// system's resolver
+#[derive(Clone)]
+pub struct Resolver {
+    backend: Backend,
+    // Print each name resolved and the addresses found (--verbose)
+    verbose: bool,
+}
+
+#[derive(Clone)]
+enum Backend {
+    // If none of the servers gives an answer, the system resolver is tried
+    // after saying so, rather than failing a request the system could resolve
+    Servers {
+        servers: Vec<SocketAddr>,
+        resolver: Arc<TokioAsyncResolver>,
+    },
+    // A failed lookup is an error: falling back to plain DNS would leak the
+    // name DNS-over-HTTPS was asked to keep private
+    Doh(Doh),
+}
+
+// clap value parser for --dns-servers, "1.1.1.1,8.8.8.8"; each server is an IP
+// address, with an optional port ("[2606:4700::1111]:53", "127.0.0.1:5353")
+pub fn parse_servers(value: &str) -> Result<Vec<SocketAddr>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .map(|server| {
+            server
+                .parse::<SocketAddr>()
+                .or_else(|_| server.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, DNS_PORT)))
+                .map_err(|_| format!("invalid DNS server \"{}\", expected an IP address with an optional port", server))
+        })
+        .collect()
+}
+
+// Creates a resolver using the --dns-servers and makes it the one raw
+// connections use too
+pub fn with_servers(servers: Vec<SocketAddr>, verbose: bool) -> Arc<Resolver> {
+    // UDP first, TCP for answers too large for a datagram
+    let name_servers: Vec<NameServerConfig> = servers
+        .iter()
+        .flat_map(|addr| [Protocol::Udp, Protocol::Tcp].map(|protocol| NameServerConfig::new(*addr, protocol)))
+        .collect();
+    let config = ResolverConfig::from_parts(None, Vec::new(), NameServerConfigGroup::from(name_servers));
+    let resolver = Arc::new(TokioAsyncResolver::tokio(config, ResolverOpts::default()));
+    install(Backend::Servers { servers, resolver }, verbose)
+}
+
+// Creates a resolver asking the --doh-url and makes it the one raw
+// connections use too
+pub fn with_doh(url: Url, verbose: bool) -> Result<Arc<Resolver>, String> {
+    Ok(install(Backend::Doh(Doh::new(url)?), verbose))
+}
+
+fn install(backend: Backend, verbose: bool) -> Arc<Resolver> {
+    let resolver = Arc::new(Resolver { backend, verbose });
+    let _ = RESOLVER.set(resolver.clone());
+    resolver
+}
+
+impl Resolver {
+    async fn lookup(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        let (ips, source) = match &self.backend {
+            Backend::Servers { servers, resolver } => {
+                let servers = servers.iter().map(SocketAddr::to_string).collect::<Vec<_>>().join(", ");
+                match resolver.lookup_ip(host).await {
+                    Ok(found) => (found.iter().collect::<Vec<_>>(), servers),
+                    Err(err) => {
+                        eprintln!(
+                            "Unable to resolve \"{}\" using {} ({}), falling back to the system resolver",
+                            host, servers, err
+                        );
+                        return Ok(net::lookup_host((host, port)).await?.collect());
+                    }
+                }
+            }
+            Backend::Doh(doh) => {
+                let ips = doh.lookup(host).await.map_err(|err| {
+                    io::Error::other(format!("DNS-over-HTTPS lookup of \"{}\" using {} failed: {}", host, doh.url, err))
+                })?;
+                (ips, doh.url.to_string())
+            }
+        };
+        if self.verbose {
+            let list = ips.iter().map(IpAddr::to_string).collect::<Vec<_>>().join(", ");
+            trace::verbose(&format!("Resolved {} to {} using {}", host, list, source));
+        }
+        Ok(ips.into_iter().map(|ip| SocketAddr::new(ip, port)).collect())
+    }
+}
+
+impl Resolve for Resolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        // reqwest fills in the port itself
+        Box::pin(async move {
+            let addrs: Addrs = Box::new(resolver.lookup(name.as_str(), 0).await?.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+// Opens a TCP connection to `host`:`port`, resolving the host with the
+// --dns-servers or --doh-url if one was given
+pub async fn connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    match RESOLVER.get() {
+        Some(resolver) if host.parse::<IpAddr>().is_err() => {
+            TcpStream::connect(resolver.lookup(host, port).await?.as_slice()).await
+        }
+        _ => TcpStream::connect((host, port)).await,
+    }
+}
+
+// clap value parser for --doh-url; DNS-over-HTTPS is HTTPS only
+pub fn parse_doh_url(value: &str) -> Result<Url, String> {
+    let url = Url::parse(value).map_err(|err| format!("invalid URL: {}", err))?;
+    match url.scheme() {
+        "https" => Ok(url),
+        scheme => Err(format!("expected an https:// URL, found \"{}\"", scheme)),
+    }
+}
+
+// An RFC 8484 DNS-over-HTTPS client. Queries are DNS wire-format messages
+// POSTed as application/dns-message, A and AAAA side by side. Answers are
+// kept for the rest of the invocation, whatever their TTL, so each name is
+// asked about once. The DoH server's own name is resolved by the system.
+#[derive(Clone)]
+struct Doh {
+    url: Url,
+    client: Client,
+    cache: Arc<Mutex<HashMap<String, Vec<IpAddr>>>>,
+}
+
+impl Doh {
+    fn new(url: Url) -> Result<Self, String> {
+        let client = Client::builder()
+            .timeout(DOH_TIMEOUT)
+            .build()
+            .map_err(|err| format!("Unable to create DNS-over-HTTPS client: {}", err))?;
+        Ok(Doh {
+            url,
+            client,
+            cache: Default::default(),
+        })
+    }
+
+    async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        let host = host.to_ascii_lowercase();
+        if let Some(ips) = self.cache.lock().unwrap().get(&host) {
+            return Ok(ips.clone());
+        }
+        let (v4, v6) = tokio::join!(self.query(&host, RecordType::A), self.query(&host, RecordType::AAAA));
+        let mut ips = v4?;
+        ips.extend(v6?);
+        if ips.is_empty() {
+            return Err("no A or AAAA records".to_string());
+        }
+        self.cache.lock().unwrap().insert(host, ips.clone());
+        Ok(ips)
+    }
+
+    async fn query(&self, host: &str, record_type: RecordType) -> Result<Vec<IpAddr>, String> {
+        let name = DnsName::from_ascii(host).map_err(|err| format!("invalid host name: {}", err))?;
+        // ID 0, as RFC 8484 recommends, so answers can be cached by HTTP
+        let mut message = Message::new();
+        message
+            .set_id(0)
+            .set_message_type(MessageType::Query)
+            .set_op_code(OpCode::Query)
+            .set_recursion_desired(true)
+            .add_query(Query::query(name, record_type));
+        let body = message.to_vec().map_err(|err| err.to_string())?;
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header(CONTENT_TYPE, DNS_MESSAGE)
+            .header(ACCEPT, DNS_MESSAGE)
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("server answered {}", response.status()));
+        }
+        if response.headers().get(CONTENT_TYPE).is_none_or(|value| value != DNS_MESSAGE) {
+            return Err(format!("server didn't answer with {}", DNS_MESSAGE));
+        }
+        let bytes = response.bytes().await.map_err(|err| err.to_string())?;
+        let answer = Message::from_vec(&bytes).map_err(|err| format!("malformed DNS message: {}", err))?;
+        match answer.response_code() {
+            ResponseCode::NoError => {}
+            ResponseCode::NXDomain => return Err("no such host".to_string()),
+            code => return Err(format!("server answered {}", code)),
+        }
+        Ok(answer
+            .answers()
+            .iter()
+            .filter_map(|record| match record.data()? {
+                RData::A(a) => Some(IpAddr::V4(a.0)),
+                RData::AAAA(aaaa) => Some(IpAddr::V6(aaaa.0)),
+                _ => None,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,68 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::process::Command;
+
+// --body-from-editor: opens $VISUAL, or else $EDITOR, on an empty temporary
+// file, like git does for a commit message, and returns what was saved. The
+// editor runs through the shell, so "code --wait" works as well as "vim". The
+// file is removed afterwards whatever happened. Saving nothing (or only
+// whitespace) or quitting with a non-zero status sends nothing.
+pub async fn compose() -> Result<String, String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.trim().is_empty()))
+        .ok_or("--body-from-editor needs an editor: set $VISUAL or $EDITOR (e.g. EDITOR=vim)")?;
+
+    let path = temp_path();
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|err| format!("Unable to create \"{}\": {}", path.display(), err))?;
+    let edited = edit(&editor, &path).await;
+    let body = edited.and_then(|_| {
+        fs::read_to_string(&path).map_err(|err| format!("Unable to read \"{}\": {}", path.display(), err))
+    });
+    let _ = fs::remove_file(&path);
+
+    let body = body?;
+    if body.trim().is_empty() {
+        return Err("Aborting: the body was left empty".to_string());
+    }
+    Ok(body)
+}
+
+async fn edit(editor: &str, path: &Path) -> Result<(), String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(editor).arg(path);
+        shell
+    } else {
+        // The path is passed as $1 rather than pasted into the command line
+        let mut shell = Command::new("sh");
+        shell.args(["-c", &format!("{} \"$1\"", editor), "sh"]).arg(path);
+        shell
+    };
+    let status = shell
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .await
+        .map_err(|err| format!("Unable to run editor \"{}\": {}", editor, err))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!("Editor \"{}\" failed ({}), nothing was sent", editor, status)),
+    }
+}
+
+// A file name in the temporary directory no other run will pick
+fn temp_path() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.subsec_nanos());
+    env::temp_dir().join(format!("terminal-web-client-body-{}-{}.txt", std::process::id(), nanos))
+}
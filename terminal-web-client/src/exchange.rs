@@ -0,0 +1,860 @@
+use crate::audit::{self, RequestLog};
+use crate::connect::{self, ConnectTo};
+use crate::decode::Decoder;
+use crate::limits::{self, HeaderLimits, RateLimit, SpeedLimit};
+use crate::progress::{ProgressMode, ProgressReporter};
+use crate::raw;
+use crate::reuse;
+use crate::sigv4::Signer;
+use crate::trace;
+use crate::tunnel::ProxyTunnel;
+use hyper::body::Bytes;
+use reqwest::header::{HeaderMap, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, LOCATION, TRANSFER_ENCODING};
+use reqwest::{Client, Method, Request, Response, StatusCode, Url, Version};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::OnceCell;
+
+// How many redirects --location-trusted follows, the same as reqwest's default
+pub const MAX_REDIRECTS: usize = 10;
+
+// A completed request: the parts of the response the rest of the client needs
+#[derive(Clone)]
+pub struct Exchange {
+    // The URL that answered, after any redirects
+    pub url: Url,
+    pub version: Version,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+// The --dedup responses, each one set once its request completes
+pub type Dedup = Mutex<HashMap<(Method, Url), Arc<OnceCell<Exchange>>>>;
+
+// Decides how a request goes over the wire: through the pooled reqwest client,
+// or over a raw HTTP/1.1 connection when an option needs one
+pub struct Transport {
+    // Negotiates the HTTP version, as reqwest does by default
+    pub client: Client,
+    // With --http1.1/--http2/--http3, the version and a client that speaks
+    // only it, which sends every pooled request instead
+    pub forced: Option<(Version, Client)>,
+    // Whether `client` retries a request the forced version failed
+    // (--http-version-fallback)
+    pub version_fallback: bool,
+    pub request_target: Option<String>,
+    // With --path-as-is, the target to send for each URL whose path Url::parse
+    // normalized, keyed by the normalized URL
+    pub path_as_is: HashMap<Url, String>,
+    // A whole request read from stdin, sent as it is instead of `request` (--stdin-headers)
+    pub raw_request: Option<Vec<u8>>,
+    pub expect100: Option<Duration>,
+    pub connect_to: Vec<ConnectTo>,
+    // Reach every host through a proxy's CONNECT tunnel (--proxy-tunnel)
+    pub proxy_tunnel: Option<ProxyTunnel>,
+    // Signs each request right before it is sent (--aws-sigv4)
+    pub aws_sigv4: Option<Signer>,
+    pub header_limits: HeaderLimits,
+    pub speed_limit: Option<SpeedLimit>,
+    // Shared by every transfer this transport makes, concurrent or not (--limit-rate)
+    pub rate_limit: Option<RateLimit>,
+    // The Content-Encodings decoded, out of decode::ENCODINGS: those advertised
+    // (--compressed, --accept-encoding), unless the encoded bytes are wanted
+    pub decode: Vec<&'static str>,
+    // Follow redirects here, keeping credentials across hosts (--location-trusted)
+    pub location_trusted: bool,
+    // Report each redirect followed and whether each request reused a
+    // pooled connection (--verbose)
+    pub verbose: bool,
+    pub progress: ProgressMode,
+    // With --dedup, GET/HEAD responses by URL, so a URL listed twice is fetched
+    // once. A request for a URL already in flight waits for that response.
+    pub dedup: Option<Dedup>,
+    // Append a line for every request sent (--log-requests)
+    pub request_log: Option<RequestLog>,
+}
+
+impl Transport {
+    // The request-target that goes on the request line
+    pub fn target(&self, request: &Request) -> String {
+        self.request_target
+            .clone()
+            .or_else(|| self.path_as_is.get(request.url()).cloned())
+            .unwrap_or_else(|| raw::origin_target(request.url()))
+    }
+
+    // Whether the request needs the raw HTTP/1.1 connection rather than reqwest:
+    // a raw request from stdin, a custom or --path-as-is request-target,
+    // Expect: 100-continue with a buffered body, a --connect-to match (reqwest
+    // can't connect somewhere other than the URL's port while keeping its Host
+    // and SNI), or a --proxy-tunnel
+    pub fn uses_raw(&self, request: &Request) -> bool {
+        let buffered = request.body().and_then(|body| body.as_bytes()).is_some();
+        self.raw_request.is_some()
+            || self.request_target.is_some()
+            || self.path_as_is.contains_key(request.url())
+            || (self.expect100.is_some() && buffered)
+            || connect::address(&self.connect_to, request.url()).is_some()
+            || self.proxy_tunnel.is_some()
+    }
+
+    // Sends a request through the pooled client. Redirects are normally left to
+    // reqwest, which drops Authorization, cookies and Proxy-Authorization as
+    // soon as a redirect leaves the original host (or port). With
+    // --location-trusted they are followed here instead, keeping every header
+    // on every hop; methods and bodies change the way reqwest changes them.
+    async fn execute(&self, mut request: Request) -> Result<Response, String> {
+        let mut hops = 0;
+        loop {
+            let next = request.try_clone().filter(|_| self.location_trusted);
+            reuse::begin();
+            let response = match self.dispatch(request).await {
+                Err(err) if limits::is_head_too_large(&err) => {
+                    return Err("Response header section is too large to parse".to_string());
+                }
+                response => response.map_err(|err| format!("Request failed: {}", err))?,
+            };
+            if let (true, Some((reused, id))) = (self.verbose, reuse::finish()) {
+                let peer = response.remote_addr().map_or_else(|| response.url().to_string(), |addr| addr.to_string());
+                match reused {
+                    true => trace::verbose(&format!("Re-using existing connection #{:08x} to {}", id, peer)),
+                    false => trace::verbose(&format!("Connected to {} (new connection #{:08x})", peer, id)),
+                }
+            }
+
+            let redirect = matches!(
+                response.status(),
+                StatusCode::MOVED_PERMANENTLY
+                    | StatusCode::FOUND
+                    | StatusCode::SEE_OTHER
+                    | StatusCode::TEMPORARY_REDIRECT
+                    | StatusCode::PERMANENT_REDIRECT
+            );
+            let location = response.headers().get(LOCATION).and_then(|value| value.to_str().ok());
+            // A streamed body can't be sent twice, so its redirect is the response
+            let (Some(mut next), true, Some(location)) = (next, redirect, location) else {
+                return Ok(response);
+            };
+            if hops == MAX_REDIRECTS {
+                return Err(format!("Request failed: more than {} redirects", MAX_REDIRECTS));
+            }
+            hops += 1;
+
+            let url = response
+                .url()
+                .join(location)
+                .map_err(|err| format!("Request failed: invalid redirect location \"{}\": {}", location, err))?;
+            if self.verbose {
+                trace::verbose(&format!("Redirect {} from {} to {}", response.status(), response.url(), url));
+            }
+            if response.status() < StatusCode::TEMPORARY_REDIRECT {
+                *next.body_mut() = None;
+                for header in [TRANSFER_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, CONTENT_LENGTH] {
+                    next.headers_mut().remove(header);
+                }
+                if !matches!(*next.method(), Method::GET | Method::HEAD) {
+                    *next.method_mut() = Method::GET;
+                }
+            }
+            *next.url_mut() = url;
+            request = next;
+        }
+    }
+
+    // A pooled connection the server closed while it sat idle can look alive
+    // until a request is written to it, and then fail without any answer. An
+    // idempotent request is sent once more, on another connection, as RFC 9110
+    // allows; anything else may already have had its effect.
+    async fn dispatch(&self, request: Request) -> reqwest::Result<Response> {
+        let again = request.try_clone().filter(|request| is_idempotent(request.method()));
+        match (self.negotiate(request).await, again) {
+            (Err(err), Some(again)) if is_stale_connection(&err) => {
+                if self.verbose {
+                    trace::verbose(&format!("Connection closed before any answer ({}), retrying on a new one", err));
+                }
+                // The connection reported is the one that answers
+                reuse::begin();
+                self.negotiate(again).await
+            }
+            (result, _) => result,
+        }
+    }
+
+    // A forced HTTP version is all a request gets, unless fallback is allowed:
+    // then a failed attempt is retried by the negotiating client, which says
+    // so and what it ended up with. A streamed body can't be sent twice, so it
+    // only gets the first attempt.
+    async fn negotiate(&self, request: Request) -> reqwest::Result<Response> {
+        let Some((version, forced)) = &self.forced else {
+            return self.client.execute(request).await;
+        };
+        let fallback = request.try_clone().filter(|_| self.version_fallback);
+        let mut first = request;
+        if *version == Version::HTTP_3 {
+            *first.version_mut() = Version::HTTP_3;
+        }
+        match (forced.execute(first).await, fallback) {
+            (Err(err), Some(fallback)) => {
+                eprintln!("{} failed ({}), falling back", trace::version_str(*version), err);
+                let response = self.client.execute(fallback).await?;
+                eprintln!("Fell back to {}", trace::version_str(response.version()));
+                Ok(response)
+            }
+            (result, _) => result,
+        }
+    }
+
+    pub async fn send(&self, request: Request) -> Result<Exchange, String> {
+        self.send_with(request, &mut |_, _, _| true).await
+    }
+
+    // Like send, but also hands each body chunk to `on_chunk` (with the status
+    // and headers) as it arrives. A chunk it returns false for has been dealt
+    // with and is left out of the returned body. A response reused under
+    // --dedup never calls it, and its body is only in the returned Exchange.
+    pub async fn send_with(
+        &self,
+        request: Request,
+        on_chunk: &mut (dyn FnMut(StatusCode, &HeaderMap, &[u8]) -> bool + Send),
+    ) -> Result<Exchange, String> {
+        // Only idempotent requests are deduplicated; anything else may have side
+        // effects. A failed request leaves nothing behind, so the next one for
+        // the URL, or one already waiting on it, is sent.
+        let cached = self
+            .dedup
+            .as_ref()
+            .filter(|_| matches!(*request.method(), Method::GET | Method::HEAD))
+            .map(|cache| {
+                let key = (request.method().clone(), request.url().clone());
+                cache.lock().unwrap().entry(key).or_default().clone()
+            });
+        let Some(cached) = cached else {
+            return self.send_once(request, on_chunk).await;
+        };
+        let url = request.url().clone();
+        let mut sent = false;
+        let exchange = cached
+            .get_or_try_init(|| {
+                sent = true;
+                self.send_once(request, on_chunk)
+            })
+            .await?;
+        if !sent {
+            eprintln!("Reusing the response already received for {}", url);
+        }
+        Ok(exchange.clone())
+    }
+
+    // Drops the response --dedup kept for a URL, so that it is sent again
+    pub fn forget(&self, url: &Url) {
+        if let Some(cache) = &self.dedup {
+            cache.lock().unwrap().retain(|(_, cached), _| cached != url);
+        }
+    }
+
+    // Sends the request as send_with does, without --dedup, and logs it
+    async fn send_once(
+        &self,
+        request: Request,
+        on_chunk: &mut (dyn FnMut(StatusCode, &HeaderMap, &[u8]) -> bool + Send),
+    ) -> Result<Exchange, String> {
+        let sent = SystemTime::now();
+        let started = Instant::now();
+        let (method, url) = (request.method().clone(), request.url().clone());
+        let result = self.transfer(request, on_chunk).await;
+        if let Some(log) = &self.request_log {
+            log.write(&audit::Entry {
+                sent,
+                method: &method,
+                url: &url,
+                duration: started.elapsed(),
+                outcome: match &result {
+                    Ok((exchange, decoded_len)) => Ok((exchange.status, *decoded_len)),
+                    Err(err) => Err(err),
+                },
+            });
+        }
+        result.map(|(exchange, _)| exchange)
+    }
+
+    // Sends a request over the raw path or the pooled client and reads the
+    // whole response, along with the length of its decoded body: on_chunk may
+    // have taken some or all of it, so the Exchange can hold less
+    async fn transfer(
+        &self,
+        request: Request,
+        on_chunk: &mut (dyn FnMut(StatusCode, &HeaderMap, &[u8]) -> bool + Send),
+    ) -> Result<(Exchange, usize), String> {
+        // --verbose says what came of an Accept-Encoding this client sent
+        let negotiated = self.verbose && request.headers().contains_key(ACCEPT_ENCODING);
+        let (url, version, status, mut headers, mut source) = if self.uses_raw(&request) {
+            // Expect: 100-continue only applies to buffered bodies
+            let buffered = request.body().and_then(|body| body.as_bytes()).is_some();
+            let expect100 = self.expect100.filter(|_| buffered);
+            let connect = connect::address(&self.connect_to, request.url());
+            let target = self.target(&request);
+            let tunnel = self.proxy_tunnel.as_ref();
+            let result = match (&self.raw_request, expect100) {
+                (Some(bytes), _) => raw::send_bytes(request.url(), bytes, connect.as_ref(), tunnel).await,
+                (None, Some(wait)) => {
+                    raw::send_expect_continue(&request, &target, wait, connect.as_ref(), tunnel).await
+                }
+                (None, None) => raw::send(&request, &target, connect.as_ref(), tunnel).await,
+            };
+            let raw::Response { url, head, body } = result.map_err(|err| format!("Request failed: {}", err))?;
+            (url, head.version, head.status, head.headers, Source::Raw(body))
+        } else {
+            let response = self.execute(request).await?;
+            let headers = response.headers().clone();
+            (response.url().clone(), response.version(), response.status(), headers, Source::Pooled(response))
+        };
+        self.header_limits.check(&headers)?;
+
+        // The body is decoded here as it streams in, rather than by reqwest,
+        // so what the server chose is known; as reqwest would, the headers
+        // then describe the decoded body
+        let encoding = headers
+            .get(CONTENT_ENCODING)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        let decoder = match encoding.as_deref().filter(|encoding| self.decode.contains(&encoding.trim())) {
+            Some(encoding) => Decoder::new(encoding)
+                .transpose()
+                .map_err(|err| format!("Unable to decode {} response: {}", encoding, err))?,
+            None => None,
+        };
+        if decoder.is_some() {
+            headers.remove(CONTENT_ENCODING);
+            headers.remove(CONTENT_LENGTH);
+        }
+        let decoded = decoder.is_some();
+
+        let mut progress = self.progress.reporter();
+        let decoding = decoder.map(|decoder| (encoding.as_deref().unwrap_or_default(), decoder));
+        let Body {
+            bytes: body,
+            received,
+            decoded_len,
+        } = self
+            .read_body(&mut source, status, decoding, &headers, on_chunk, &mut *progress)
+            .await?;
+        if negotiated {
+            trace::verbose(&match (&encoding, decoded) {
+                (Some(encoding), _) if received == 0 => format!("Content-Encoding: {}, no body to decode", encoding),
+                (Some(encoding), true) => {
+                    format!("Content-Encoding: {}, decoded {} bytes to {}", encoding, received, decoded_len)
+                }
+                (Some(encoding), false) => format!("Content-Encoding: {}, left encoded", encoding),
+                (None, _) => "No Content-Encoding, the response was sent as it is".to_string(),
+            });
+        }
+
+        let exchange = Exchange {
+            url,
+            version,
+            status,
+            headers,
+            body,
+        };
+        Ok((exchange, decoded_len))
+    }
+
+    // Reads a streamed body to the end, decoding it with the decoder for the
+    // Content-Encoding if there is one, and tells `progress` about every chunk
+    // as it comes over the wire
+    async fn read_body(
+        &self,
+        source: &mut Source,
+        status: StatusCode,
+        decoding: Option<(&str, Decoder)>,
+        headers: &HeaderMap,
+        on_chunk: &mut (dyn FnMut(StatusCode, &HeaderMap, &[u8]) -> bool + Send),
+        progress: &mut (dyn ProgressReporter + Send),
+    ) -> Result<Body, String> {
+        let (encoding, mut decoder) = decoding.unzip();
+        let decode_error = |err| format!("Unable to decode {} response: {}", encoding.unwrap_or_default(), err);
+        progress.on_start(source.content_length());
+        let mut body = Body::default();
+        let mut stall = self.speed_limit.map(SpeedLimit::watch);
+        loop {
+            let read = match &mut stall {
+                Some(watch) => watch.wait(source.chunk()).await?,
+                None => source.chunk().await,
+            };
+            let Some(chunk) = read.map_err(|err| format!("Unable to read response body: {}", err))? else {
+                break;
+            };
+            body.received += chunk.len();
+            if let Some(watch) = &mut stall {
+                watch.received(chunk.len() as u64)?;
+            }
+            let decoded = match &mut decoder {
+                Some(decoder) => Cow::Owned(decoder.feed(&chunk).map_err(decode_error)?),
+                None => Cow::Borrowed(&chunk[..]),
+            };
+            body.decoded_len += decoded.len();
+            if !decoded.is_empty() && on_chunk(status, headers, &decoded) {
+                body.bytes.extend_from_slice(&decoded);
+            }
+            progress.on_chunk(chunk.len() as u64);
+            if let Some(limit) = &self.rate_limit {
+                limit.take(chunk.len() as u64).await;
+            }
+        }
+        // An empty body (HEAD, 204, 304) has nothing to decode
+        if let Some(decoder) = decoder.filter(|_| body.received > 0) {
+            let rest = decoder.finish().map_err(decode_error)?;
+            body.decoded_len += rest.len();
+            if !rest.is_empty() && on_chunk(status, headers, &rest) {
+                body.bytes.extend_from_slice(&rest);
+            }
+        }
+        progress.on_finish();
+        Ok(body)
+    }
+}
+
+// Where read_body reads a body from: the pooled client's response, or a raw
+// connection
+enum Source {
+    Pooled(Response),
+    Raw(raw::Body),
+}
+
+impl Source {
+    fn content_length(&self) -> Option<u64> {
+        match self {
+            Source::Pooled(response) => response.content_length(),
+            Source::Raw(body) => body.content_length(),
+        }
+    }
+
+    async fn chunk(&mut self) -> Result<Option<Bytes>, String> {
+        match self {
+            Source::Pooled(response) => response.chunk().await.map_err(|err| err.to_string()),
+            Source::Raw(body) => Ok(body.chunk().await.map_err(|err| err.to_string())?.map(Bytes::from)),
+        }
+    }
+}
+
+// A body as read_body kept it
+#[derive(Default)]
+struct Body {
+    // What on_chunk didn't take
+    bytes: Vec<u8>,
+    // Bytes as they came over the wire, and after decoding
+    received: usize,
+    decoded_len: usize,
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+// Whether a request failed because its connection was closed or reset before
+// a response began, rather than because the server couldn't be reached
+fn is_stale_connection(err: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<hyper::Error>() {
+            if err.is_incomplete_message() || err.is_canceled() {
+                return true;
+            }
+        }
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            if matches!(err.kind(), ErrorKind::ConnectionReset | ErrorKind::BrokenPipe) {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, Part};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[derive(Debug, PartialEq)]
+    enum Call {
+        Start(Option<u64>),
+        Chunk(u64),
+        Finish,
+    }
+
+    // Keeps every call the transport makes, to compare afterwards
+    #[derive(Default)]
+    struct Recorder(Vec<Call>);
+
+    impl ProgressReporter for Recorder {
+        fn on_start(&mut self, total: Option<u64>) {
+            self.0.push(Call::Start(total));
+        }
+
+        fn on_chunk(&mut self, bytes: u64) {
+            self.0.push(Call::Chunk(bytes));
+        }
+
+        fn on_finish(&mut self) {
+            self.0.push(Call::Finish);
+        }
+    }
+
+    fn transport() -> Transport {
+        Transport {
+            client: Client::builder().no_proxy().build().unwrap(),
+            forced: None,
+            version_fallback: false,
+            request_target: None,
+            path_as_is: HashMap::new(),
+            raw_request: None,
+            expect100: None,
+            connect_to: Vec::new(),
+            proxy_tunnel: None,
+            aws_sigv4: None,
+            header_limits: HeaderLimits {
+                max_count: 100,
+                max_bytes: 64 * 1024,
+            },
+            speed_limit: None,
+            rate_limit: None,
+            decode: Vec::new(),
+            location_trusted: false,
+            verbose: false,
+            progress: ProgressMode::None,
+            dedup: None,
+            request_log: None,
+        }
+    }
+
+    // A response whose body arrives in these chunks, then fails if `fail`
+    fn streamed(chunks: Vec<Vec<u8>>, fail: bool) -> Response {
+        let (mut sender, body) = hyper::Body::channel();
+        tokio::spawn(async move {
+            for chunk in chunks {
+                sender.send_data(Bytes::from(chunk)).await.unwrap();
+            }
+            if fail {
+                sender.abort();
+            }
+        });
+        Response::from(hyper::Response::new(body))
+    }
+
+    async fn read(response: Response, decoding: Option<(&str, Decoder)>) -> (Result<Body, String>, Vec<Call>) {
+        let (status, headers) = (response.status(), response.headers().clone());
+        let mut recorder = Recorder::default();
+        let result = transport()
+            .read_body(&mut Source::Pooled(response), status, decoding, &headers, &mut |_, _, _| true, &mut recorder)
+            .await;
+        (result, recorder.0)
+    }
+
+    #[tokio::test]
+    async fn reports_each_chunk() {
+        let chunks = vec![b"ab".to_vec(), b"cde".to_vec(), b"f".to_vec()];
+        let (body, calls) = read(streamed(chunks, false), None).await;
+        assert_eq!(body.unwrap().bytes, b"abcdef");
+        assert_eq!(
+            calls,
+            [Call::Start(None), Call::Chunk(2), Call::Chunk(3), Call::Chunk(1), Call::Finish]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_the_content_length() {
+        let response = Response::from(hyper::Response::new(hyper::Body::from("hello")));
+        let (body, calls) = read(response, None).await;
+        assert_eq!(body.unwrap().bytes, b"hello");
+        assert_eq!(calls, [Call::Start(Some(5)), Call::Chunk(5), Call::Finish]);
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    // Progress counts the bytes on the wire, not the decoded ones
+    #[tokio::test]
+    async fn reports_encoded_bytes() {
+        let gzip = gzip(&[b'x'; 10_000]);
+        let (first, second) = gzip.split_at(gzip.len() / 2);
+        let chunks = vec![first.to_vec(), second.to_vec()];
+
+        let decoder = Decoder::new("gzip").unwrap().unwrap();
+        let (body, calls) = read(streamed(chunks, false), Some(("gzip", decoder))).await;
+        let body = body.unwrap();
+        assert_eq!(body.bytes, [b'x'; 10_000]);
+        assert_eq!((body.received, body.decoded_len), (gzip.len(), 10_000));
+        assert_eq!(
+            calls,
+            [
+                Call::Start(None),
+                Call::Chunk(first.len() as u64),
+                Call::Chunk(second.len() as u64),
+                Call::Finish
+            ]
+        );
+    }
+
+    // A body that fails partway never finishes
+    #[tokio::test]
+    async fn no_finish_after_a_failed_read() {
+        let (body, calls) = read(streamed(vec![b"abc".to_vec()], true), None).await;
+        assert!(body.is_err_and(|err| err.starts_with("Unable to read response body")));
+        assert_eq!(calls, [Call::Start(None), Call::Chunk(3)]);
+    }
+
+    // A transport that reaches example.test through --connect-to, so every
+    // request to it takes the raw path, and the URL to request
+    fn connecting_to(server: &mock::MockServer) -> (Transport, Url) {
+        let rule = format!("example.test:80:127.0.0.1:{}", server.url.port().unwrap());
+        let transport = Transport {
+            connect_to: vec![connect::parse(&rule).unwrap()],
+            ..transport()
+        };
+        (transport, Url::parse("http://example.test/").unwrap())
+    }
+
+    // --compressed decodes a body that came over the raw path, and streams it
+    // to on_chunk, like one from the pooled client
+    #[tokio::test]
+    async fn raw_path_decodes_the_body() {
+        let server = mock::serve(|_| {
+            let body = gzip(&[b'x'; 10_000]);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            );
+            vec![Part::Send(head.into_bytes()), Part::Send(body), Part::Close]
+        })
+        .await;
+        let (transport, url) = connecting_to(&server);
+        let transport = Transport {
+            decode: vec!["gzip"],
+            ..transport
+        };
+        let request = Request::new(Method::GET, url);
+        assert!(transport.uses_raw(&request));
+
+        let mut streamed = Vec::new();
+        let exchange = transport
+            .send_with(request, &mut |_, _, chunk| {
+                streamed.extend_from_slice(chunk);
+                true
+            })
+            .await
+            .unwrap();
+        assert_eq!(exchange.body, [b'x'; 10_000]);
+        assert_eq!(streamed, exchange.body);
+        assert!(!exchange.headers.contains_key(CONTENT_ENCODING));
+    }
+
+    // reqwest 0.11 can't decode zstd, so the transport does, however the
+    // encoded body is split up on the way
+    #[tokio::test]
+    async fn decodes_zstd() {
+        let text = "zstd body ".repeat(2_000);
+        let encoded = zstd::encode_all(text.as_bytes(), 0).unwrap();
+        let server = mock::serve(move |_| {
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Encoding: zstd\r\nContent-Length: {}\r\n\r\n",
+                encoded.len()
+            );
+            let mut parts = vec![Part::Send(head.into_bytes())];
+            parts.extend(encoded.chunks(7).map(|piece| Part::Send(piece.to_vec())));
+            parts
+        })
+        .await;
+        let transport = Transport {
+            decode: vec!["zstd"],
+            ..transport()
+        };
+        let exchange = transport.send(Request::new(Method::GET, server.at("/"))).await.unwrap();
+        assert_eq!(exchange.body, text.as_bytes());
+        assert!(!exchange.headers.contains_key(CONTENT_ENCODING));
+        assert!(!exchange.headers.contains_key(CONTENT_LENGTH));
+    }
+
+    // A server that closes each connection, unanswered, at its second request,
+    // as one that dropped an idle keep-alive connection looks to the client
+    async fn closing_idle_connections() -> mock::MockServer {
+        let answered = Mutex::new(Vec::new());
+        mock::serve(move |request| {
+            let mut answered = answered.lock().unwrap();
+            if answered.contains(&request.connection) {
+                return vec![Part::Close];
+            }
+            answered.push(request.connection);
+            vec![Part::Send(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_vec())]
+        })
+        .await
+    }
+
+    // A GET that finds its pooled connection closed is sent again on a new one;
+    // a POST may already have had its effect, so it fails
+    #[tokio::test]
+    async fn retries_idempotent_requests_on_a_stale_connection() {
+        let server = closing_idle_connections().await;
+        let transport = transport();
+        for _ in 0..2 {
+            let exchange = transport.send(Request::new(Method::GET, server.at("/"))).await.unwrap();
+            assert_eq!(exchange.body, b"ok");
+        }
+        let connections: Vec<_> = server.received().iter().map(|request| request.connection).collect();
+        assert_eq!(connections, [0, 0, 1]);
+
+        let err = transport.send(Request::new(Method::POST, server.at("/"))).await.err().unwrap();
+        assert!(err.starts_with("Request failed"), "{}", err);
+    }
+
+    // --limit-rate caps what concurrent transfers receive together, over the
+    // raw path and the pooled client alike
+    #[tokio::test]
+    async fn rate_limit_caps_total_throughput() {
+        let server = mock::serve(|_| {
+            let head = "HTTP/1.1 200 OK\r\nContent-Length: 50000\r\n\r\n".as_bytes().to_vec();
+            vec![Part::Send(head), Part::Send(vec![b'x'; 50_000]), Part::Close]
+        })
+        .await;
+        let (transport, url) = connecting_to(&server);
+        let transport = Transport {
+            rate_limit: Some(RateLimit::new(100_000)),
+            ..transport
+        };
+
+        let started = Instant::now();
+        let (raw, pooled) = tokio::join!(
+            transport.send(Request::new(Method::GET, url)),
+            transport.send(Request::new(Method::GET, server.at("/")))
+        );
+        assert_eq!(raw.unwrap().body.len() + pooled.unwrap().body.len(), 100_000);
+        // 100 KB at 100 KB/s, from an empty bucket
+        assert!(started.elapsed() >= Duration::from_millis(950), "{:?}", started.elapsed());
+    }
+
+    // --log-requests counts the whole body, including what on_chunk took
+    // (--pretty streaming it to stdout) and so left out of the Exchange
+    #[tokio::test]
+    async fn log_counts_bytes_on_chunk_took() {
+        let server = mock::serve(|_| {
+            vec![Part::Send(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello".to_vec())]
+        })
+        .await;
+        let path = std::env::temp_dir().join(format!("terminal-web-client-log-{}.ndjson", std::process::id()));
+        let transport = Transport {
+            request_log: Some(RequestLog::open(path.to_str().unwrap()).unwrap()),
+            ..transport()
+        };
+        let exchange = transport
+            .send_with(Request::new(Method::GET, server.at("/")), &mut |_, _, _| false)
+            .await
+            .unwrap();
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(exchange.body.is_empty());
+        assert!(log.contains("\"bytes\":5,"), "{}", log);
+    }
+
+    // A server that answers every request with its number, after `delay`,
+    // and counts them
+    async fn counting_server(delay: Duration) -> (Url, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/page", listener.local_addr().unwrap())).unwrap();
+        let count = Arc::new(AtomicUsize::new(0));
+        let counted = count.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let n = counted.fetch_add(1, Ordering::SeqCst) + 1;
+                tokio::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut buf = [0; 1024];
+                    while !head.ends_with(b"\r\n\r\n") {
+                        let read = stream.read(&mut buf).await.unwrap();
+                        head.extend_from_slice(&buf[..read]);
+                    }
+                    tokio::time::sleep(delay).await;
+                    let body = n.to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        (url, count)
+    }
+
+    fn deduplicating() -> Transport {
+        Transport {
+            dedup: Some(Mutex::default()),
+            ..transport()
+        }
+    }
+
+    #[tokio::test]
+    async fn dedup_coalesces_requests_in_flight() {
+        let (url, count) = counting_server(Duration::from_millis(200)).await;
+        let transport = deduplicating();
+        let (first, second) = tokio::join!(
+            transport.send(Request::new(Method::GET, url.clone())),
+            transport.send(Request::new(Method::GET, url.clone()))
+        );
+        assert_eq!(first.unwrap().body, b"1");
+        assert_eq!(second.unwrap().body, b"1");
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // Later requests for the URL reuse it too
+        assert_eq!(transport.send(Request::new(Method::GET, url)).await.unwrap().body, b"1");
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn dedup_sends_again_once_forgotten() {
+        let (url, count) = counting_server(Duration::ZERO).await;
+        let transport = deduplicating();
+        assert_eq!(transport.send(Request::new(Method::GET, url.clone())).await.unwrap().body, b"1");
+        transport.forget(&url);
+        assert_eq!(transport.send(Request::new(Method::GET, url.clone())).await.unwrap().body, b"2");
+        assert_eq!(transport.send(Request::new(Method::GET, url)).await.unwrap().body, b"2");
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn dedup_leaves_other_methods_alone() {
+        let (url, count) = counting_server(Duration::ZERO).await;
+        let transport = deduplicating();
+        for _ in 0..2 {
+            transport.send(Request::new(Method::POST, url.clone())).await.unwrap();
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    // A failed request isn't kept, so the next one for the URL is sent
+    #[tokio::test]
+    async fn dedup_keeps_no_failures() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+        let transport = deduplicating();
+        assert!(transport.send(Request::new(Method::GET, url.clone())).await.is_err());
+        let cache = transport.dedup.as_ref().unwrap().lock().unwrap();
+        assert!(cache.values().all(|cached| cached.get().is_none()));
+    }
+}
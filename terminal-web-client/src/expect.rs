@@ -0,0 +1,365 @@
+use crate::exchange::Exchange;
+use regex::{Regex, RegexBuilder};
+use reqwest::header::{HeaderMap, HeaderName};
+use reqwest::StatusCode;
+use std::fmt;
+
+// How much of the body to show when a body expectation fails
+const EXCERPT_BYTES: usize = 256;
+
+// Everything a response is checked against before the request counts as a success
+pub struct Expectations {
+    status: Vec<StatusRange>,
+    headers: Vec<(HeaderAssertion, Option<Regex>)>,
+    body_contains: Vec<String>,
+    body_matches: Vec<Regex>,
+    ignore_case: bool,
+}
+
+impl Expectations {
+    pub fn new(
+        status: Vec<StatusRange>,
+        headers: Vec<HeaderAssertion>,
+        body_contains: Vec<String>,
+        body_patterns: Vec<String>,
+        ignore_case: bool,
+    ) -> Result<Self, String> {
+        let headers = headers
+            .into_iter()
+            .map(|assertion| {
+                let regex = match assertion.operator {
+                    Operator::Matches => Some(
+                        RegexBuilder::new(&assertion.value)
+                            .case_insensitive(ignore_case)
+                            .build()
+                            .map_err(|err| format!("Invalid --expect-header pattern \"{}\": {}", assertion.value, err))?,
+                    ),
+                    _ => None,
+                };
+                Ok((assertion, regex))
+            })
+            .collect::<Result<_, String>>()?;
+        let body_matches = body_patterns
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(ignore_case)
+                    .build()
+                    .map_err(|err| format!("Invalid --expect-body-matches pattern \"{}\": {}", pattern, err))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Expectations {
+            status,
+            headers,
+            body_contains,
+            body_matches,
+            ignore_case,
+        })
+    }
+
+    // Without --expect-status any 2xx is fine
+    pub fn check_status(&self, status: StatusCode) -> Result<(), String> {
+        if self.status.is_empty() && !status.is_success() {
+            Err(format!("Request failed with status: {}", status))
+        } else if !self.status.is_empty() && !self.status.iter().any(|range| range.contains(status)) {
+            Err(format!("Unexpected status: expected {}, got {}", describe(&self.status), status))
+        } else {
+            Ok(())
+        }
+    }
+
+    // Checks the status, headers and body all at once, so a failure reports
+    // every expectation that didn't hold, one per line, rather than the first
+    pub fn check(&self, exchange: &Exchange) -> Result<(), String> {
+        let failures: Vec<String> = [
+            self.check_status(exchange.status),
+            self.check_headers(&exchange.headers),
+            self.check_body(&exchange.body),
+        ]
+        .into_iter()
+        .filter_map(Result::err)
+        .collect();
+        match failures.is_empty() {
+            true => Ok(()),
+            false => Err(failures.join("\n")),
+        }
+    }
+
+    fn check_headers(&self, headers: &HeaderMap) -> Result<(), String> {
+        let failures: Vec<String> = self
+            .headers
+            .iter()
+            .filter_map(|(assertion, regex)| {
+                let values: Vec<String> = headers
+                    .get_all(&assertion.name)
+                    .iter()
+                    .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+                    .collect();
+                if values.is_empty() {
+                    return Some(format!("Header {} failed: no {} header", assertion, assertion.name));
+                }
+                // Any one of a repeated header's values will do
+                let holds = |value: &String| match (assertion.operator, regex) {
+                    (_, Some(regex)) => regex.is_match(value),
+                    (Operator::Equals, _) if self.ignore_case => value.eq_ignore_ascii_case(&assertion.value),
+                    (Operator::Equals, _) => *value == assertion.value,
+                    _ if self.ignore_case => value.to_lowercase().contains(&assertion.value.to_lowercase()),
+                    _ => value.contains(assertion.value.as_str()),
+                };
+                match values.iter().any(holds) {
+                    true => None,
+                    false => Some(format!("Header {} failed: got \"{}\"", assertion, values.join("\", \""))),
+                }
+            })
+            .collect();
+        match failures.is_empty() {
+            true => Ok(()),
+            false => Err(failures.join("\n")),
+        }
+    }
+
+    fn check_body(&self, body: &[u8]) -> Result<(), String> {
+        let text = String::from_utf8_lossy(body);
+        let mut failures = Vec::new();
+
+        for needle in &self.body_contains {
+            let found = if self.ignore_case {
+                text.to_lowercase().contains(&needle.to_lowercase())
+            } else {
+                text.contains(needle.as_str())
+            };
+            if !found {
+                failures.push(format!("Body does not contain \"{}\"", needle));
+            }
+        }
+        for pattern in &self.body_matches {
+            if !pattern.is_match(&text) {
+                failures.push(format!("Body does not match /{}/", pattern.as_str()));
+            }
+        }
+
+        if failures.is_empty() {
+            return Ok(());
+        }
+        let excerpt = String::from_utf8_lossy(&body[..body.len().min(EXCERPT_BYTES)]);
+        failures.push(format!(
+            "First {} of {} body bytes:\n{}",
+            body.len().min(EXCERPT_BYTES),
+            body.len(),
+            excerpt
+        ));
+        Err(failures.join("\n"))
+    }
+}
+
+// One --expect-header value, "name==value" (equals), "name~=value"
+// (contains) or "name=~regex" (matches)
+#[derive(Clone)]
+pub struct HeaderAssertion {
+    name: HeaderName,
+    operator: Operator,
+    value: String,
+}
+
+#[derive(Clone, Copy)]
+enum Operator {
+    Equals,
+    Contains,
+    Matches,
+}
+
+impl fmt::Display for HeaderAssertion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operator = match self.operator {
+            Operator::Equals => "==",
+            Operator::Contains => "~=",
+            Operator::Matches => "=~",
+        };
+        write!(f, "\"{}{}{}\"", self.name, operator, self.value)
+    }
+}
+
+// clap value parser for --expect-header. A header name can't contain "=" or
+// "~", so the operator starts at the first of either.
+pub fn parse_header_assertion(value: &str) -> Result<HeaderAssertion, String> {
+    let expected = || format!("expected name==value, name~=value or name=~regex, found \"{}\"", value);
+    let at = value.find(['=', '~']).ok_or_else(expected)?;
+    let operator = match value.get(at..at + 2) {
+        Some("==") => Operator::Equals,
+        Some("~=") => Operator::Contains,
+        Some("=~") => Operator::Matches,
+        _ => return Err(expected()),
+    };
+    let name = HeaderName::from_bytes(value[..at].trim().as_bytes())
+        .map_err(|_| format!("invalid header name \"{}\"", value[..at].trim()))?;
+    Ok(HeaderAssertion {
+        name,
+        operator,
+        value: value[at + 2..].to_string(),
+    })
+}
+
+// One --expect-status value: a single code ("301") or an inclusive range ("200-299")
+#[derive(Clone, Copy)]
+pub struct StatusRange {
+    start: u16,
+    end: u16,
+}
+
+impl StatusRange {
+    pub fn contains(&self, status: StatusCode) -> bool {
+        (self.start..=self.end).contains(&status.as_u16())
+    }
+}
+
+impl fmt::Display for StatusRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+// clap value parser for --expect-status
+pub fn parse_status_range(value: &str) -> Result<StatusRange, String> {
+    let code = |part: &str| match part.trim().parse::<u16>() {
+        Ok(code) if (100..=999).contains(&code) => Ok(code),
+        _ => Err(format!("\"{}\" is not a status code (100-999)", part.trim())),
+    };
+    let (start, end) = match value.split_once('-') {
+        Some((start, end)) => (code(start)?, code(end)?),
+        None => (code(value)?, code(value)?),
+    };
+    if start > end {
+        return Err(format!("Range \"{}\" is empty", value));
+    }
+    Ok(StatusRange { start, end })
+}
+
+// Formats a set of ranges for messages, e.g. "200-299, 301"
+fn describe(ranges: &[StatusRange]) -> String {
+    ranges.iter().map(|range| range.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: u16) -> StatusCode {
+        StatusCode::from_u16(code).unwrap()
+    }
+
+    fn expecting(ranges: &[&str]) -> Expectations {
+        let ranges = ranges.iter().map(|range| parse_status_range(range).unwrap()).collect();
+        Expectations::new(ranges, Vec::new(), Vec::new(), Vec::new(), false).unwrap()
+    }
+
+    #[test]
+    fn single_code() {
+        let range = parse_status_range("301").unwrap();
+        assert!(range.contains(status(301)));
+        assert!(!range.contains(status(300)) && !range.contains(status(302)));
+        assert_eq!(range.to_string(), "301");
+    }
+
+    #[test]
+    fn inclusive_range() {
+        let range = parse_status_range("200-299").unwrap();
+        assert!(range.contains(status(200)) && range.contains(status(250)) && range.contains(status(299)));
+        assert!(!range.contains(status(199)) && !range.contains(status(300)));
+        assert_eq!(range.to_string(), "200-299");
+    }
+
+    #[test]
+    fn rejects_reversed_and_invalid_ranges() {
+        assert_eq!(parse_status_range("299-200").err().unwrap(), "Range \"299-200\" is empty");
+        for value in ["99", "1000", "2xx", "", "200-", "-299", "200-1000"] {
+            assert!(parse_status_range(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn status_outside_every_range_fails() {
+        let expectations = expecting(&["200-299", "301"]);
+        assert!(expectations.check_status(status(204)).is_ok());
+        assert!(expectations.check_status(status(301)).is_ok());
+        assert_eq!(
+            expectations.check_status(status(404)).err().unwrap(),
+            "Unexpected status: expected 200-299, 301, got 404 Not Found"
+        );
+        // Without --expect-status any 2xx passes
+        let expectations = expecting(&[]);
+        assert!(expectations.check_status(status(201)).is_ok());
+        assert!(expectations.check_status(status(302)).is_err());
+    }
+
+    fn asserting(assertions: &[&str], ignore_case: bool) -> Expectations {
+        let assertions = assertions
+            .iter()
+            .map(|assertion| parse_header_assertion(assertion).unwrap())
+            .collect();
+        Expectations::new(Vec::new(), assertions, Vec::new(), Vec::new(), ignore_case).unwrap()
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(HeaderName::from_bytes(name.as_bytes()).unwrap(), value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn header_equals() {
+        let response = headers(&[("content-type", "application/json")]);
+        assert!(asserting(&["Content-Type==application/json"], false).check_headers(&response).is_ok());
+        assert_eq!(
+            asserting(&["Content-Type==application"], false).check_headers(&response).err().unwrap(),
+            "Header \"content-type==application\" failed: got \"application/json\""
+        );
+    }
+
+    #[test]
+    fn header_contains() {
+        let response = headers(&[("cache-control", "public, max-age=60")]);
+        assert!(asserting(&["Cache-Control~=max-age"], false).check_headers(&response).is_ok());
+        assert!(asserting(&["Cache-Control~=no-store"], false).check_headers(&response).is_err());
+    }
+
+    #[test]
+    fn header_matches() {
+        let response = headers(&[("etag", "\"v42\"")]);
+        assert!(asserting(&["ETag=~^\"v[0-9]+\"$"], false).check_headers(&response).is_ok());
+        assert!(asserting(&["ETag=~^v[0-9]+$"], false).check_headers(&response).is_err());
+        let unclosed = vec![parse_header_assertion("ETag=~(").unwrap()];
+        assert!(Expectations::new(Vec::new(), unclosed, Vec::new(), Vec::new(), false).is_err());
+    }
+
+    // Header names never depend on case; values only do without --ignore-case
+    #[test]
+    fn header_names_are_case_insensitive() {
+        let response = headers(&[("x-request-id", "ABC")]);
+        assert!(asserting(&["X-REQUEST-ID==ABC"], false).check_headers(&response).is_ok());
+        assert!(asserting(&["x-Request-Id==abc"], false).check_headers(&response).is_err());
+        assert!(asserting(&["x-Request-Id==abc", "X-Request-ID~=b"], true).check_headers(&response).is_ok());
+    }
+
+    #[test]
+    fn missing_header_and_any_repeated_value() {
+        let response = headers(&[("set-cookie", "a=1"), ("set-cookie", "b=2")]);
+        assert!(asserting(&["Set-Cookie==b=2"], false).check_headers(&response).is_ok());
+        assert_eq!(
+            asserting(&["Location~=/"], false).check_headers(&response).err().unwrap(),
+            "Header \"location~=/\" failed: no location header"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_header_assertions() {
+        for value in ["Content-Type", "Content-Type=json", "Content-Type~json", "Bad Name==x", "==x"] {
+            assert!(parse_header_assertion(value).is_err(), "{}", value);
+        }
+    }
+}
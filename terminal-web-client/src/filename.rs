@@ -0,0 +1,204 @@
+use crate::sigv4::percent_decode;
+use reqwest::Url;
+
+// Longest file name written, in bytes; most file systems allow 255
+const MAX_NAME_BYTES: usize = 255;
+
+// The file name -O saves to: the last segment of the URL's path, decoded
+pub fn from_url(url: &Url) -> Option<String> {
+    let segment = url.path_segments()?.next_back()?;
+    sanitize(&String::from_utf8_lossy(&percent_decode(segment)))
+}
+
+// The file name a Content-Disposition header suggests (-J). An RFC 5987
+// `filename*=charset'lang'value` wins over a plain `filename=`, quoted or
+// not, as RFC 6266 says. None if there is no usable name.
+pub fn from_content_disposition(value: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for param in params(value).into_iter().skip(1) {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename" => plain = Some(unquote(value.trim())),
+            "filename*" => extended = decode_extended(value.trim()),
+            _ => {}
+        }
+    }
+    extended.and_then(|name| sanitize(&name)).or_else(|| sanitize(&plain?))
+}
+
+// Makes a server-supplied name safe to create in the working directory: only
+// the part after the last "/" or "\" is kept, control characters are dropped,
+// and what remains must not be empty, only dots ("..") or a Windows drive
+pub fn sanitize(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim();
+    if name.chars().all(|c| c == '.') || name.contains(':') {
+        return None;
+    }
+
+    let mut end = name.len().min(MAX_NAME_BYTES);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(name[..end].to_string())
+}
+
+// Splits a header value on ";" outside of quoted strings
+fn params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+// A token as it is, or a quoted string without its quotes and escapes
+fn unquote(value: &str) -> String {
+    let Some(inner) = value.strip_prefix('"') else {
+        return value.to_string();
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// An RFC 5987 ext-value, "UTF-8'en'na%C3%AFve.txt". Only UTF-8 and
+// ISO-8859-1 are defined; anything else is ignored.
+fn decode_extended(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let (charset, _language, encoded) = (parts.next()?, parts.next()?, parts.next()?);
+    let bytes = percent_decode(encoded);
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disposition(value: &str) -> Option<String> {
+        from_content_disposition(value)
+    }
+
+    #[test]
+    fn plain_filename() {
+        assert_eq!(disposition("attachment; filename=\"report.pdf\""), Some("report.pdf".to_string()));
+        assert_eq!(disposition("attachment; filename=report.pdf"), Some("report.pdf".to_string()));
+        assert_eq!(disposition("attachment; FileName = report.pdf "), Some("report.pdf".to_string()));
+        assert_eq!(
+            disposition("attachment; filename=\"a \\\"b\\\"; c.txt\""),
+            Some("a \"b\"; c.txt".to_string())
+        );
+    }
+
+    #[test]
+    fn extended_filename() {
+        assert_eq!(
+            disposition("attachment; filename*=UTF-8''na%C3%AFve%20file.txt"),
+            Some("naïve file.txt".to_string())
+        );
+        assert_eq!(
+            disposition("attachment; filename*=iso-8859-1'en'caf%E9.txt"),
+            Some("café.txt".to_string())
+        );
+    }
+
+    #[test]
+    fn extended_filename_wins() {
+        let both = Some("€ rates.csv".to_string());
+        assert_eq!(
+            disposition("attachment; filename=\"rates.csv\"; filename*=UTF-8''%E2%82%AC%20rates.csv"),
+            both
+        );
+        assert_eq!(
+            disposition("attachment; filename*=UTF-8''%E2%82%AC%20rates.csv; filename=\"rates.csv\""),
+            both
+        );
+        // An extended name that can't be used leaves the plain one
+        assert_eq!(
+            disposition("attachment; filename=\"rates.csv\"; filename*=KOI8-R''%E2"),
+            Some("rates.csv".to_string())
+        );
+        assert_eq!(
+            disposition("attachment; filename=\"rates.csv\"; filename*=UTF-8''.."),
+            Some("rates.csv".to_string())
+        );
+    }
+
+    #[test]
+    fn traversal_keeps_the_last_segment() {
+        assert_eq!(disposition("attachment; filename=\"../../etc/passwd\""), Some("passwd".to_string()));
+        assert_eq!(disposition("attachment; filename=\"/etc/passwd\""), Some("passwd".to_string()));
+        assert_eq!(
+            disposition("attachment; filename=\"..\\\\..\\\\boot.ini\""),
+            Some("boot.ini".to_string())
+        );
+        assert_eq!(
+            disposition("attachment; filename*=UTF-8''..%2F..%5Cevil.sh"),
+            Some("evil.sh".to_string())
+        );
+        assert_eq!(sanitize("C:evil.exe"), None);
+    }
+
+    // No usable name gives None, and the -o/-O name is used instead
+    #[test]
+    fn unusable_names() {
+        for value in [
+            "attachment",
+            "attachment; filename=",
+            "attachment; filename=\"\"",
+            "attachment; filename=\".\"",
+            "attachment; filename=\"..\"",
+            "attachment; filename=\"...\"",
+            "attachment; filename=\"dir/\"",
+            "attachment; filename=\"../..\"",
+            "attachment; filename=\" \t \"",
+        ] {
+            assert_eq!(disposition(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn sanitize_names() {
+        assert_eq!(sanitize(" a\u{7}b.txt "), Some("ab.txt".to_string()));
+        assert_eq!(sanitize(".hidden"), Some(".hidden".to_string()));
+        let long = "é".repeat(200);
+        let kept = sanitize(&long).unwrap();
+        assert_eq!(kept.len(), 254);
+        assert!(long.starts_with(&kept));
+    }
+
+    #[test]
+    fn names_from_urls() {
+        let name = |url: &str| from_url(&Url::parse(url).unwrap());
+        assert_eq!(name("https://host/files/a%20b.tar.gz?x=1"), Some("a b.tar.gz".to_string()));
+        assert_eq!(name("https://host/files/..%2F..%2Fpasswd"), Some("passwd".to_string()));
+        assert_eq!(name("https://host/files/"), None);
+        assert_eq!(name("https://host"), None);
+    }
+}
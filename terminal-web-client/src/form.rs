@@ -0,0 +1,215 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// multipart/form-data encoding for -F/--form and --form-string. The body is
+// built up front so the request stays cloneable (--repeat) and printable
+// (--dry-run, --trace-ascii).
+
+pub struct Part {
+    name: String,
+    file_name: Option<String>,
+    data: Vec<u8>,
+}
+
+impl Part {
+    // --form-string: the value is always sent verbatim
+    pub fn literal(arg: &str) -> Result<Self, String> {
+        let (name, value) = split_field(arg)?;
+        Ok(Part::text(name, value))
+    }
+
+    // -F/--form: "name=@path" uploads a file, "name=<path" sends the file's
+    // contents as a plain field, anything else is sent verbatim
+    pub fn parse(arg: &str) -> Result<Self, String> {
+        let (name, value) = split_field(arg)?;
+        if let Some(path) = value.strip_prefix('@') {
+            Part::file(name, path)
+        } else if let Some(path) = value.strip_prefix('<') {
+            Ok(Part {
+                name: name.to_string(),
+                file_name: None,
+                data: read_file(path)?,
+            })
+        } else {
+            Part::literal(arg)
+        }
+    }
+
+    // A file upload, named after the file
+    fn file(name: &str, path: &str) -> Result<Self, String> {
+        let file_name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+        Ok(Part {
+            name: name.to_string(),
+            file_name: Some(file_name),
+            data: read_file(path)?,
+        })
+    }
+
+    fn text(name: &str, value: &str) -> Self {
+        Part {
+            name: name.to_string(),
+            file_name: None,
+            data: value.as_bytes().to_vec(),
+        }
+    }
+}
+
+// --form-json: the fields of a JSON object, in the object's order,
+// {"name": "value", "upload": "@path"}. A string starting with "@" uploads
+// that file, as -F does; other strings, numbers and booleans are sent as
+// text, and an array sends its field once per element. null and nested
+// objects have no form equivalent and are rejected.
+pub fn from_json(text: &str) -> Result<Vec<Part>, String> {
+    let invalid = |message: String| format!("Invalid --form-json: {}", message);
+    let fields = match serde_json::from_str::<Value>(text).map_err(|err| invalid(err.to_string()))? {
+        Value::Object(fields) if !fields.is_empty() => fields,
+        Value::Object(_) => return Err(invalid("the object has no fields".to_string())),
+        _ => return Err(invalid("expected an object of fields".to_string())),
+    };
+    let mut parts = Vec::new();
+    for (name, value) in &fields {
+        let values = match value {
+            Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            parts.push(match value {
+                Value::String(value) => match value.strip_prefix('@') {
+                    Some(path) => Part::file(name, path)?,
+                    None => Part::text(name, value),
+                },
+                Value::Number(_) | Value::Bool(_) => Part::text(name, &value.to_string()),
+                _ => return Err(invalid(format!("\"{}\" must be a string, number, boolean or array of them", name))),
+            });
+        }
+    }
+    Ok(parts)
+}
+
+// Encodes the parts, returning the Content-Type header value and the body
+pub fn encode(parts: &[Part]) -> (String, Vec<u8>) {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let boundary = format!("------------------------{:016x}", nanos as u64 ^ std::process::id() as u64);
+
+    let mut body = Vec::new();
+    for part in parts {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        let mut disposition = format!("Content-Disposition: form-data; name=\"{}\"", escape(&part.name));
+        if let Some(file_name) = &part.file_name {
+            disposition.push_str(&format!("; filename=\"{}\"", escape(file_name)));
+        }
+        body.extend_from_slice(disposition.as_bytes());
+        body.extend_from_slice(b"\r\n");
+        if part.file_name.is_some() {
+            body.extend_from_slice(b"Content-Type: application/octet-stream\r\n");
+        }
+        body.extend_from_slice(b"\r\n");
+        body.extend_from_slice(&part.data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+fn split_field(arg: &str) -> Result<(&str, &str), String> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name, value)),
+        _ => Err(format!("Form field should be \"name=value\", found \"{}\"", arg)),
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|err| format!("Unable to read form file \"{}\": {}", path, err))
+}
+
+// Quotes a name or filename for Content-Disposition. A line break would end
+// the header and let the value add headers of its own, so CR and LF are
+// percent-encoded, as browsers do.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(parts: &[Part]) -> String {
+        String::from_utf8(encode(parts).1).unwrap()
+    }
+
+    // --form-string sends "@" and "<" values verbatim instead of reading a file
+    #[test]
+    fn form_string_never_reads_a_file() {
+        for value in ["@home", "<home"] {
+            let part = Part::literal(&format!("note={}", value)).unwrap();
+            assert_eq!(part.file_name, None);
+            assert_eq!(part.data, value.as_bytes());
+            assert!(body(&[part]).contains(&format!("name=\"note\"\r\n\r\n{}\r\n", value)));
+        }
+        // -F reads it
+        let err = Part::parse("note=@/nonexistent/home").err().unwrap();
+        assert!(err.starts_with("Unable to read form file"), "{}", err);
+    }
+
+    // A text field and a file upload, in the object's order
+    #[test]
+    fn form_json_builds_two_fields() {
+        let path = std::env::temp_dir().join(format!("terminal-web-client-form-{}.txt", std::process::id()));
+        fs::write(&path, "file contents").unwrap();
+        let json = serde_json::json!({"a": "1", "upload": format!("@{}", path.display())}).to_string();
+        let parts = from_json(&json);
+        fs::remove_file(&path).unwrap();
+
+        let parts = parts.unwrap();
+        assert_eq!(parts.len(), 2);
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let body = body(&parts);
+        let text = body.find("name=\"a\"\r\n\r\n1\r\n").unwrap();
+        let file = body
+            .find(&format!(
+                "name=\"upload\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\nfile contents\r\n",
+                file_name
+            ))
+            .unwrap();
+        assert!(text < file);
+    }
+
+    #[test]
+    fn form_json_rejects_what_a_form_cant_hold() {
+        for json in ["[]", "{}", "{\"a\":null}", "{\"a\":{\"b\":1}}", "{\"a\":"] {
+            let err = from_json(json).err().unwrap();
+            assert!(err.starts_with("Invalid --form-json"), "{}: {}", json, err);
+        }
+        let err = from_json("{\"a\":\"@/nonexistent/file\"}").err().unwrap();
+        assert!(err.starts_with("Unable to read form file"), "{}", err);
+    }
+
+    #[test]
+    fn line_breaks_cant_add_headers() {
+        let part = Part {
+            name: "a\r\nX-Injected: 1".to_string(),
+            file_name: Some("f\"\n.txt".to_string()),
+            data: b"v".to_vec(),
+        };
+        let body = body(&[part]);
+        assert!(!body.contains("\nX-Injected"), "{}", body);
+        assert!(
+            body.contains("name=\"a%0D%0AX-Injected: 1\"; filename=\"f\\\"%0A.txt\"\r\n"),
+            "{}",
+            body
+        );
+    }
+}
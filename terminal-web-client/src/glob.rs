@@ -0,0 +1,224 @@
+// curl-style URL globbing: "{a,b,c}" alternatives and "[1-10]", "[01-10]",
+// "[a-z]" or "[0-100:10]" ranges expand one URL into many. A backslash keeps
+// the next character literal, and brackets that aren't a valid range (an IPv6
+// host like "[::1]") are left alone.
+
+// Refuse to expand a pattern into more URLs than this
+const MAX_URLS: usize = 100_000;
+
+// One concrete URL and the text each glob in the pattern matched, in order,
+// for "#1"-style placeholders
+pub struct Expanded {
+    pub url: String,
+    pub matches: Vec<String>,
+}
+
+enum Segment {
+    Literal(String),
+    Glob(Vec<String>),
+}
+
+pub fn expand(pattern: &str) -> Result<Vec<Expanded>, String> {
+    let segments = parse(pattern)?;
+
+    let mut expanded = vec![Expanded {
+        url: String::new(),
+        matches: Vec::new(),
+    }];
+    for segment in &segments {
+        match segment {
+            Segment::Literal(text) => expanded.iter_mut().for_each(|url| url.url.push_str(text)),
+            Segment::Glob(values) => {
+                if expanded.len() * values.len() > MAX_URLS {
+                    return Err(format!("URL pattern \"{}\" expands to more than {} URLs", pattern, MAX_URLS));
+                }
+                // Earlier globs vary slowest, as in curl
+                expanded = expanded
+                    .iter()
+                    .flat_map(|url| {
+                        values.iter().map(move |value| {
+                            let mut matches = url.matches.clone();
+                            matches.push(value.clone());
+                            Expanded {
+                                url: format!("{}{}", url.url, value),
+                                matches,
+                            }
+                        })
+                    })
+                    .collect();
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+fn parse(pattern: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut rest = pattern;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '\\' if rest.len() > 1 => {
+                let escaped = rest[1..].chars().next().unwrap();
+                literal.push(escaped);
+                rest = &rest[1 + escaped.len_utf8()..];
+            }
+            '{' => {
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| format!("Unmatched \"{{\" in URL pattern \"{}\"", pattern))?;
+                let values: Vec<String> = rest[1..end].split(',').map(String::from).collect();
+                segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                segments.push(Segment::Glob(values));
+                rest = &rest[end + 1..];
+            }
+            '[' => match rest.find(']').and_then(|end| range(&rest[1..end]).map(|values| (end, values))) {
+                Some((end, values)) => {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    segments.push(Segment::Glob(values?));
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    literal.push(c);
+                    rest = &rest[1..];
+                }
+            },
+            c => {
+                literal.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    segments.push(Segment::Literal(literal));
+    Ok(segments)
+}
+
+// The values of a "start-end[:step]" range, None if `spec` isn't shaped like
+// one, or an error if it is but the range is unusable
+fn range(spec: &str) -> Option<Result<Vec<String>, String>> {
+    let (bounds, step) = match spec.split_once(':') {
+        Some((bounds, step)) => (bounds, step.parse::<usize>().ok()?),
+        None => (spec, 1),
+    };
+    let (start, end) = bounds.split_once('-')?;
+    if step == 0 {
+        return Some(Err(format!("Invalid step in URL range \"[{}]\"", spec)));
+    }
+
+    // Numeric, zero-padded to the width of the start when it has leading zeros
+    if let (Ok(first), Ok(last)) = (start.parse::<usize>(), end.parse::<usize>()) {
+        if first > last {
+            return Some(Err(format!("Invalid URL range \"[{}]\": start is after end", spec)));
+        }
+        if (last - first) / step >= MAX_URLS {
+            return Some(Err(format!("URL range \"[{}]\" is too large", spec)));
+        }
+        let width = if start.starts_with('0') { start.len() } else { 0 };
+        let values = (first..=last)
+            .step_by(step)
+            .map(|n| format!("{:0width$}", n, width = width))
+            .collect();
+        return Some(Ok(values));
+    }
+
+    // Single letters of the same case
+    let mut start_chars = start.chars();
+    let mut end_chars = end.chars();
+    match (start_chars.next(), start_chars.next(), end_chars.next(), end_chars.next()) {
+        (Some(first), None, Some(last), None)
+            if first.is_ascii_alphabetic()
+                && last.is_ascii_alphabetic()
+                && first.is_ascii_lowercase() == last.is_ascii_lowercase() =>
+        {
+            if first > last {
+                return Some(Err(format!("Invalid URL range \"[{}]\": start is after end", spec)));
+            }
+            Some(Ok((first..=last).step_by(step).map(String::from).collect()))
+        }
+        _ => None,
+    }
+}
+
+// The highest "#N" placeholder in an output template, 0 if there are none
+pub fn max_placeholder(template: &str) -> usize {
+    placeholders(template).map(|(_, n)| n).max().unwrap_or(0)
+}
+
+// The first glob (numbered from 1) that matches different text across the
+// expanded URLs but has no "#N" in the template, so that every URL it tells
+// apart would be written to the same file
+pub fn unused_glob(template: &str, expanded: &[Expanded]) -> Option<usize> {
+    let first = expanded.first()?;
+    (1..=first.matches.len()).find(|&n| {
+        let varies = expanded.iter().any(|url| url.matches[n - 1] != first.matches[n - 1]);
+        varies && !placeholders(template).any(|(_, used)| used == n)
+    })
+}
+
+// Replaces "#1", "#2", ... in an output template with what those globs matched
+pub fn fill(template: &str, matches: &[String]) -> String {
+    let mut filled = String::new();
+    let mut copied = 0;
+    for (range, n) in placeholders(template) {
+        filled.push_str(&template[copied..range.start]);
+        filled.push_str(matches.get(n - 1).map_or(&template[range.clone()], String::as_str));
+        copied = range.end;
+    }
+    filled.push_str(&template[copied..]);
+    filled
+}
+
+// Each "#N" (N >= 1) in a template, with its byte range
+fn placeholders(template: &str) -> impl Iterator<Item = (std::ops::Range<usize>, usize)> + '_ {
+    template.match_indices('#').filter_map(move |(start, _)| {
+        let digits = template[start + 1..].bytes().take_while(u8::is_ascii_digit).count();
+        let n = template[start + 1..start + 1 + digits].parse::<usize>().ok()?;
+        (n >= 1).then_some((start..start + 1 + digits, n))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(pattern: &str) -> Vec<String> {
+        expand(pattern).unwrap().into_iter().map(|expanded| expanded.url).collect()
+    }
+
+    #[test]
+    fn expands_alternatives_and_ranges() {
+        assert_eq!(urls("http://h/{a,b}/[1-2]"), ["http://h/a/1", "http://h/a/2", "http://h/b/1", "http://h/b/2"]);
+        assert_eq!(urls("http://h/[08-10]"), ["http://h/08", "http://h/09", "http://h/10"]);
+        assert_eq!(urls("http://h/[a-e:2]"), ["http://h/a", "http://h/c", "http://h/e"]);
+        assert_eq!(urls("http://[::1]/\\{x}"), ["http://[::1]/{x}"]);
+    }
+
+    #[test]
+    fn fills_placeholders() {
+        let matches = vec!["a".to_string(), "7".to_string()];
+        assert_eq!(fill("#2-#1.txt", &matches), "7-a.txt");
+        assert_eq!(fill("#3#0#.txt", &matches), "#3#0#.txt");
+        assert_eq!(max_placeholder("#2-#1-#12"), 12);
+        assert_eq!(max_placeholder("plain.txt"), 0);
+    }
+
+    #[test]
+    fn finds_unused_globs() {
+        let expanded = expand("http://h/{a,b}/[1-3]").unwrap();
+        assert_eq!(unused_glob("#1_#2.txt", &expanded), None);
+        assert_eq!(unused_glob("#2_#1.txt", &expanded), None);
+        assert_eq!(unused_glob("#1.txt", &expanded), Some(2));
+        assert_eq!(unused_glob("#2.txt", &expanded), Some(1));
+        assert_eq!(unused_glob("out.txt", &expanded), Some(1));
+        assert_eq!(unused_glob("#12.txt", &expanded), Some(1));
+    }
+
+    // A glob with a single value names one URL, so it needn't be in the name
+    #[test]
+    fn single_value_globs_need_no_placeholder() {
+        let expanded = expand("http://h/{only}/[1-2]").unwrap();
+        assert_eq!(unused_glob("#2.txt", &expanded), None);
+        assert_eq!(unused_glob("out.txt", &expand("http://h/plain").unwrap()), None);
+    }
+}
@@ -0,0 +1,147 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::error::Error;
+use std::fmt;
+
+// Why a "Name: value" line isn't a header
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeaderError {
+    MissingColon(String),
+    EmptyName(String),
+    InvalidName(String),
+    InvalidValue(String),
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeaderError::MissingColon(line) => write!(f, "expected \"Name: value\", found \"{}\"", line),
+            HeaderError::EmptyName(line) => write!(f, "header has no name: \"{}\"", line),
+            HeaderError::InvalidName(name) => write!(f, "invalid header name \"{}\"", name),
+            HeaderError::InvalidValue(name) => write!(f, "invalid value for header \"{}\"", name),
+        }
+    }
+}
+
+impl Error for HeaderError {}
+
+// Parses "Name: value", also the clap value parser for --headers. Whitespace
+// around the name and the value is dropped, and the value may be empty
+// ("X-Empty:"). The name is split off at the first colon, so the value can
+// hold more ("Referer: http://host:8080/"). A name must be a token: no spaces,
+// no control characters, no separators like "(" or "@". A value can't hold
+// control characters other than tab (a line break would smuggle in another
+// header); other bytes, UTF-8 included, are sent as they are.
+pub fn parse_header(line: &str) -> Result<(HeaderName, HeaderValue), HeaderError> {
+    let (name, value) = line
+        .split_once(':')
+        .ok_or_else(|| HeaderError::MissingColon(line.to_string()))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(HeaderError::EmptyName(line.to_string()));
+    }
+    let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| HeaderError::InvalidName(name.to_string()))?;
+    let value = HeaderValue::from_str(value.trim()).map_err(|_| HeaderError::InvalidValue(name.to_string()))?;
+    Ok((name, value))
+}
+
+// Parses every line into one map, in order. A name given twice keeps both
+// values, as separate header lines, rather than the last one winning.
+pub fn parse_all<'a>(lines: impl IntoIterator<Item = &'a str>) -> Result<HeaderMap, HeaderError> {
+    let mut headers = HeaderMap::new();
+    for line in lines {
+        let (name, value) = parse_header(line)?;
+        headers.append(name, value);
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(line: &str) -> (String, String) {
+        let (name, value) = parse_header(line).unwrap();
+        (name.to_string(), String::from_utf8(value.as_bytes().to_vec()).unwrap())
+    }
+
+    fn pair(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn name_and_value() {
+        assert_eq!(parsed("Accept: text/html"), pair("accept", "text/html"));
+        assert_eq!(parsed("X-Token:abc"), pair("x-token", "abc"));
+        assert_eq!(parsed("Referer: http://host:8080/"), pair("referer", "http://host:8080/"));
+    }
+
+    #[test]
+    fn empty_value() {
+        assert_eq!(parsed("X-Empty:"), pair("x-empty", ""));
+        assert_eq!(parsed("X-Empty:   "), pair("x-empty", ""));
+    }
+
+    #[test]
+    fn whitespace_is_trimmed() {
+        assert_eq!(parsed("  Accept  :  text/html  "), pair("accept", "text/html"));
+        assert_eq!(parsed("X-Tab:\tinner\tvalue\t"), pair("x-tab", "inner\tvalue"));
+        assert_eq!(parsed("X-Spaces: a  b"), pair("x-spaces", "a  b"));
+    }
+
+    #[test]
+    fn missing_colon() {
+        assert_eq!(
+            parse_header("Accept text/html"),
+            Err(HeaderError::MissingColon("Accept text/html".to_string()))
+        );
+        assert_eq!(parse_header(""), Err(HeaderError::MissingColon(String::new())));
+    }
+
+    #[test]
+    fn empty_name() {
+        assert_eq!(parse_header(": value"), Err(HeaderError::EmptyName(": value".to_string())));
+        assert_eq!(parse_header("  :"), Err(HeaderError::EmptyName("  :".to_string())));
+    }
+
+    #[test]
+    fn invalid_name() {
+        for name in ["X Token", "X(Token)", "user@host", "X-\u{1}", "Näme"] {
+            assert_eq!(
+                parse_header(&format!("{}: value", name)),
+                Err(HeaderError::InvalidName(name.to_string())),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_value() {
+        assert_eq!(
+            parse_header("X-Inject: a\r\nHost: evil"),
+            Err(HeaderError::InvalidValue("x-inject".to_string()))
+        );
+        assert_eq!(parse_header("X-Nul: a\0b"), Err(HeaderError::InvalidValue("x-nul".to_string())));
+        assert_eq!(parsed("X-Utf8: naïve"), pair("x-utf8", "naïve"));
+    }
+
+    #[test]
+    fn duplicate_names_keep_every_value() {
+        let headers = parse_all(["Accept: text/html", "X-A: 1", "accept: application/json"]).unwrap();
+        let accept: Vec<&str> = headers
+            .get_all("accept")
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect();
+        assert_eq!(accept, ["text/html", "application/json"]);
+        assert_eq!(headers.len(), 3);
+    }
+
+    #[test]
+    fn parse_all_stops_at_the_first_error() {
+        assert_eq!(
+            parse_all(["Accept: text/html", "broken", ": x"]),
+            Err(HeaderError::MissingColon("broken".to_string()))
+        );
+    }
+}
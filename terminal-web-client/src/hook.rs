@@ -0,0 +1,75 @@
+use crate::headers::{self, HeaderError};
+use crate::report;
+use reqwest::Request;
+use std::io::Write;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+// External commands run around each request, through the shell.
+//
+// --pre-request-cmd gets the request as JSON on stdin (see report::request_json)
+// just before it is sent. Each "Name: value" line it prints sets that header on
+// the request, replacing any existing value; "Name:" alone removes it. Other
+// output is ignored, so a hook that only logs can print nothing.
+//
+// --post-response-cmd gets the --output-format json document of the response
+// on stdin. Its output goes to the terminal as-is.
+//
+// A hook that can't be run or exits non-zero fails the request.
+
+pub async fn pre_request(command: &str, request: &mut Request) -> Result<(), String> {
+    let stdout = run(command, report::request_json(request)).await?;
+    for line in String::from_utf8_lossy(&stdout).lines() {
+        let (name, value) = match headers::parse_header(line) {
+            Ok(header) => header,
+            Err(HeaderError::MissingColon(_)) => continue,
+            Err(err) => return Err(format!("Pre-request command printed a malformed header: {}", err)),
+        };
+        if value.is_empty() {
+            request.headers_mut().remove(&name);
+        } else {
+            request.headers_mut().insert(name, value);
+        }
+    }
+    Ok(())
+}
+
+pub async fn post_response(command: &str, response_json: String) -> Result<(), String> {
+    let stdout = run(command, response_json).await?;
+    std::io::stdout()
+        .write_all(&stdout)
+        .map_err(|err| format!("Unable to write post-response command output: {}", err))
+}
+
+// Runs `command` with `input` on stdin and returns its stdout
+async fn run(command: &str, input: String) -> Result<Vec<u8>, String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    let mut child = shell
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|err| format!("Unable to run hook command \"{}\": {}", command, err))?;
+
+    // A hook may exit without reading its input, so a broken pipe isn't an error
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(input.as_bytes()).await;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|err| format!("Unable to run hook command \"{}\": {}", command, err))?;
+    if !output.status.success() {
+        return Err(format!("Hook command \"{}\" failed ({})", command, output.status));
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,252 @@
+use crate::cli;
+use crate::request;
+use clap::ArgMatches;
+use std::fs;
+
+// The `import` subcommand: translates a curl command line into this client's
+// `request` arguments and runs it (or prints it with --print). Only the curl
+// options with an equivalent here are understood; the rest are reported and
+// skipped.
+pub async fn run(matches: &ArgMatches) {
+    let words: Vec<String> = matches.get_many::<String>("curl").unwrap().cloned().collect();
+    // A single argument is a whole quoted command line, several are already split
+    let tokens = if words.len() == 1 {
+        match split(&words[0]) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        }
+    } else {
+        words
+    };
+
+    let args = match translate(&tokens) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    if matches.get_flag("print") {
+        let quoted: Vec<String> = args.iter().map(|arg| quote(arg)).collect();
+        println!("rustcurl request {}", quoted.join(" "));
+        return;
+    }
+
+    let command = ["rustcurl", "request"].into_iter().map(String::from).chain(args);
+    match cli::command().try_get_matches_from(command) {
+        Ok(matches) => request::run(matches.subcommand_matches("request").unwrap()).await,
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+// Converts curl arguments into `request` arguments
+fn translate(tokens: &[String]) -> Result<Vec<String>, String> {
+    let mut tokens = tokens.iter().map(String::as_str).peekable();
+    if tokens.peek() == Some(&"curl") {
+        tokens.next();
+    }
+
+    let mut args = Vec::new();
+    let mut urls = Vec::new();
+    let mut method = None;
+    let mut data: Vec<String> = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        // Split "-sSL" style clusters and "-XPOST" style attached values
+        let (option, attached) = if token.starts_with("--") {
+            match token.split_once('=') {
+                Some((option, value)) => (option.to_string(), Some(value.to_string())),
+                None => (token.to_string(), None),
+            }
+        } else if token.starts_with('-') && token.len() > 2 {
+            let option = token[..2].to_string();
+            if takes_value(&option) {
+                (option, Some(token[2..].to_string()))
+            } else {
+                // Every letter of a cluster without values is its own flag
+                let mut flags = token[1..].chars();
+                let first = format!("-{}", flags.next().unwrap());
+                for flag in flags {
+                    flag_only(&format!("-{}", flag), &mut method);
+                }
+                (first, None)
+            }
+        } else if token.starts_with('-') && token.len() == 2 {
+            (token.to_string(), None)
+        } else {
+            urls.push(token.to_string());
+            continue;
+        };
+
+        let mut value = || -> Result<String, String> {
+            match attached.clone() {
+                Some(value) => Ok(value),
+                None => tokens
+                    .next()
+                    .map(String::from)
+                    .ok_or_else(|| format!("curl option {} needs a value", option)),
+            }
+        };
+
+        match option.as_str() {
+            "-X" | "--request" => method = Some(value()?),
+            "-H" | "--header" => args.extend(["--headers".to_string(), value()?]),
+            "-d" | "--data" | "--data-ascii" | "--data-binary" => data.push(read_data(&value()?)?),
+            "--data-raw" => data.push(value()?),
+            "-F" | "--form" => args.extend(["--form".to_string(), value()?]),
+            "--form-string" => args.extend(["--form-string".to_string(), value()?]),
+            "-o" | "--output" => args.extend(["--output".to_string(), value()?]),
+            "--url" => urls.push(value()?),
+            "-A" | "--user-agent" => args.extend(["--headers".to_string(), format!("User-Agent: {}", value()?)]),
+            "-e" | "--referer" => args.extend(["--headers".to_string(), format!("Referer: {}", value()?)]),
+            // Like curl, a value without "=" names a cookie jar file
+            "-b" | "--cookie" => match value()? {
+                cookie if cookie.contains('=') => args.extend(["--headers".to_string(), format!("Cookie: {}", cookie)]),
+                jar => args.extend(["--load-cookies".to_string(), jar]),
+            },
+            "--aws-sigv4" => args.extend(["--aws-sigv4".to_string(), value()?]),
+            "--oauth2-bearer" => args.extend(["--oauth2-bearer".to_string(), value()?]),
+            "--interface" => args.extend(["--interface".to_string(), value()?]),
+            "--expect100-timeout" => args.extend(["--expect100-timeout".to_string(), value()?]),
+            "--connect-to" => args.extend(["--connect-to".to_string(), value()?]),
+            "--dns-servers" => args.extend(["--dns-servers".to_string(), value()?]),
+            "--doh-url" => args.extend(["--doh-url".to_string(), value()?]),
+            "--request-target" => args.extend(["--request-target".to_string(), value()?]),
+            "--trace-ascii" => args.extend(["--trace-ascii".to_string(), value()?]),
+            "--retry" => args.extend(["--retry".to_string(), value()?]),
+            "-Y" | "--speed-limit" => args.extend(["--speed-limit".to_string(), value()?]),
+            "-y" | "--speed-time" => args.extend(["--speed-time".to_string(), value()?]),
+            "--keepalive-time" => args.extend(["--keepalive".to_string(), value()?]),
+            "--compressed" => args.push("--compressed".to_string()),
+            "-N" | "--no-buffer" => args.push("--no-buffer".to_string()),
+            "-j" | "--junk-session-cookies" => args.push("--junk-session-cookies".to_string()),
+            "--fail-with-body" => args.push("--fail-with-body".to_string()),
+            "-g" | "--globoff" => args.push("--globoff".to_string()),
+            "--max-time" | "--connect-timeout" | "-m" | "-w" | "--write-out" | "-u" | "--user" => {
+                let value = value()?;
+                eprintln!("Ignoring unsupported curl option {} {}", option, value);
+            }
+            _ => flag_only(&option, &mut method),
+        }
+    }
+
+    if urls.is_empty() {
+        return Err("The curl command has no URL".to_string());
+    }
+    if !data.is_empty() {
+        args.extend(["--body".to_string(), data.join("&")]);
+        method.get_or_insert_with(|| "POST".to_string());
+    }
+
+    let mut translated = urls;
+    translated.extend(["-X".to_string(), method.unwrap_or_else(|| "GET".to_string())]);
+    translated.extend(args);
+    Ok(translated)
+}
+
+// Options that take a value, used to split attached short options like -XPOST
+fn takes_value(option: &str) -> bool {
+    ["-X", "-H", "-d", "-F", "-o", "-A", "-e", "-b", "-m", "-w", "-u"].contains(&option)
+}
+
+// Handles curl flags that take no value
+fn flag_only(option: &str, method: &mut Option<String>) {
+    match option {
+        "-I" | "--head" => *method = Some("HEAD".to_string()),
+        // Output and redirect flags have no effect here: redirects are always followed
+        "-s" | "--silent" | "-S" | "--show-error" | "-L" | "--location" | "-v" | "--verbose" | "-i"
+        | "--include" | "-k" | "--insecure" | "-f" | "--fail" => {}
+        _ => eprintln!("Ignoring unsupported curl option {}", option),
+    }
+}
+
+// curl's -d reads the data from a file when it starts with @
+fn read_data(value: &str) -> Result<String, String> {
+    match value.strip_prefix('@') {
+        Some(path) => fs::read_to_string(path)
+            .map(|data| data.replace(['\r', '\n'], ""))
+            .map_err(|err| format!("Failed to read {}: {}", path, err)),
+        None => Ok(value.to_string()),
+    }
+}
+
+// Splits a command line the way a POSIX shell would: single and double
+// quotes, backslash escapes and backslash-newline continuations
+fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("Unterminated ' in curl command".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("Unterminated \" in curl command".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("Unterminated \" in curl command".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') | None => {}
+                Some(c) => {
+                    in_word = true;
+                    word.push(c);
+                }
+            },
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+// Quotes an argument for --print when the shell would otherwise split it
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
@@ -0,0 +1,296 @@
+use reqwest::header::HeaderMap;
+use std::error::Error;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+// Guards against responses with pathological header sections. hyper refuses
+// more than 100 headers on its own, so --max-headers can only tighten that for
+// pooled requests; the raw HTTP/1.1 path relies on these limits entirely.
+pub struct HeaderLimits {
+    pub max_count: usize,
+    pub max_bytes: usize,
+}
+
+impl HeaderLimits {
+    pub fn check(&self, headers: &HeaderMap) -> Result<(), String> {
+        if headers.len() > self.max_count {
+            return Err(format!(
+                "Response has {} headers, more than the limit of {} (--max-headers)",
+                headers.len(),
+                self.max_count
+            ));
+        }
+
+        // Count each header as "name: value\r\n", like it appeared on the wire
+        let bytes: usize = headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len() + 4)
+            .sum();
+        if bytes > self.max_bytes {
+            return Err(format!(
+                "Response headers are {} bytes, more than the limit of {} (--max-header-bytes)",
+                bytes, self.max_bytes
+            ));
+        }
+        Ok(())
+    }
+}
+
+// True when hyper gave up parsing a response head because it was too large
+pub fn is_head_too_large(err: &reqwest::Error) -> bool {
+    let mut source = err.source();
+    while let Some(cause) = source {
+        if cause.to_string().contains("message head is too large") {
+            return true;
+        }
+        source = cause.source();
+    }
+    false
+}
+
+// Aborts a response body that stalls: fewer than `min_rate` bytes per second
+// averaged over a whole `window` (--speed-limit/--speed-time, like curl). A
+// half-open connection sends nothing and never closes, so the reads are
+// raced against the end of each window rather than checked between chunks.
+#[derive(Clone, Copy)]
+pub struct SpeedLimit {
+    pub min_rate: u64,
+    pub window: Duration,
+}
+
+impl SpeedLimit {
+    pub fn watch(self) -> StallWatch {
+        StallWatch {
+            limit: self,
+            window_start: Instant::now(),
+            bytes: 0,
+        }
+    }
+}
+
+pub struct StallWatch {
+    limit: SpeedLimit,
+    window_start: Instant,
+    bytes: u64,
+}
+
+impl StallWatch {
+    // Waits for `read`, failing if a window ends with too few bytes received
+    pub async fn wait<T>(&mut self, read: impl Future<Output = T>) -> Result<T, String> {
+        tokio::pin!(read);
+        loop {
+            let deadline = self.window_start + self.limit.window;
+            match tokio::time::timeout_at(deadline, &mut read).await {
+                Ok(value) => return Ok(value),
+                Err(_) => self.end_window()?,
+            }
+        }
+    }
+
+    pub fn received(&mut self, bytes: u64) -> Result<(), String> {
+        self.bytes += bytes;
+        if Instant::now() >= self.window_start + self.limit.window {
+            self.end_window()?;
+        }
+        Ok(())
+    }
+
+    fn end_window(&mut self) -> Result<(), String> {
+        let elapsed = self.window_start.elapsed();
+        if (self.bytes as f64) < self.limit.min_rate as f64 * elapsed.as_secs_f64() {
+            return Err(format!(
+                "Transfer stalled: {} bytes received in {:.1}s, below the limit of {} bytes/s (--speed-limit)",
+                self.bytes,
+                elapsed.as_secs_f64(),
+                self.limit.min_rate
+            ));
+        }
+        self.window_start = Instant::now();
+        self.bytes = 0;
+        Ok(())
+    }
+}
+
+// clap value parser for --limit-rate: bytes per second, with an optional K, M
+// or G suffix (powers of 1024, like curl)
+pub fn parse_rate(value: &str) -> Result<u64, String> {
+    let (digits, scale) = match value.char_indices().last() {
+        Some((at, 'k' | 'K')) => (&value[..at], 1 << 10),
+        Some((at, 'm' | 'M')) => (&value[..at], 1 << 20),
+        Some((at, 'g' | 'G')) => (&value[..at], 1 << 30),
+        _ => (value, 1),
+    };
+    match digits.parse::<u64>() {
+        Ok(rate) if rate > 0 => rate
+            .checked_mul(scale)
+            .ok_or_else(|| format!("\"{}\" is too large", value)),
+        _ => Err(format!("expected a positive number of bytes per second (e.g. 500K), found \"{}\"", value)),
+    }
+}
+
+// Caps the combined receive rate of every transfer (--limit-rate) with one
+// token bucket that all of them draw from, so bench workers running at the
+// same time share the budget instead of each getting all of it. Tokens are
+// taken after a chunk has arrived and a transfer that overdraws waits off the
+// debt while holding the bucket; tokio's mutex queues waiters in order, so
+// the transfers take turns.
+pub struct RateLimit {
+    rate: u64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimit {
+    pub fn new(rate: u64) -> Self {
+        RateLimit {
+            rate,
+            // Starts empty so not even the first second can go over the cap
+            bucket: Mutex::new(Bucket {
+                tokens: 0.0,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    // Accounts for `bytes` just received, waiting until they fit the rate
+    pub async fn take(&self, bytes: u64) {
+        let mut bucket = self.bucket.lock().await;
+        let now = Instant::now();
+        let burst = self.rate as f64;
+        bucket.tokens = (bucket.tokens + now.duration_since(bucket.refilled).as_secs_f64() * burst).min(burst);
+        bucket.refilled = now;
+        bucket.tokens -= bytes as f64;
+        if bucket.tokens < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-bucket.tokens / burst)).await;
+            bucket.tokens = 0.0;
+            bucket.refilled = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, Part};
+    use crate::raw;
+    use reqwest::{Client, Method, Request};
+
+    fn limits() -> HeaderLimits {
+        HeaderLimits {
+            max_count: 100,
+            max_bytes: 64 * 1024,
+        }
+    }
+
+    fn headers(count: usize, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for i in 0..count {
+            headers.append(
+                reqwest::header::HeaderName::from_bytes(format!("x-h{}", i).as_bytes()).unwrap(),
+                value.parse().unwrap(),
+            );
+        }
+        headers
+    }
+
+    #[test]
+    fn header_count_limit() {
+        assert!(limits().check(&headers(100, "v")).is_ok());
+        let err = limits().check(&headers(101, "v")).unwrap_err();
+        assert!(err.contains("101 headers") && err.contains("--max-headers"), "{}", err);
+    }
+
+    #[test]
+    fn header_bytes_limit() {
+        // "x-h0: " + value + "\r\n"
+        let limits = HeaderLimits {
+            max_count: 100,
+            max_bytes: 20,
+        };
+        assert!(limits.check(&headers(1, &"v".repeat(12))).is_ok());
+        let err = limits.check(&headers(1, &"v".repeat(13))).unwrap_err();
+        assert!(err.contains("--max-header-bytes"), "{}", err);
+    }
+
+    #[test]
+    fn parses_rates() {
+        assert_eq!(parse_rate("500"), Ok(500));
+        assert_eq!(parse_rate("100K"), Ok(100 * 1024));
+        assert_eq!(parse_rate("100k"), Ok(100 * 1024));
+        assert_eq!(parse_rate("1M"), Ok(1024 * 1024));
+        assert_eq!(parse_rate("2G"), Ok(2 * 1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn rejects_garbage_rates() {
+        for value in ["", "K", "0", "-5", "1.5M", "10X", "fast", "99999999999999999999G"] {
+            assert!(parse_rate(value).is_err_and(|err| err.contains("positive number")), "{}", value);
+        }
+        assert!(parse_rate("17179869184G").is_err_and(|err| err.contains("too large")));
+    }
+
+    fn stall_limit() -> SpeedLimit {
+        SpeedLimit {
+            min_rate: 1000,
+            window: Duration::from_millis(100),
+        }
+    }
+
+    // A read that never completes fails once a window passes with nothing
+    #[tokio::test]
+    async fn detects_a_stall() {
+        let mut watch = stall_limit().watch();
+        let started = Instant::now();
+        let err = watch.wait(std::future::pending::<()>()).await.unwrap_err();
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert!(err.starts_with("Transfer stalled: 0 bytes received"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn a_steady_transfer_passes() {
+        let mut watch = stall_limit().watch();
+        for _ in 0..5 {
+            let bytes = watch.wait(tokio::time::sleep(Duration::from_millis(30))).await.map(|()| 100);
+            watch.received(bytes.unwrap()).unwrap();
+        }
+        // Too little over a whole window fails when the next bytes arrive
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(watch.received(1).is_err());
+    }
+
+    // A server that answers with 5000 headers
+    async fn flooding_server() -> mock::MockServer {
+        mock::serve(|_| {
+            let mut response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n".to_string();
+            for i in 0..5_000 {
+                response.push_str(&format!("X-Flood-{}: {}\r\n", i, i));
+            }
+            response.push_str("\r\nok");
+            vec![Part::Send(response.into_bytes()), Part::Close]
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn raw_path_refuses_thousands_of_headers() {
+        let server = flooding_server().await;
+        let request = Request::new(Method::GET, server.at("/"));
+        let response = raw::send(&request, "/", None, None).await.unwrap();
+        let err = limits().check(&response.head.headers).unwrap_err();
+        assert!(err.contains("5002 headers"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn pooled_path_refuses_thousands_of_headers() {
+        let server = flooding_server().await;
+        let client = Client::builder().no_proxy().build().unwrap();
+        let err = client.get(server.at("/")).send().await.unwrap_err();
+        assert!(is_head_too_large(&err), "{}", err);
+    }
+}
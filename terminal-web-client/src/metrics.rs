@@ -0,0 +1,104 @@
+use reqwest::StatusCode;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+// Upper bounds of the latency histogram in seconds, Prometheus' own defaults
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// The requests of a --repeat or bench run in the Prometheus text exposition
+// format (--metrics-prometheus), for a textfile collector or a pushgateway:
+// request counts by status, failed requests (no response at all), and a
+// latency histogram. `samples` are each request's latency and status.
+pub fn exposition(samples: &[(Duration, Option<StatusCode>)]) -> String {
+    let mut statuses: BTreeMap<u16, usize> = BTreeMap::new();
+    let mut errors = 0;
+    for (_, status) in samples {
+        match status {
+            Some(status) => *statuses.entry(status.as_u16()).or_default() += 1,
+            None => errors += 1,
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str("# HELP twc_requests_total Requests that got a response, by status code.\n");
+    out.push_str("# TYPE twc_requests_total counter\n");
+    for (status, count) in &statuses {
+        let _ = writeln!(out, "twc_requests_total{{status=\"{}\"}} {}", status, count);
+    }
+    out.push_str("# HELP twc_request_errors_total Requests that failed without a response.\n");
+    out.push_str("# TYPE twc_request_errors_total counter\n");
+    let _ = writeln!(out, "twc_request_errors_total {}", errors);
+
+    out.push_str("# HELP twc_request_duration_seconds Time from sending a request to reading its whole response.\n");
+    out.push_str("# TYPE twc_request_duration_seconds histogram\n");
+    let seconds: Vec<f64> = samples.iter().map(|(latency, _)| latency.as_secs_f64()).collect();
+    // Buckets are cumulative: each counts every sample at or under its bound
+    for bound in BUCKETS {
+        let count = seconds.iter().filter(|&&secs| secs <= bound).count();
+        let _ = writeln!(out, "twc_request_duration_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+    }
+    let _ = writeln!(out, "twc_request_duration_seconds_bucket{{le=\"+Inf\"}} {}", seconds.len());
+    let _ = writeln!(out, "twc_request_duration_seconds_sum {}", seconds.iter().sum::<f64>());
+    let _ = writeln!(out, "twc_request_duration_seconds_count {}", seconds.len());
+    out
+}
+
+// Prints the exposition, or writes it to `path` unless that is "-"
+pub fn write(path: &str, samples: &[(Duration, Option<StatusCode>)]) {
+    let text = exposition(samples);
+    if path == "-" {
+        print!("{}", text);
+    } else if let Err(err) = std::fs::write(path, text) {
+        eprintln!("Unable to write metrics to \"{}\": {}", path, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposition_counts_statuses_and_buckets() {
+        // Binary fractions, so the sum prints exactly
+        let samples = [
+            (Duration::from_secs_f64(0.00390625), Some(StatusCode::OK)),
+            (Duration::from_secs_f64(0.03125), Some(StatusCode::OK)),
+            (Duration::from_secs_f64(0.75), Some(StatusCode::NOT_FOUND)),
+            (Duration::from_secs(20), None),
+        ];
+        let text = exposition(&samples);
+        let lines: Vec<&str> = text.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(
+            lines,
+            [
+                "twc_requests_total{status=\"200\"} 2",
+                "twc_requests_total{status=\"404\"} 1",
+                "twc_request_errors_total 1",
+                "twc_request_duration_seconds_bucket{le=\"0.005\"} 1",
+                "twc_request_duration_seconds_bucket{le=\"0.01\"} 1",
+                "twc_request_duration_seconds_bucket{le=\"0.025\"} 1",
+                "twc_request_duration_seconds_bucket{le=\"0.05\"} 2",
+                "twc_request_duration_seconds_bucket{le=\"0.1\"} 2",
+                "twc_request_duration_seconds_bucket{le=\"0.25\"} 2",
+                "twc_request_duration_seconds_bucket{le=\"0.5\"} 2",
+                "twc_request_duration_seconds_bucket{le=\"1\"} 3",
+                "twc_request_duration_seconds_bucket{le=\"2.5\"} 3",
+                "twc_request_duration_seconds_bucket{le=\"5\"} 3",
+                "twc_request_duration_seconds_bucket{le=\"10\"} 3",
+                "twc_request_duration_seconds_bucket{le=\"+Inf\"} 4",
+                "twc_request_duration_seconds_sum 20.78515625",
+                "twc_request_duration_seconds_count 4",
+            ]
+        );
+        assert!(text.contains("# TYPE twc_request_duration_seconds histogram\n"));
+    }
+
+    // A bound is inclusive
+    #[test]
+    fn sample_on_a_bound_counts_in_its_bucket() {
+        let text = exposition(&[(Duration::from_millis(100), Some(StatusCode::OK))]);
+        assert!(text.contains("twc_request_duration_seconds_bucket{le=\"0.05\"} 0\n"));
+        assert!(text.contains("twc_request_duration_seconds_bucket{le=\"0.1\"} 1\n"));
+    }
+}
@@ -0,0 +1,137 @@
+use reqwest::Url;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+// A local HTTP/1.1 server for tests. Each request is answered with whatever
+// the test's `respond` returns for it, byte for byte. A connection stays open
+// for further requests until the client closes it or the answer ends with
+// Part::Close.
+
+// One request as it arrived
+#[derive(Clone, Debug)]
+pub struct Received {
+    // The connection it came on, numbered from 0 in the order they were
+    // accepted. Only the CLI tests look at it.
+    #[allow(dead_code)]
+    pub connection: usize,
+    // The request line and headers, without the blank line
+    pub head: String,
+    // Decoded from Content-Length or chunked framing
+    pub body: Vec<u8>,
+}
+
+impl Received {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+}
+
+// What the server does next while answering
+pub enum Part {
+    Send(Vec<u8>),
+    // Pause before the next part. Only the CLI tests use it.
+    #[allow(dead_code)]
+    Wait(Duration),
+    Close,
+}
+
+pub struct MockServer {
+    pub url: Url,
+    received: Arc<Mutex<Vec<Received>>>,
+}
+
+impl MockServer {
+    // The URL of a path on the server
+    pub fn at(&self, path: &str) -> Url {
+        self.url.join(path).unwrap()
+    }
+
+    // Every request so far, in the order they arrived
+    pub fn received(&self) -> Vec<Received> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+pub async fn serve<F>(respond: F) -> MockServer
+where
+    F: Fn(&Received) -> Vec<Part> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let respond = Arc::new(respond);
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = received.clone();
+    tokio::spawn(async move {
+        let mut connection = 0;
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(answer(stream, connection, respond.clone(), log.clone()));
+            connection += 1;
+        }
+    });
+    MockServer { url, received }
+}
+
+async fn answer<F>(stream: TcpStream, connection: usize, respond: Arc<F>, log: Arc<Mutex<Vec<Received>>>)
+where
+    F: Fn(&Received) -> Vec<Part> + Send + Sync + 'static,
+{
+    let mut stream = BufReader::new(stream);
+    while let Some(request) = read_request(&mut stream, connection).await {
+        log.lock().unwrap().push(request.clone());
+        for part in respond(&request) {
+            let sent = match part {
+                Part::Send(bytes) => stream.get_mut().write_all(&bytes).await,
+                Part::Wait(pause) => {
+                    tokio::time::sleep(pause).await;
+                    Ok(())
+                }
+                Part::Close => return,
+            };
+            if sent.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+async fn read_request(stream: &mut BufReader<TcpStream>, connection: usize) -> Option<Received> {
+    let mut head = String::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        if line.trim_end().is_empty() {
+            break;
+        }
+        head.push_str(&line);
+    }
+    let mut request = Received {
+        connection,
+        head: head.trim_end().to_string(),
+        body: Vec::new(),
+    };
+
+    if request.header("transfer-encoding").is_some_and(|value| value.contains("chunked")) {
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.ok()?;
+            let size = usize::from_str_radix(line.trim_end(), 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            stream.read_exact(&mut chunk).await.ok()?;
+            if size == 0 {
+                break;
+            }
+            request.body.extend_from_slice(&chunk[..size]);
+        }
+    } else if let Some(length) = request.header("content-length").and_then(|value| value.parse().ok()) {
+        request.body = vec![0; length];
+        stream.read_exact(&mut request.body).await.ok()?;
+    }
+    Some(request)
+}
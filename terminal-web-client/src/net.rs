@@ -0,0 +1,23 @@
+use std::net::IpAddr;
+
+// Resolves an --interface value to a local address. Accepts either a literal
+// IP address or an interface name such as "eth0" / "lo", preferring IPv4.
+pub fn interface_addr(name: &str) -> Result<IpAddr, String> {
+    if let Ok(addr) = name.parse::<IpAddr>() {
+        return Ok(addr);
+    }
+
+    let interfaces = if_addrs::get_if_addrs()
+        .map_err(|err| format!("Unable to list network interfaces: {}", err))?;
+    let mut addrs: Vec<IpAddr> = interfaces
+        .iter()
+        .filter(|iface| iface.name == name)
+        .map(|iface| iface.ip())
+        .collect();
+    addrs.sort_by_key(|addr| !addr.is_ipv4());
+
+    addrs
+        .first()
+        .copied()
+        .ok_or_else(|| format!("Unable to resolve interface \"{}\" to an address", name))
+}
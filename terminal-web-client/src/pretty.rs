@@ -0,0 +1,316 @@
+// Reformats structured response bodies for reading in a terminal. The
+// Content-Type picks the formatter; anything unrecognized, or a body that
+// doesn't parse, is left for the caller to print as-is.
+
+use crate::color;
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE};
+
+const INDENT: &str = "  ";
+// JSON bodies larger than this, or of unknown length, are formatted as they
+// arrive rather than once complete
+const STREAM_THRESHOLD: u64 = 8 * 1024 * 1024;
+
+// With `color`, JSON keys and string values are highlighted
+pub fn format(content_type: Option<&str>, body: &[u8], color: bool) -> Option<String> {
+    let text = std::str::from_utf8(body).ok()?;
+    let mime = content_type?.split(';').next()?.trim().to_ascii_lowercase();
+
+    if mime == "application/json" || mime.ends_with("+json") {
+        json(text, color)
+    } else if mime == "application/xml" || mime == "text/xml" || mime.ends_with("+xml") {
+        xml(text)
+    } else if mime == "text/csv" {
+        csv(text)
+    } else {
+        None
+    }
+}
+
+// Whether a response should go through JsonStream as it arrives
+pub fn streams(headers: &HeaderMap) -> bool {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let json = header(CONTENT_TYPE)
+        .and_then(|value| value.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase())
+        .is_some_and(|mime| mime == "application/json" || mime.ends_with("+json"));
+    let length = header(CONTENT_LENGTH).and_then(|value| value.trim().parse::<u64>().ok());
+    json && length.is_none_or(|length| length > STREAM_THRESHOLD)
+}
+
+// Re-indents JSON without parsing it into values, so numbers and key order
+// come out exactly as the server sent them
+fn json(text: &str, color: bool) -> Option<String> {
+    let mut stream = JsonStream::new(color);
+    let mut out = stream.feed(text.trim().as_bytes());
+    out.push_str(&stream.finish()?);
+    Some(out)
+}
+
+// The JSON formatter, fed the body a chunk at a time so a huge response can
+// be printed as it arrives without holding all of it. Only the current string
+// and a partial UTF-8 character are kept between chunks.
+pub struct JsonStream {
+    color: bool,
+    depth: usize,
+    // The string being read, quotes and escapes included
+    string: Option<String>,
+    escaped: bool,
+    // A complete string waiting for the next character: a ":" makes it a key
+    pending_string: Option<String>,
+    // Just after "{" or "[": the next character says whether it is empty
+    pending_open: bool,
+    // The start of a UTF-8 character split across chunks
+    partial: Vec<u8>,
+    // Set on input that isn't JSON; everything after it is passed through
+    failed: bool,
+}
+
+impl JsonStream {
+    pub fn new(color: bool) -> Self {
+        JsonStream {
+            color,
+            depth: 0,
+            string: None,
+            escaped: false,
+            pending_string: None,
+            pending_open: false,
+            partial: Vec::new(),
+            failed: false,
+        }
+    }
+
+    // Formats the next chunk, returning what can be printed so far
+    pub fn feed(&mut self, chunk: &[u8]) -> String {
+        let mut bytes = std::mem::take(&mut self.partial);
+        bytes.extend_from_slice(chunk);
+        let valid = match std::str::from_utf8(&bytes) {
+            Ok(text) => text.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(_) => {
+                self.failed = true;
+                bytes.len()
+            }
+        };
+        self.partial = bytes.split_off(valid);
+
+        let text = String::from_utf8_lossy(&bytes);
+        if self.failed {
+            return text.into_owned();
+        }
+        let mut out = String::new();
+        for c in text.chars() {
+            if self.failed {
+                out.push(c);
+            } else {
+                self.push(c, &mut out);
+            }
+        }
+        out
+    }
+
+    // The rest of the output once the body is complete, or None if it
+    // wasn't complete JSON
+    pub fn finish(&mut self) -> Option<String> {
+        let mut out = String::new();
+        if let Some(string) = self.pending_string.take() {
+            self.paint_string(&string, false, &mut out);
+        }
+        let complete =
+            !self.failed && self.partial.is_empty() && self.string.is_none() && !self.pending_open && self.depth == 0;
+        complete.then_some(out)
+    }
+
+    fn push(&mut self, c: char, out: &mut String) {
+        if let Some(string) = &mut self.string {
+            string.push(c);
+            match c {
+                _ if self.escaped => self.escaped = false,
+                '\\' => self.escaped = true,
+                '"' => self.pending_string = self.string.take(),
+                _ => {}
+            }
+            return;
+        }
+        if c.is_whitespace() {
+            return;
+        }
+
+        if std::mem::take(&mut self.pending_open) {
+            // Keep empty containers on one line
+            if matches!(c, '}' | ']') {
+                out.push(c);
+                return;
+            }
+            self.depth += 1;
+            self.newline(out);
+        }
+        if let Some(string) = self.pending_string.take() {
+            self.paint_string(&string, c == ':', out);
+        }
+
+        match c {
+            '"' => self.string = Some(String::from(c)),
+            '{' | '[' => {
+                out.push(c);
+                self.pending_open = true;
+            }
+            '}' | ']' => match self.depth.checked_sub(1) {
+                Some(depth) => {
+                    self.depth = depth;
+                    self.newline(out);
+                    out.push(c);
+                }
+                None => {
+                    self.failed = true;
+                    out.push(c);
+                }
+            },
+            ',' => {
+                out.push(c);
+                self.newline(out);
+            }
+            ':' => out.push_str(": "),
+            c => out.push(c),
+        }
+    }
+
+    fn paint_string(&self, string: &str, key: bool, out: &mut String) {
+        match (self.color, key) {
+            (false, _) => out.push_str(string),
+            (true, true) => out.push_str(&color::paint(string, color::KEY)),
+            (true, false) => out.push_str(&color::paint(string, color::STRING)),
+        }
+    }
+
+    fn newline(&self, out: &mut String) {
+        out.push('\n');
+        out.push_str(&INDENT.repeat(self.depth));
+    }
+}
+
+// Puts each element on its own line, indented by nesting. Text that is the
+// only content of an element stays on the same line as its tags.
+fn xml(text: &str) -> Option<String> {
+    // Split into tags and the text between them
+    let mut tokens = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            let end = if rest.starts_with("<!--") {
+                rest.find("-->")? + 3
+            } else if rest.starts_with("<![CDATA[") {
+                rest.find("]]>")? + 3
+            } else {
+                rest.find('>')? + 1
+            };
+            tokens.push(&rest[..end]);
+            rest = &rest[end..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let content = rest[..end].trim();
+            if !content.is_empty() {
+                tokens.push(content);
+            }
+            rest = &rest[end..];
+        }
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        let is_open = token.starts_with('<')
+            && !token.starts_with("</")
+            && !token.starts_with("<?")
+            && !token.starts_with("<!")
+            && !token.ends_with("/>");
+
+        if token.starts_with("</") {
+            depth = depth.checked_sub(1)?;
+            lines.push(format!("{}{}", INDENT.repeat(depth), token));
+        } else if is_open
+            && tokens.get(i + 1).is_some_and(|next| !next.starts_with('<'))
+            && tokens.get(i + 2).is_some_and(|close| close.starts_with("</"))
+        {
+            // <name>text</name> on one line
+            lines.push(format!("{}{}{}{}", INDENT.repeat(depth), token, tokens[i + 1], tokens[i + 2]));
+            i += 2;
+        } else {
+            lines.push(format!("{}{}", INDENT.repeat(depth), token));
+            if is_open {
+                depth += 1;
+            }
+        }
+        i += 1;
+    }
+    (depth == 0).then(|| lines.join("\n"))
+}
+
+// Lays CSV out as a table with each column padded to its widest cell
+fn csv(text: &str) -> Option<String> {
+    let rows = parse_csv(text)?;
+    let columns = rows.iter().map(Vec::len).max()?;
+    let mut widths = vec![0; columns];
+    for row in &rows {
+        for (column, cell) in row.iter().enumerate() {
+            widths[column] = widths[column].max(cell.chars().count());
+        }
+    }
+
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(column, cell)| format!("{:<width$}", cell, width = widths[column]))
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect();
+    Some(lines.join("\n"))
+}
+
+// RFC 4180 records: quoted fields may hold commas, newlines and "" escapes
+fn parse_csv(text: &str) -> Option<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                // Show embedded newlines escaped so rows stay on one line
+                '\n' => field.push_str("\\n"),
+                c => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if in_quotes {
+        return None;
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Some(rows)
+}
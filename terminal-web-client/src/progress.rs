@@ -0,0 +1,248 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// How often progress is reported while a body downloads
+const INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 30;
+
+// Receives the events of one body download. The transport drives it and
+// doesn't care where (or whether) progress ends up.
+pub trait ProgressReporter {
+    // The body is about to be read; `total` is the Content-Length if known
+    fn on_start(&mut self, total: Option<u64>);
+    // `bytes` more bytes of the body arrived
+    fn on_chunk(&mut self, bytes: u64);
+    // The whole body has been read
+    fn on_finish(&mut self);
+}
+
+// Which reporter the command line asked for
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+    None,
+    // A redrawn bar on stderr, for terminals
+    Bar,
+    // One JSON object per line on stderr, for programs wrapping the client:
+    //   {"event":"progress"|"done","bytes":N,"total":N|null,"percent":F|null,"speed":F,"elapsed_ms":N}
+    // `total`/`percent` are null when the server sent no Content-Length, `speed` is bytes/second.
+    Json,
+}
+
+impl ProgressMode {
+    // Whatever the mode, the download is also tracked for SIGUSR1
+    pub fn reporter(self) -> Box<dyn ProgressReporter + Send> {
+        let inner: Box<dyn ProgressReporter + Send> = match self {
+            ProgressMode::None => Box::new(Silent),
+            ProgressMode::Bar => Box::new(Bar(Counter::default())),
+            ProgressMode::Json => Box::new(Json(Counter::default())),
+        };
+        Box::new(Tracked(inner))
+    }
+}
+
+// The body download in progress, if any, for dump_on_signal
+static CURRENT: Mutex<Option<Counter>> = Mutex::new(None);
+
+// Sending SIGUSR1 to the client prints where the current download is to
+// stderr, like dd does, without disturbing it:
+//   kill -USR1 <pid>
+// Only on Unix; elsewhere there is no such signal and this does nothing.
+#[cfg(unix)]
+pub fn dump_on_signal() -> io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut signals = signal(SignalKind::user_defined1())?;
+    tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            match CURRENT.lock().unwrap().as_ref() {
+                Some(counter) => {
+                    let total = counter.total.map_or_else(String::new, |total| format!(" of {}", human_bytes(total as f64)));
+                    let percent = counter.percent().map_or_else(String::new, |percent| format!(" ({:.1}%)", percent));
+                    eprintln!(
+                        "\nProgress: {}{}{} at {}/s, {:.1}s elapsed",
+                        human_bytes(counter.bytes as f64),
+                        total,
+                        percent,
+                        human_bytes(counter.speed()),
+                        counter.started.elapsed().as_secs_f64()
+                    );
+                }
+                None => eprintln!("\nProgress: no transfer in progress"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn dump_on_signal() -> io::Result<()> {
+    Ok(())
+}
+
+struct Tracked(Box<dyn ProgressReporter + Send>);
+
+impl ProgressReporter for Tracked {
+    fn on_start(&mut self, total: Option<u64>) {
+        let mut counter = Counter::default();
+        counter.start(total);
+        *CURRENT.lock().unwrap() = Some(counter);
+        self.0.on_start(total);
+    }
+
+    fn on_chunk(&mut self, bytes: u64) {
+        if let Some(counter) = CURRENT.lock().unwrap().as_mut() {
+            counter.bytes += bytes;
+        }
+        self.0.on_chunk(bytes);
+    }
+
+    fn on_finish(&mut self) {
+        self.0.on_finish();
+    }
+}
+
+// Also when the download fails partway and on_finish never comes
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        *CURRENT.lock().unwrap() = None;
+    }
+}
+
+struct Silent;
+
+impl ProgressReporter for Silent {
+    fn on_start(&mut self, _total: Option<u64>) {}
+    fn on_chunk(&mut self, _bytes: u64) {}
+    fn on_finish(&mut self) {}
+}
+
+struct Bar(Counter);
+
+impl ProgressReporter for Bar {
+    fn on_start(&mut self, total: Option<u64>) {
+        self.0.start(total);
+    }
+
+    fn on_chunk(&mut self, bytes: u64) {
+        if self.0.add(bytes) {
+            self.draw();
+        }
+    }
+
+    fn on_finish(&mut self) {
+        self.draw();
+        eprintln!();
+    }
+}
+
+impl Bar {
+    fn draw(&self) {
+        let counter = &self.0;
+        let percent = counter.percent();
+        let filled = percent.map_or(0, |percent| (percent / 100_f64 * BAR_WIDTH as f64) as usize);
+        let percent = percent.map_or("    ?".to_string(), |percent| format!("{:5.1}", percent));
+        eprint!(
+            "\r[{}{}] {}%  {}  {}/s   ",
+            "#".repeat(filled),
+            ".".repeat(BAR_WIDTH - filled),
+            percent,
+            human_bytes(counter.bytes as f64),
+            human_bytes(counter.speed())
+        );
+        let _ = io::stderr().flush();
+    }
+}
+
+struct Json(Counter);
+
+impl ProgressReporter for Json {
+    fn on_start(&mut self, total: Option<u64>) {
+        self.0.start(total);
+    }
+
+    fn on_chunk(&mut self, bytes: u64) {
+        if self.0.add(bytes) {
+            self.emit("progress");
+        }
+    }
+
+    fn on_finish(&mut self) {
+        self.emit("done");
+    }
+}
+
+impl Json {
+    fn emit(&self, event: &str) {
+        let counter = &self.0;
+        let null = || "null".to_string();
+        eprintln!(
+            "{{\"event\":\"{}\",\"bytes\":{},\"total\":{},\"percent\":{},\"speed\":{:.1},\"elapsed_ms\":{}}}",
+            event,
+            counter.bytes,
+            counter.total.map_or_else(null, |total| total.to_string()),
+            counter.percent().map_or_else(null, |percent| format!("{:.1}", percent)),
+            counter.speed(),
+            counter.started.elapsed().as_millis()
+        );
+    }
+}
+
+// Byte count, rate and report throttling shared by the visible reporters
+struct Counter {
+    total: Option<u64>,
+    bytes: u64,
+    started: Instant,
+    last_report: Option<Instant>,
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Counter {
+            total: None,
+            bytes: 0,
+            started: Instant::now(),
+            last_report: None,
+        }
+    }
+}
+
+impl Counter {
+    fn start(&mut self, total: Option<u64>) {
+        *self = Counter {
+            total,
+            ..Counter::default()
+        };
+    }
+
+    // Counts the bytes and says whether a report is due
+    fn add(&mut self, bytes: u64) -> bool {
+        self.bytes += bytes;
+        let due = self.last_report.is_none_or(|last| last.elapsed() >= INTERVAL);
+        if due {
+            self.last_report = Some(Instant::now());
+        }
+        due
+    }
+
+    fn speed(&self) -> f64 {
+        self.bytes as f64 / self.started.elapsed().as_secs_f64().max(0.001)
+    }
+
+    fn percent(&self) -> Option<f64> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| (self.bytes as f64 / total as f64 * 100_f64).min(100_f64))
+    }
+}
+
+fn human_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024_f64 && unit < UNITS.len() - 1 {
+        value /= 1024_f64;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
@@ -0,0 +1,529 @@
+use crate::dns;
+use crate::headers::parse_header;
+use crate::tunnel::ProxyTunnel;
+use reqwest::header::{HeaderMap, HOST};
+use reqwest::{Method, Request, StatusCode, Url, Version};
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_native_tls::{native_tls, TlsConnector};
+
+// A bare-bones HTTP/1.1 connection for the cases reqwest/hyper can't express
+// (waiting on 100-continue, a custom request-target, ...). One request per
+// connection, no pooling.
+
+// Hard ceiling on a response head, whatever --max-header-bytes says
+const MAX_HEAD_BYTES: usize = 1024 * 1024;
+// Hard ceiling on a response body, which is usually held in memory whole
+const MAX_BODY_BYTES: u64 = 1024 * 1024 * 1024;
+// Longest chunk-size or trailer line read
+const MAX_LINE_BYTES: u64 = 64 * 1024;
+// Most of a body read at once
+const PIECE_BYTES: u64 = 64 * 1024;
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+pub struct Head {
+    pub version: Version,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+}
+
+pub struct Connection {
+    stream: BufReader<Box<dyn Stream>>,
+    url: Url,
+}
+
+impl Connection {
+    // Connects to the URL's host, or to `connect` (host, port) instead while
+    // still presenting the URL's host for TLS. With a `tunnel`, the same
+    // address is reached through the proxy's CONNECT tunnel.
+    pub async fn open(url: &Url, connect: Option<&(String, u16)>, tunnel: Option<&ProxyTunnel>) -> io::Result<Self> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| invalid("URL has no host"))?
+            .to_string();
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| invalid("URL has no port"))?;
+        let (to_host, to_port) = match connect {
+            Some((connect_host, connect_port)) => (connect_host.as_str(), *connect_port),
+            None => (host.as_str(), port),
+        };
+        let tcp = match tunnel {
+            Some(tunnel) => tunnel.open(to_host, to_port).await?,
+            None => dns::connect(to_host, to_port).await?,
+        };
+
+        let stream: Box<dyn Stream> = match url.scheme() {
+            "http" => Box::new(tcp),
+            "https" => {
+                let connector = native_tls::TlsConnector::new().map_err(io::Error::other)?;
+                let tls = TlsConnector::from(connector)
+                    .connect(&host, tcp)
+                    .await
+                    .map_err(io::Error::other)?;
+                Box::new(tls)
+            }
+            scheme => return Err(invalid(&format!("Unsupported scheme \"{}\"", scheme))),
+        };
+        Ok(Connection {
+            stream: BufReader::new(stream),
+            url: url.clone(),
+        })
+    }
+
+    pub async fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.stream.get_mut().write_all(bytes).await?;
+        self.stream.get_mut().flush().await
+    }
+
+    // Reads a status line and header block (including interim 1xx responses)
+    pub async fn read_head(&mut self) -> io::Result<Head> {
+        let mut line = String::new();
+        (&mut self.stream)
+            .take(MAX_HEAD_BYTES as u64)
+            .read_line(&mut line)
+            .await?;
+        let mut parts = line.trim_end().splitn(3, ' ');
+        let version = match parts.next() {
+            Some("HTTP/1.0") => Version::HTTP_10,
+            Some("HTTP/1.1") => Version::HTTP_11,
+            _ => return Err(invalid(&format!("Malformed status line \"{}\"", line.trim_end()))),
+        };
+        let status = parts
+            .next()
+            .and_then(|code| code.parse::<u16>().ok())
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .ok_or_else(|| invalid(&format!("Malformed status line \"{}\"", line.trim_end())))?;
+
+        let mut headers = HeaderMap::new();
+        let mut head_bytes = line.len();
+        loop {
+            line.clear();
+            let read = (&mut self.stream)
+                .take((MAX_HEAD_BYTES - head_bytes) as u64 + 1)
+                .read_line(&mut line)
+                .await?;
+            if read == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            head_bytes += read;
+            if head_bytes > MAX_HEAD_BYTES {
+                return Err(invalid("Response header section is too large"));
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            let (name, value) =
+                parse_header(header).map_err(|err| invalid(&format!("Malformed response header: {}", err)))?;
+            headers.append(name, value);
+        }
+        Ok(Head {
+            version,
+            status,
+            headers,
+        })
+    }
+
+    // Reads the final response head, skipping any interim 1xx responses
+    pub async fn read_response(mut self, method: &Method) -> io::Result<Response> {
+        let mut head = self.read_head().await?;
+        while head.status.is_informational() {
+            head = self.read_head().await?;
+        }
+        self.finish(head, method)
+    }
+
+    // Hands over the body that belongs to an already-parsed final response head
+    pub fn finish(self, head: Head, method: &Method) -> io::Result<Response> {
+        let chunked = head
+            .headers
+            .get("transfer-encoding")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.to_ascii_lowercase().contains("chunked"));
+        let length = head
+            .headers
+            .get("content-length")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+
+        let framing = if method == Method::HEAD
+            || head.status == StatusCode::NO_CONTENT
+            || head.status == StatusCode::NOT_MODIFIED
+        {
+            Framing::Done
+        } else if chunked {
+            Framing::Chunked(0)
+        } else if let Some(length) = length {
+            if length > MAX_BODY_BYTES {
+                return Err(too_large());
+            }
+            Framing::Length(length)
+        } else {
+            Framing::Close
+        };
+        Ok(Response {
+            url: self.url,
+            head,
+            body: Body {
+                stream: self.stream,
+                framing,
+                received: 0,
+            },
+        })
+    }
+}
+
+// A final response, its body still to be read
+pub struct Response {
+    pub url: Url,
+    pub head: Head,
+    pub body: Body,
+}
+
+// A response body, read off the connection piece by piece as it arrives
+pub struct Body {
+    stream: BufReader<Box<dyn Stream>>,
+    framing: Framing,
+    received: u64,
+}
+
+enum Framing {
+    // Bytes left of a Content-Length body
+    Length(u64),
+    // Bytes left of the current chunk, 0 when a chunk-size line is next
+    Chunked(u64),
+    // Everything up to the server closing the connection
+    Close,
+    Done,
+}
+
+impl Body {
+    // The length the head announced, if it did
+    pub fn content_length(&self) -> Option<u64> {
+        match self.framing {
+            Framing::Length(length) => Some(length),
+            Framing::Done => Some(0),
+            _ => None,
+        }
+    }
+
+    // The next piece of the body as it arrives, or None at its end. Lengths
+    // come from the server, so nothing is allocated ahead of the bytes.
+    pub async fn chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let wanted = match self.framing {
+            Framing::Length(0) | Framing::Done => {
+                self.framing = Framing::Done;
+                return Ok(None);
+            }
+            Framing::Length(left) | Framing::Chunked(left @ 1..) => left,
+            Framing::Chunked(0) => match self.chunk_size().await? {
+                0 => {
+                    self.skip_trailers().await?;
+                    self.framing = Framing::Done;
+                    return Ok(None);
+                }
+                size => {
+                    self.framing = Framing::Chunked(size);
+                    size
+                }
+            },
+            // One byte past the ceiling, to tell a body that goes over it
+            Framing::Close => MAX_BODY_BYTES + 1 - self.received,
+        };
+
+        let mut piece = vec![0; wanted.min(PIECE_BYTES) as usize];
+        let read = self.stream.read(&mut piece).await?;
+        piece.truncate(read);
+        if read == 0 {
+            if let Framing::Close = self.framing {
+                self.framing = Framing::Done;
+                return Ok(None);
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.received += read as u64;
+        match &mut self.framing {
+            Framing::Length(left) => *left -= read as u64,
+            Framing::Chunked(left) => {
+                *left -= read as u64;
+                if *left == 0 {
+                    // The line break after the chunk's data
+                    self.stream.read_line(&mut String::new()).await?;
+                }
+            }
+            Framing::Close if self.received > MAX_BODY_BYTES => return Err(too_large()),
+            _ => {}
+        }
+        Ok(Some(piece))
+    }
+
+    async fn chunk_size(&mut self) -> io::Result<u64> {
+        let mut line = String::new();
+        (&mut self.stream).take(MAX_LINE_BYTES).read_line(&mut line).await?;
+        let size = line.trim_end().split(';').next().unwrap_or_default();
+        let size = u64::from_str_radix(size.trim(), 16)
+            .map_err(|_| invalid(&format!("Malformed chunk size \"{}\"", line.trim_end())))?;
+        self.received
+            .checked_add(size)
+            .filter(|total| *total <= MAX_BODY_BYTES)
+            .ok_or_else(too_large)?;
+        Ok(size)
+    }
+
+    // Skips trailers up to the terminating blank line
+    async fn skip_trailers(&mut self) -> io::Result<()> {
+        let mut trailers = 0;
+        loop {
+            let mut line = String::new();
+            let read = (&mut self.stream).take(MAX_LINE_BYTES).read_line(&mut line).await?;
+            trailers += read;
+            if read == 0 || line.trim_end().is_empty() {
+                return Ok(());
+            }
+            if trailers > MAX_HEAD_BYTES {
+                return Err(invalid("Response trailer section is too large"));
+            }
+        }
+    }
+}
+
+// Serializes the request line and headers of a reqwest request. `target` is
+// the request-target written on the request line. A Host header is added
+// from the URL unless the request already carries one.
+pub fn request_head(request: &Request, target: &str, extra: &[(&str, String)]) -> Vec<u8> {
+    let mut head = format!("{} {} HTTP/1.1\r\n", request.method(), target);
+    if !request.headers().contains_key(HOST) {
+        head.push_str(&format!("Host: {}\r\n", host_header(request.url())));
+    }
+    for (name, value) in request.headers() {
+        head.push_str(&format!("{}: {}\r\n", name, String::from_utf8_lossy(value.as_bytes())));
+    }
+    for (name, value) in extra {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    head.into_bytes()
+}
+
+// The origin-form request target ("/path?query") of a URL
+pub fn origin_target(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+// The request target of a URL exactly as it was written, dot segments and
+// all (--path-as-is): everything from the end of the authority up to any
+// fragment. Url::parse resolves "." and ".." segments, so this has to come
+// from the original text.
+pub fn as_is_target(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.split('#').next().unwrap_or_default();
+    match rest.find(['/', '?']) {
+        Some(at) if rest[at..].starts_with('/') => rest[at..].to_string(),
+        Some(at) => format!("/{}", &rest[at..]),
+        None => "/".to_string(),
+    }
+}
+
+fn host_header(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+// Checks a --request-target value: origin-form ("/path?query"), absolute-form
+// ("http://host/path") or asterisk-form ("*")
+pub fn validate_target(target: &str) -> Result<(), String> {
+    if target.is_empty() || target.bytes().any(|b| b.is_ascii_whitespace() || b.is_ascii_control()) {
+        return Err(format!("Invalid request target \"{}\": must be non-empty without spaces", target));
+    }
+    if target == "*" || target.starts_with('/') || Url::parse(target).is_ok_and(|url| url.has_host()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid request target \"{}\": expected \"/path\", \"scheme://host/path\" or \"*\"",
+            target
+        ))
+    }
+}
+
+// The method of a raw request (--stdin-headers). The only check is that it
+// starts with a line beginning with a method, since the method decides whether
+// the response has a body; the rest of the request line, the headers and the
+// body are sent as they are, malformed or not.
+pub fn request_method(bytes: &[u8]) -> Result<Method, String> {
+    let end = bytes
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or("Raw request has no request line (\"METHOD target HTTP/1.1\" and a line break)")?;
+    let line = String::from_utf8_lossy(&bytes[..end]);
+    let method = line.trim_end_matches('\r').split(' ').next().unwrap_or_default();
+    Method::from_bytes(method.as_bytes())
+        .map_err(|_| format!("Raw request doesn't start with a method: \"{}\"", line.trim_end()))
+}
+
+// Sends `bytes` byte for byte as the whole request and reads the response.
+// The URL only says where to connect; its path and host aren't sent.
+pub async fn send_bytes(
+    url: &Url,
+    bytes: &[u8],
+    connect: Option<&(String, u16)>,
+    tunnel: Option<&ProxyTunnel>,
+) -> io::Result<Response> {
+    let method = request_method(bytes).map_err(|err| invalid(&err))?;
+    let mut connection = Connection::open(url, connect, tunnel).await?;
+    connection.send(bytes).await?;
+    connection.read_response(&method).await
+}
+
+// Sends a request with `target` on the request line and reads the response
+pub async fn send(
+    request: &Request,
+    target: &str,
+    connect: Option<&(String, u16)>,
+    tunnel: Option<&ProxyTunnel>,
+) -> io::Result<Response> {
+    let body = buffered_body(request)?;
+    let mut extra = vec![("Connection", "close".to_string())];
+    if !body.is_empty() {
+        extra.push(("Content-Length", body.len().to_string()));
+    }
+
+    let mut connection = Connection::open(request.url(), connect, tunnel).await?;
+    connection.send(&request_head(request, target, &extra)).await?;
+    connection.send(body).await?;
+    connection.read_response(request.method()).await
+}
+
+// Sends the request head with "Expect: 100-continue" and waits up to `wait`
+// for the server's go-ahead before sending the body. A final response that
+// arrives first (e.g. 417 or 401) is returned without sending the body at all;
+// if nothing arrives in time the body is sent anyway.
+pub async fn send_expect_continue(
+    request: &Request,
+    target: &str,
+    wait: Duration,
+    connect: Option<&(String, u16)>,
+    tunnel: Option<&ProxyTunnel>,
+) -> io::Result<Response> {
+    let body = buffered_body(request)?;
+    let extra = [
+        ("Content-Length", body.len().to_string()),
+        ("Expect", "100-continue".to_string()),
+        ("Connection", "close".to_string()),
+    ];
+
+    let mut connection = Connection::open(request.url(), connect, tunnel).await?;
+    connection.send(&request_head(request, target, &extra)).await?;
+
+    match tokio::time::timeout(wait, connection.read_head()).await {
+        Ok(head) => {
+            let head = head?;
+            if !head.status.is_informational() {
+                return connection.finish(head, request.method());
+            }
+        }
+        Err(_) => eprintln!("No 100 Continue after {:?}, sending body anyway", wait),
+    }
+
+    connection.send(body).await?;
+    connection.read_response(request.method()).await
+}
+
+fn buffered_body(request: &Request) -> io::Result<&[u8]> {
+    match request.body() {
+        Some(body) => body
+            .as_bytes()
+            .ok_or_else(|| invalid("A streamed body can't be sent over a raw connection, use --no-chunked")),
+        None => Ok(&[]),
+    }
+}
+
+fn too_large() -> io::Error {
+    invalid(&format!("Response body is larger than {} bytes", MAX_BODY_BYTES))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, Part};
+
+    // Sends a GET over a raw connection to a server that answers with
+    // `response`, and reads the whole body
+    async fn get(response: &'static [u8]) -> io::Result<Vec<u8>> {
+        let server = mock::serve(move |_| vec![Part::Send(response.to_vec()), Part::Close]).await;
+        let request = Request::new(Method::GET, server.at("/"));
+        let mut body = send(&request, "/", None, None).await?.body;
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.chunk().await? {
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
+    }
+
+    #[tokio::test]
+    async fn reads_a_content_length_body() {
+        let body = get(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello").await.unwrap();
+        assert_eq!(body, b"hello");
+    }
+
+    #[tokio::test]
+    async fn reads_a_chunked_body() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nX-Trailer: 1\r\n\r\n";
+        assert_eq!(get(response).await.unwrap(), b"hello world");
+    }
+
+    #[tokio::test]
+    async fn reads_to_the_end_without_a_length() {
+        let body = get(b"HTTP/1.0 200 OK\r\n\r\nuntil close").await.unwrap();
+        assert_eq!(body, b"until close");
+    }
+
+    // A length the server made up is refused rather than allocated
+    #[tokio::test]
+    async fn refuses_huge_lengths() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 18446744073709551615\r\n\r\nabc";
+        let err = get(response).await.err().unwrap();
+        assert!(err.to_string().contains("larger than"), "{}", err);
+
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffff\r\nabc";
+        let err = get(response).await.err().unwrap();
+        assert!(err.to_string().contains("larger than"), "{}", err);
+
+        // Two chunks that only overflow together
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         3\r\nabc\r\nfffffffffffffffe\r\nabc";
+        let err = get(response).await.err().unwrap();
+        assert!(err.to_string().contains("larger than"), "{}", err);
+
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n10000000000000000\r\nabc";
+        let err = get(response).await.err().unwrap();
+        assert!(err.to_string().contains("Malformed chunk size"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn a_short_body_is_an_error() {
+        let err = get(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc").await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn as_is_targets() {
+        assert_eq!(as_is_target("http://host/a/../b?x=1#frag"), "/a/../b?x=1");
+        assert_eq!(as_is_target("http://host?x"), "/?x");
+        assert_eq!(as_is_target("http://host"), "/");
+    }
+}
@@ -0,0 +1,28 @@
+use reqwest::header::{HeaderName, HeaderValue};
+use std::collections::HashSet;
+
+const DEFAULT_SENSITIVE: [&str; 3] = ["authorization", "cookie", "proxy-authorization"];
+
+// Decides which header values are hidden when requests/responses are echoed
+// back to the user (dry-run, trace, ...). Redaction is on by default.
+#[derive(Clone)]
+pub struct Redactor {
+    enabled: bool,
+    names: HashSet<String>,
+}
+
+impl Redactor {
+    pub fn new<'a>(enabled: bool, extra: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut names: HashSet<String> = DEFAULT_SENSITIVE.iter().map(|name| name.to_string()).collect();
+        names.extend(extra.into_iter().map(|name| name.trim().to_ascii_lowercase()));
+        Redactor { enabled, names }
+    }
+
+    pub fn value(&self, name: &HeaderName, value: &HeaderValue) -> String {
+        if self.enabled && self.names.contains(name.as_str()) {
+            "<redacted>".to_string()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        }
+    }
+}
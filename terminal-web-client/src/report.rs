@@ -0,0 +1,66 @@
+use crate::exchange::Exchange;
+use crate::json;
+use crate::trace;
+use reqwest::header::HeaderMap;
+use reqwest::Request;
+use std::time::Duration;
+
+// The --output-format json document, one object per request on a single line:
+//   {"url":"https://host/final","status":200,"version":"HTTP/1.1",
+//    "headers":{"content-type":["text/plain"],...},"time_ms":12.5,
+//    "body":"..."|null,"body_encoding":"utf8"|"base64"|null,"body_bytes":N}
+// Header names are lowercase and map to every value received, in order. The
+// body is null when it was left out (--no-body, or written with -o).
+pub fn json(exchange: &Exchange, elapsed: Duration, include_body: bool) -> String {
+    let (body, encoding) = body(include_body.then_some(exchange.body.as_slice()));
+    json::object([
+        ("url", json::string(exchange.url.as_str())),
+        ("status", exchange.status.as_u16().to_string()),
+        ("version", json::string(trace::version_str(exchange.version))),
+        ("headers", headers(&exchange.headers)),
+        ("time_ms", format!("{:.1}", elapsed.as_secs_f64() * 1000_f64)),
+        ("body", body),
+        ("body_encoding", encoding),
+        ("body_bytes", exchange.body.len().to_string()),
+    ])
+}
+
+// A request about to be sent, in the same shape:
+//   {"method":"GET","url":"...","headers":{...},"body":"..."|null,"body_encoding":...}
+// The body is null when there is none or it is streamed from stdin.
+pub fn request_json(request: &Request) -> String {
+    let (body, encoding) = body(request.body().and_then(|body| body.as_bytes()));
+    json::object([
+        ("method", json::string(request.method().as_str())),
+        ("url", json::string(request.url().as_str())),
+        ("headers", headers(request.headers())),
+        ("body", body),
+        ("body_encoding", encoding),
+    ])
+}
+
+// Header names mapped to every value, in the order received
+fn headers(headers: &HeaderMap) -> String {
+    let mut names: Vec<&str> = Vec::new();
+    for name in headers.keys() {
+        if !names.contains(&name.as_str()) {
+            names.push(name.as_str());
+        }
+    }
+    json::object(names.into_iter().map(|name| {
+        let values = headers
+            .get_all(name)
+            .iter()
+            .map(|value| json::string(&String::from_utf8_lossy(value.as_bytes())));
+        (name, json::array(values))
+    }))
+}
+
+// The body as text when it is UTF-8 and base64 otherwise, with its encoding
+fn body(bytes: Option<&[u8]>) -> (String, String) {
+    match bytes.map(|bytes| (bytes, std::str::from_utf8(bytes))) {
+        None => ("null".to_string(), "null".to_string()),
+        Some((_, Ok(text))) => (json::string(text), json::string("utf8")),
+        Some((bytes, Err(_))) => (json::string(&json::base64(bytes)), json::string("base64")),
+    }
+}
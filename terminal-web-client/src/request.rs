@@ -0,0 +1,1249 @@
+use crate::audit::RequestLog;
+use crate::auth;
+use crate::canonical;
+use crate::color;
+use crate::connect::ConnectTo;
+use crate::cookies;
+use crate::decode;
+use crate::diff;
+use crate::dns;
+use crate::editor;
+use crate::exchange::{Exchange, Transport, MAX_REDIRECTS};
+use crate::expect::{Expectations, HeaderAssertion, StatusRange};
+use crate::filename;
+use crate::form;
+use crate::glob;
+use crate::headers;
+use crate::hook;
+use crate::limits::{HeaderLimits, RateLimit, SpeedLimit};
+use crate::metrics;
+use crate::net;
+use crate::pretty;
+use crate::progress::{self, ProgressMode};
+use crate::raw;
+use crate::redact::Redactor;
+use crate::sigv4::{self, Signer};
+use crate::template;
+use crate::report;
+use crate::resume::{self, ContinueAt};
+use crate::retry::{Jitter, RetryPolicy, Rng};
+use crate::reuse;
+use crate::trace::{self, Trace};
+use crate::tunnel::{self, ProxyTunnel};
+use clap::ArgMatches;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_TYPE, HOST, RANGE,
+};
+use reqwest::{redirect, Body, Client, ClientBuilder, Method, Proxy, Request, StatusCode, Url, Version};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio_util::io::ReaderStream;
+
+// Everything needed to send the request described on the command line
+pub struct Prepared {
+    pub urls: Vec<Target>,
+    pub request: Request,
+    pub transport: Transport,
+    pub redactor: Redactor,
+    pub trace: Option<Trace>,
+}
+
+// One URL to send the request to, and where its response body goes
+pub struct Target {
+    pub url: Url,
+    pub output: Option<String>,
+    // Where the body goes instead when the status is 400 or above (--output-error-to)
+    pub error_output: Option<String>,
+}
+
+// Builds the client and the request from the request options (shared by `request` and `bench`)
+pub async fn prepare(matches: &ArgMatches) -> Result<Prepared, String> {
+    // URLs to request, checked up front so a typo doesn't surface halfway through a batch.
+    // URL globs ("{a,b}", "[1-10]") expand into several URLs unless --globoff is given,
+    // and "#1", "#2", ... in -o and --output-error-to are replaced with what each glob matched.
+    let output = matches.get_one::<String>("output");
+    let error_output = matches.get_one::<String>("output-error-to");
+    let mut urls = Vec::new();
+    let mut path_as_is = HashMap::new();
+    for pattern in matches.get_many::<String>("url").unwrap() {
+        let expanded = if matches.get_flag("globoff") {
+            vec![glob::Expanded {
+                url: pattern.clone(),
+                matches: Vec::new(),
+            }]
+        } else {
+            glob::expand(pattern)?
+        };
+        let globs = expanded.first().map_or(0, |expanded| expanded.matches.len());
+        let mut templates = output.into_iter().chain(error_output);
+        if let Some(template) = templates.find(|template| glob::max_placeholder(template) > globs) {
+            return Err(format!(
+                "Output \"{}\" uses #{} but URL \"{}\" has {} glob(s)",
+                template,
+                glob::max_placeholder(template),
+                pattern,
+                globs
+            ));
+        }
+        // Each URL a template doesn't tell apart would overwrite the previous
+        // one's file. Appending them all to one -o file is fine.
+        let overwritten = output.filter(|_| !matches.get_flag("append")).into_iter().chain(error_output);
+        for template in overwritten {
+            if let Some(n) = glob::unused_glob(template, &expanded) {
+                return Err(format!(
+                    "Output \"{}\" doesn't use #{} of URL \"{}\", so its URLs would all be saved to the same file",
+                    template, n, pattern
+                ));
+            }
+        }
+        for mut expanded in expanded {
+            if matches.get_flag("url-encode") {
+                expanded.url = percent_encode_url(&expanded.url);
+            }
+            let url = Url::parse(&expanded.url)
+                .map_err(|err| format!("Invalid URL \"{}\": {}", expanded.url, err))?;
+            // Parsing resolves dot segments; only a path it changed is sent another way
+            if matches.get_flag("path-as-is") {
+                let target = raw::as_is_target(&expanded.url);
+                if target != raw::origin_target(&url) {
+                    raw::validate_target(&target)?;
+                    if path_as_is.get(&url).is_some_and(|other| *other != target) {
+                        return Err(format!(
+                            "With --path-as-is, \"{}\" and another URL both normalize to {}",
+                            expanded.url, url
+                        ));
+                    }
+                    path_as_is.insert(url.clone(), target);
+                }
+            }
+            let output = match output {
+                Some(template) => Some(glob::fill(template, &expanded.matches)),
+                None if matches.get_flag("remote-name") => Some(
+                    filename::from_url(&url)
+                        .ok_or_else(|| format!("-O: \"{}\" has no file name at the end of its path", url))?,
+                ),
+                None => None,
+            };
+            urls.push(Target {
+                url,
+                output,
+                error_output: error_output.map(|template| glob::fill(template, &expanded.matches)),
+            });
+        }
+    }
+
+    // With --stdin-headers the whole request comes from stdin, method included;
+    // the URL is still needed to know where to connect
+    let raw_request = if matches.get_flag("stdin-headers") {
+        let mut bytes = Vec::new();
+        if let Err(err) = tokio::io::stdin().read_to_end(&mut bytes).await {
+            return Err(format!("Unable to read the request from stdin: {}", err));
+        }
+        Some(bytes)
+    } else {
+        None
+    };
+    let raw_method = raw_request.as_deref().map(raw::request_method).transpose()?;
+    let method_str = matches.get_one::<String>("method").map_or("GET", String::as_str); // HTTP method (GET, POST, etc.)
+    
+    // Parse headers if any are provided; a name given twice is sent twice
+    let headers = headers::parse_all(matches.get_many::<String>("headers").unwrap_or_default().map(String::as_str))
+        .map_err(|err| format!("Invalid --headers value: {}", err))?;
+
+    // Parse body if provided
+    let body_str = matches.get_one::<String>("body");
+
+    // Compressed responses are decoded by the transport unless the raw bytes are wanted.
+    // --accept-encoding picks the advertised encodings, and only those are decoded.
+    let compressed = matches.get_flag("compressed");
+    let keep_encoded = matches.get_flag("compressed-output") || matches.get_flag("raw");
+    let accept_encoding = matches.get_one::<String>("accept-encoding");
+    let advertised = |encoding: &str| {
+        accept_encoding.map_or(compressed, |value| {
+            value.split(',').any(|item| item.split(';').next().unwrap_or_default().trim() == encoding)
+        })
+    };
+    // --verbose tells new connections from reused ones by watching reqwest's
+    // connection logging
+    let verbose = matches.get_flag("verbose");
+    if verbose {
+        reuse::install();
+    }
+
+    // Host names are looked up with the --dns-servers or --doh-url, by
+    // reqwest and by the raw connection alike
+    let resolver = match (matches.get_one::<Vec<SocketAddr>>("dns-servers"), matches.get_one::<Url>("doh-url")) {
+        (Some(servers), _) => Some(dns::with_servers(servers.clone(), verbose)),
+        (None, Some(url)) => Some(dns::with_doh(url.clone(), verbose)?),
+        (None, None) => None,
+    };
+
+    // Cookies from a saved jar go out with every matching request
+    let jar = match matches.get_one::<String>("load-cookies") {
+        Some(path) => Some(Arc::new(cookies::load_jar(path, matches.get_flag("junk-session-cookies"))?)),
+        None => None,
+    };
+
+    // A SOCKS5 proxy for everything the client sends; with socks5h the proxy
+    // resolves host names, so no DNS lookup leaks outside it
+    let socks = [("socks5", "socks5"), ("socks5-hostname", "socks5h")]
+        .into_iter()
+        .find_map(|(arg, scheme)| Some((arg, scheme, matches.get_one::<(String, u16)>(arg)?)));
+    let proxy = match socks {
+        Some((_, scheme, (host, port))) => {
+            let host = match host.contains(':') {
+                true => format!("[{}]", host),
+                false => host.clone(),
+            };
+            let mut proxy = Proxy::all(format!("{}://{}:{}", scheme, host, port))
+                .map_err(|err| format!("Invalid SOCKS5 proxy: {}", err))?;
+            if let Some((user, password)) = matches.get_one::<(String, String)>("proxy-user") {
+                proxy = proxy.basic_auth(user, password);
+            }
+            Some(proxy)
+        }
+        None => None,
+    };
+
+    // Every client gets the same settings; a forced HTTP version adds its own
+    let client_builder = || -> Result<ClientBuilder, String> {
+        let mut builder = Client::builder().connection_verbose(verbose);
+        // Bind to a specific local interface if requested
+        if let Some(interface) = matches.get_one::<String>("interface") {
+            builder = builder.local_address(net::interface_addr(interface)?);
+        }
+        if let Some(resolver) = &resolver {
+            builder = builder.dns_resolver(resolver.clone());
+        }
+        // Connection reuse tuning, mostly useful together with --repeat
+        if let Some(secs) = matches.get_one::<u64>("keepalive") {
+            builder = builder.tcp_keepalive(Duration::from_secs(*secs));
+        }
+        if let Some(secs) = matches.get_one::<f64>("pool-idle-timeout") {
+            builder = builder.pool_idle_timeout(Duration::from_secs_f64(*secs));
+        }
+        if let Some(max) = matches.get_one::<usize>("pool-max-idle") {
+            builder = builder.pool_max_idle_per_host(*max);
+        }
+        // No idle connections are kept, so each request connects again
+        if matches.get_flag("no-keepalive") {
+            builder = builder.pool_max_idle_per_host(0);
+        }
+        if let Some(jar) = &jar {
+            builder = builder.cookie_provider(jar.clone());
+        }
+        if let Some(proxy) = &proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        if let Some(policy) = redirect_policy(matches) {
+            builder = builder.redirect(policy);
+        }
+        // With reqwest's http3 feature on, native-tls no longer offers h2
+        #[cfg(feature = "http3")]
+        if matches.get_flag("http3") {
+            builder = builder.use_rustls_tls();
+        }
+        Ok(builder)
+    };
+
+    // --http1.1, --http2 and --http3 send each pooled request with a client
+    // that speaks only that version: HTTP/2 with prior knowledge, so a server
+    // without it fails instead of quietly answering in HTTP/1.1. Whether the
+    // regular client, which negotiates, may then step in is up to
+    // --http-version-fallback; HTTP/3 is tried first and falls back unless
+    // told otherwise, the others don't.
+    let forced = if matches.get_flag("http1.1") {
+        Some((Version::HTTP_11, client_builder()?.http1_only()))
+    } else if matches.get_flag("http2") {
+        Some((Version::HTTP_2, client_builder()?.http2_prior_knowledge()))
+    } else if matches.get_flag("http3") {
+        #[cfg(feature = "http3")]
+        {
+            let builder = client_builder()?
+                .http3_prior_knowledge()
+                .set_quic_max_idle_timeout(Duration::from_secs(10));
+            Some((Version::HTTP_3, builder))
+        }
+        #[cfg(not(feature = "http3"))]
+        return Err("--http3 needs a build with the \"http3\" feature (see Cargo.toml)".to_string());
+    } else {
+        None
+    };
+    let forced = match forced {
+        Some((version, builder)) => {
+            let client = builder.build().map_err(|err| {
+                format!("Unable to create {} client: {}", trace::version_str(version), err)
+            })?;
+            Some((version, client))
+        }
+        None => None,
+    };
+    let version_fallback = match matches.get_one::<String>("http-version-fallback").map(String::as_str) {
+        Some(setting) => setting == "on",
+        None => forced.as_ref().is_some_and(|(version, _)| *version == Version::HTTP_3),
+    };
+
+    let client = client_builder()?
+        .build()
+        .map_err(|err| format!("Unable to create HTTP client: {}", err))?;
+
+    // Convert the string method to an actual Method enum; a raw request can use any
+    let method = match (raw_method, method_str.to_uppercase().as_str()) {
+        (Some(method), _) => method,
+        (None, "GET") => Method::GET,
+        (None, "POST") => Method::POST,
+        (None, "PUT") => Method::PUT,
+        (None, "DELETE") => Method::DELETE,
+        (None, "PATCH") => Method::PATCH,
+        (None, "HEAD") => Method::HEAD,
+        (None, _) => return Err(format!("Unsupported HTTP method: {}", method_str)),
+    };
+    if matches.get_flag("dedup") && !matches!(method, Method::GET | Method::HEAD) {
+        eprintln!("--dedup has no effect on {} requests, every URL is sent", method);
+    }
+
+    // Start building the request; it is sent as-is to every URL
+    let mut request = client.request(method, urls[0].url.clone());
+
+    // Add headers to the request if there are any
+    request = request.headers(headers);
+
+    // Bearer token, either given directly or fetched from a credential helper
+    let token = match (matches.get_one::<String>("oauth2-bearer"), matches.get_one::<String>("oauth2-bearer-cmd")) {
+        (Some(token), _) => Some(token.clone()),
+        (None, Some(command)) => Some(auth::token_from_command(command).await?),
+        (None, None) => None,
+    };
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+
+    // Override the Host header; hyper only fills it in from the URL when it's missing
+    if let Some(host) = matches.get_one::<String>("host-header") {
+        match HeaderValue::from_str(host) {
+            // headers() replaces rather than appends, so this wins over --headers "Host: ..."
+            Ok(value) if !host.is_empty() => request = request.headers(HeaderMap::from_iter([(HOST, value)])),
+            _ => return Err(format!("Invalid Host header value \"{}\"", host)),
+        }
+    }
+
+    // The transport decodes responses rather than reqwest, which therefore
+    // advertises nothing, so ask for the encodings ourselves
+    if let Some(value) = accept_encoding {
+        request = request.header(ACCEPT_ENCODING, value.as_str());
+    } else if compressed {
+        request = request.header(ACCEPT_ENCODING, "gzip, br, zstd");
+    }
+
+    // Add the body to the request if provided (for POST, PUT, etc.). A stdin body
+    // has no known length, so it is streamed with chunked transfer-encoding.
+    if let Some(body) = body_str {
+        if body == "@-" && !matches.get_flag("no-chunked") {
+            request = request.body(Body::wrap_stream(ReaderStream::new(tokio::io::stdin())));
+        } else if body == "@-" {
+            let mut buffer = Vec::new();
+            if let Err(err) = tokio::io::stdin().read_to_end(&mut buffer).await {
+                return Err(format!("Unable to read body from stdin: {}", err));
+            }
+            request = request.body(buffer);
+        } else {
+            request = request.body(body.to_string());
+        }
+    }
+
+    // A body template is filled in once, before any URL is sent to
+    if let Some(path) = matches.get_one::<String>("body-template") {
+        let vars = matches
+            .get_many::<(String, String)>("var")
+            .unwrap_or_default()
+            .cloned()
+            .collect();
+        request = request.body(template::render(path, &vars, matches.get_flag("allow-missing-vars"))?);
+    }
+
+    // An edited body is written once, before any URL is sent to
+    if matches.get_flag("body-from-editor") {
+        request = request.body(editor::compose().await?);
+    }
+
+    // Build a multipart body from -F/--form-string/--form-json fields, in command-line order
+    let mut fields = Vec::new();
+    if let (Some(indices), Some(values)) = (matches.indices_of("form"), matches.get_many::<String>("form")) {
+        fields.extend(indices.zip(values).map(|(index, value)| (index, form::Part::parse(value))));
+    }
+    if let (Some(indices), Some(values)) =
+        (matches.indices_of("form-string"), matches.get_many::<String>("form-string"))
+    {
+        fields.extend(indices.zip(values).map(|(index, value)| (index, form::Part::literal(value))));
+    }
+    if let (Some(indices), Some(values)) = (matches.indices_of("form-json"), matches.get_many::<String>("form-json")) {
+        for (index, value) in indices.zip(values) {
+            fields.extend(form::from_json(value)?.into_iter().map(|part| (index, Ok(part))));
+        }
+    }
+    if !fields.is_empty() {
+        fields.sort_by_key(|(index, _)| *index);
+        let parts: Vec<form::Part> = fields
+            .into_iter()
+            .map(|(_, part)| part)
+            .collect::<Result<_, _>>()?;
+        let (content_type, body) = form::encode(&parts);
+        request = request.header(CONTENT_TYPE, content_type).body(body);
+    }
+
+    // Sensitive header values are hidden from anything we echo back
+    let redactor = Redactor::new(
+        !matches.get_flag("no-redact"),
+        matches.get_many::<String>("redact-header").unwrap_or_default(),
+    );
+
+    if let Some(format) = matches.get_one::<String>("trace-time") {
+        trace::set_time_format(format);
+    }
+
+    // Open the trace output if requested
+    let trace = match matches.get_one::<String>("trace-ascii") {
+        Some(path) => Some(
+            Trace::open(path, redactor.clone())
+                .map_err(|err| format!("Unable to open trace file \"{}\": {}", path, err))?,
+        ),
+        None => None,
+    };
+
+    // Build the request
+    let mut request = request
+        .build()
+        .map_err(|err| format!("Invalid request: {}", err))?;
+
+    // Gzip the body if asked to; only servers that expect it will accept this
+    if matches.get_flag("compressed-request") {
+        let compressed = match request.body().map(|body| body.as_bytes()) {
+            Some(Some(body)) => gzip(body),
+            Some(None) => {
+                return Err("--compressed-request can't compress a streamed body, use --no-chunked".to_string())
+            }
+            None => return Err("--compressed-request needs a request body".to_string()),
+        };
+        let compressed = compressed.map_err(|err| format!("Unable to compress request body: {}", err))?;
+        *request.body_mut() = Some(compressed.into());
+        request.headers_mut().insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    }
+
+    // Check an overridden request-target before anything is sent
+    let request_target = matches.get_one::<String>("request-target").cloned();
+    if let Some(target) = &request_target {
+        raw::validate_target(target)?;
+    }
+
+    // Progress goes to stderr: JSON when asked for, a bar when saving to a file from a terminal
+    let progress = if matches.get_flag("progress-json") {
+        ProgressMode::Json
+    } else if matches.contains_id("save") && std::io::stderr().is_terminal() {
+        ProgressMode::Bar
+    } else {
+        ProgressMode::None
+    };
+
+    // AWS credentials are looked up once; each request is signed as it goes out
+    let aws_sigv4 = match matches.get_one::<sigv4::Config>("aws-sigv4") {
+        Some(config) => Some(Signer {
+            config: config.clone(),
+            credentials: sigv4::credentials()?,
+        }),
+        None => None,
+    };
+
+    let transport = Transport {
+        client,
+        forced,
+        version_fallback,
+        request_target,
+        path_as_is,
+        raw_request,
+        expect100: matches.get_one::<f64>("expect100-timeout").map(|secs| Duration::from_secs_f64(*secs)),
+        connect_to: matches.get_many::<ConnectTo>("connect-to").unwrap_or_default().cloned().collect(),
+        proxy_tunnel: matches.get_one::<(String, u16)>("proxy-tunnel").map(|(host, port)| ProxyTunnel {
+            host: host.clone(),
+            port: *port,
+            authorization: matches
+                .get_one::<(String, String)>("proxy-user")
+                .map(|(user, password)| tunnel::basic(user, password)),
+            verbose: matches.get_flag("verbose"),
+        }),
+        aws_sigv4,
+        header_limits: HeaderLimits {
+            max_count: *matches.get_one::<usize>("max-headers").unwrap(),
+            max_bytes: *matches.get_one::<usize>("max-header-bytes").unwrap(),
+        },
+        speed_limit: speed_limit(matches),
+        rate_limit: matches.get_one::<u64>("limit-rate").map(|rate| RateLimit::new(*rate)),
+        decode: decode::ENCODINGS.into_iter().filter(|encoding| advertised(encoding) && !keep_encoded).collect(),
+        location_trusted: matches.get_flag("location-trusted"),
+        verbose,
+        progress,
+        dedup: matches.get_flag("dedup").then(Default::default),
+        request_log: matches.get_one::<String>("log-requests").map(|path| RequestLog::open(path)).transpose()?,
+    };
+
+    // The raw connection doesn't go through reqwest's proxy, and silently
+    // connecting directly would defeat the point of a SOCKS5 proxy
+    if let Some((arg, _, _)) = socks {
+        if transport.uses_raw(&request) {
+            return Err(format!(
+                "--{} can't be combined with options that need a raw connection \
+                 (--request-target, --path-as-is, --expect100-timeout, --connect-to, --stdin-headers)",
+                arg
+            ));
+        }
+    }
+    // Likewise a raw connection would be a fallback to HTTP/1.1 nobody allowed
+    if let Some((version, _)) = transport.forced.as_ref().filter(|_| !transport.version_fallback) {
+        if *version != Version::HTTP_11 && transport.uses_raw(&request) {
+            return Err(format!(
+                "--{} can't be combined with options that need a raw HTTP/1.1 connection \
+                 (--request-target, --path-as-is, --expect100-timeout, --connect-to, --proxy-tunnel, \
+                 --stdin-headers) unless --http-version-fallback=on",
+                if *version == Version::HTTP_2 { "http2" } else { "http3" }
+            ));
+        }
+    }
+
+    Ok(Prepared {
+        urls,
+        request,
+        transport,
+        redactor,
+        trace,
+    })
+}
+
+// Exit status for a command line that can't be run as given, as clap exits
+// for the options it rejects itself; 1 is for requests that ran and failed
+pub const USAGE_ERROR: i32 = 2;
+
+// The `request` subcommand: sends the request to every URL and writes out the responses
+pub async fn run(matches: &ArgMatches) {
+    if let Err(err) = progress::dump_on_signal() {
+        eprintln!("Unable to listen for SIGUSR1: {}", err);
+    }
+    let Prepared {
+        urls,
+        request,
+        transport,
+        redactor,
+        mut trace,
+    } = match prepare(matches).await {
+        Ok(prepared) => prepared,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(USAGE_ERROR);
+        }
+    };
+
+    // Just show the request for every URL on a dry run
+    if matches.get_flag("dry-run") {
+        for target in &urls {
+            print_request(&request, &target.url, &redactor);
+        }
+        return;
+    }
+
+    // An existing -o/-O file is only replaced once the user says so
+    if let Err(err) = confirm_overwrites(&urls, matches) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+
+    // Send it to each URL once, or --repeat times over the same client so connections are pooled
+    let repeat = *matches.get_one::<u32>("repeat").unwrap() as usize;
+    let total = urls.len() * repeat;
+    if total > 1 && request.try_clone().is_none() {
+        eprintln!("A streamed body can only be sent once, use --no-chunked with --repeat or several URLs");
+        std::process::exit(USAGE_ERROR);
+    }
+
+    // What a response must look like to count as a success
+    let expectations = Expectations::new(
+        matches.get_many::<StatusRange>("expect-status").unwrap_or_default().copied().collect(),
+        matches.get_many::<HeaderAssertion>("expect-header").unwrap_or_default().cloned().collect(),
+        matches.get_many::<String>("expect-body-contains").unwrap_or_default().cloned().collect(),
+        matches.get_many::<String>("expect-body-matches").unwrap_or_default().cloned().collect(),
+        matches.get_flag("expect-ignore-case"),
+    );
+    let expectations = match expectations {
+        Ok(expectations) => expectations,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(USAGE_ERROR);
+        }
+    };
+
+    let abort_on_error = matches.get_flag("abort-on-error");
+    let mut outcomes = Vec::new();
+    let mut request = Some(request);
+    let interval = matches.get_one::<f64>("repeat-interval").map(|secs| Duration::from_secs_f64(*secs));
+    'urls: for target in &urls {
+        for round in 0..repeat {
+            if let Some(interval) = interval.filter(|_| round > 0) {
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = tokio::signal::ctrl_c() => break 'urls,
+                }
+            }
+            let mut attempt = if outcomes.len() + 1 < total {
+                request.as_ref().and_then(Request::try_clone).expect("checked above")
+            } else {
+                request.take().expect("last attempt")
+            };
+            *attempt.url_mut() = target.url.clone();
+            // Every round is sent; --dedup only spares a URL listed again
+            if round > 0 {
+                transport.forget(&target.url);
+            }
+
+            // Ctrl-C abandons the request in flight
+            let started = Instant::now();
+            let writing_output = Cell::new(false);
+            let result = tokio::select! {
+                result = perform(&transport, attempt, target, matches, &expectations, trace.as_mut(), &writing_output) => result,
+                _ = tokio::signal::ctrl_c() => interrupted(target, &transport, writing_output.get()),
+            };
+            let status = match &result {
+                Ok(status) => Some(*status),
+                Err(failure) => {
+                    eprintln!("{}", failure.message);
+                    failure.status
+                }
+            };
+            outcomes.push(Outcome {
+                url: target.url.to_string(),
+                status,
+                elapsed: started.elapsed(),
+                failed: result.is_err(),
+            });
+
+            if abort_on_error && outcomes.last().is_some_and(|outcome| outcome.failed) {
+                break 'urls;
+            }
+        }
+    }
+
+    if urls.len() > 1 {
+        print_summary(&outcomes);
+    }
+    if let Some(path) = matches.get_one::<String>("metrics-prometheus") {
+        let samples: Vec<_> = outcomes.iter().map(|outcome| (outcome.elapsed, outcome.status)).collect();
+        metrics::write(path, &samples);
+    }
+    if outcomes.iter().any(|outcome| outcome.failed) {
+        std::process::exit(1);
+    }
+}
+
+// The result of one request in a batch, for the summary table
+struct Outcome {
+    url: String,
+    status: Option<StatusCode>,
+    elapsed: Duration,
+    failed: bool,
+}
+
+// Why a request counts as failed, with the response status if there was one
+struct Failure {
+    status: Option<StatusCode>,
+    message: String,
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure { status: None, message }
+    }
+}
+
+// Sends one request and writes out its response. Returns the response status
+// when the request succeeded (2xx, or one of the --expect-status codes, and
+// any body expectations hold).
+async fn perform(
+    transport: &Transport,
+    mut request: Request,
+    target: &Target,
+    matches: &ArgMatches,
+    expectations: &Expectations,
+    mut trace: Option<&mut Trace>,
+    writing_output: &Cell<bool>,
+) -> Result<StatusCode, Failure> {
+    let started = Instant::now();
+    if let Some(command) = matches.get_one::<String>("pre-request-cmd") {
+        hook::pre_request(command, &mut request).await?;
+    }
+    let quic = transport.forced.as_ref().is_some_and(|(version, _)| *version == Version::HTTP_3);
+    let protocol = (matches.get_flag("show-protocol") || quic)
+        .then(|| (request.url().scheme() == "https", transport.uses_raw(&request)));
+    // With --no-buffer a body bound for stdout is written out as each chunk
+    // arrives, provided the status means it would be printed at all
+    let json = matches.get_one::<String>("output-format").is_some_and(|format| format == "json");
+    let live = matches.get_flag("no-buffer")
+        && target.output.is_none()
+        && !json
+        && !matches.contains_id("header-out");
+    let fail_with_body = matches.get_flag("fail-with-body");
+    // --stdout-tee echoes the body saved with -o/-O to stdout byte for byte,
+    // as it arrives, like --no-buffer but without the "Response: " label
+    let tee = matches.get_flag("stdout-tee");
+    // --raw writes the body exactly as received: no decoding (see
+    // keep_encoded), formatting, label or trailing newline
+    let raw_output = matches.get_flag("raw");
+
+    // A large JSON body bound for stdout with --pretty is formatted as it
+    // arrives and never held whole, so a multi-GB dump doesn't run out of
+    // memory. That is only possible when nothing else needs the complete body
+    // afterwards; a small or known-small body takes the in-memory path.
+    let pretty = match matches.get_one::<String>("pretty").map(String::as_str) {
+        _ if raw_output => false,
+        Some("always") => true,
+        Some("auto") => std::io::stdout().is_terminal(),
+        _ => false,
+    };
+    let stream_pretty = pretty
+        && !live
+        && target.output.is_none()
+        && !json
+        && !matches.contains_id("header-out")
+        && trace.is_none()
+        && transport.dedup.is_none()
+        && !matches.get_flag("continue")
+        && !matches.get_flag("json-normalize")
+        && ["expect-body-contains", "expect-body-matches", "diff-against", "post-response-cmd"]
+            .iter()
+            .all(|id| !matches.contains_id(id));
+    let mut pretty_stream: Option<pretty::JsonStream> = None;
+
+    // -C: the bytes before `resume_from` are already in the output file, so
+    // only the rest is asked for. With --continue, what each attempt received
+    // is kept in `received`, and a retry after the transfer broke off asks
+    // for the bytes after it.
+    let resume_from = match matches.get_one::<ContinueAt>("continue-at") {
+        None => 0,
+        Some(ContinueAt::Offset(offset)) => *offset,
+        Some(ContinueAt::OutputSize) => target
+            .output
+            .as_ref()
+            .and_then(|path| fs::metadata(path).ok())
+            .map_or(0, |metadata| metadata.len()),
+    };
+    let resume_retries = matches.get_flag("continue");
+    let received = Mutex::new(Vec::new());
+
+    let mut streamed = false;
+    let mut on_chunk = |status: StatusCode, headers: &HeaderMap, chunk: &[u8]| {
+        if resume_retries {
+            received.lock().unwrap().extend_from_slice(chunk);
+        }
+        let passed = expectations.check_status(status).is_ok();
+        if (!passed && !fail_with_body) || (target.error_output.is_some() && is_error(status)) {
+            return true;
+        }
+        if passed && stream_pretty && pretty::streams(headers) {
+            let stream = pretty_stream.get_or_insert_with(|| {
+                println!("Response:");
+                pretty::JsonStream::new(color::enabled(matches))
+            });
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(stream.feed(chunk).as_bytes());
+            let _ = stdout.flush();
+            return false;
+        }
+        if !live && !tee {
+            return true;
+        }
+        let mut stdout = std::io::stdout().lock();
+        if !streamed {
+            streamed = true;
+            if !tee && !raw_output {
+                let _ = stdout.write_all(b"Response: ");
+            }
+        }
+        let _ = stdout.write_all(chunk);
+        let _ = stdout.flush();
+        true
+    };
+    let retry = retry_policy(matches);
+    let mut retries = 0;
+    let mut response = loop {
+        // Keep a copy to resend if the server turns this attempt away. A
+        // streamed body can only be sent once, so it is never retried.
+        let spare = retry
+            .as_ref()
+            .filter(|policy| retries < policy.retries)
+            .and_then(|_| request.try_clone());
+        let kept = received.lock().unwrap().len();
+        let offset = resume_from + kept as u64;
+        if offset > 0 {
+            let range = HeaderValue::from_str(&format!("bytes={}-", offset)).expect("valid header value");
+            request.headers_mut().insert(RANGE, range);
+        }
+        if let Some(signer) = &transport.aws_sigv4 {
+            signer.sign(&mut request)?;
+        }
+        if let Some(trace) = trace.as_mut() {
+            if let Err(err) = trace.request(&request, &transport.target(&request)) {
+                eprintln!("Unable to write trace: {}", err);
+            }
+        }
+
+        let response = match transport.send_with(request, &mut on_chunk).await {
+            Ok(response) => response,
+            // The transfer broke off after some of the body arrived: ask for the rest
+            Err(message) if resume_retries && received.lock().unwrap().len() > kept => {
+                let (Some(spare), Some(policy)) = (spare, &retry) else {
+                    return Err(message.into());
+                };
+                let wait = policy.delay(retries, &HeaderMap::new());
+                retries += 1;
+                eprintln!(
+                    "{}, resuming at byte {} in {:.1}s ({} of {})",
+                    message,
+                    resume_from + received.lock().unwrap().len() as u64,
+                    wait.as_secs_f64(),
+                    retries,
+                    policy.retries
+                );
+                tokio::time::sleep(wait).await;
+                request = spare;
+                continue;
+            }
+            Err(message) => return Err(message.into()),
+        };
+        // A complete response replaces whatever this attempt had streamed so far
+        received.lock().unwrap().truncate(kept);
+        match (spare, &retry) {
+            (Some(spare), Some(policy)) if policy.should_retry(response.status) => {
+                let wait = policy.delay(retries, &response.headers);
+                retries += 1;
+                eprintln!(
+                    "{} answered {}, retrying in {:.1}s ({} of {})",
+                    target.url,
+                    response.status,
+                    wait.as_secs_f64(),
+                    retries,
+                    policy.retries
+                );
+                tokio::time::sleep(wait).await;
+                request = spare;
+            }
+            _ => break response,
+        }
+    };
+
+    // A 206 continues from where the file and earlier attempts left off, so it
+    // must start exactly there; a 200 means the server ignored the range and
+    // sent the whole body, which replaces everything before it
+    let resumed = response.status == StatusCode::PARTIAL_CONTENT && resume_from > 0;
+    if response.status == StatusCode::PARTIAL_CONTENT {
+        let expected = resume_from + received.lock().unwrap().len() as u64;
+        if resume::range_start(&response.headers) != Some(expected) {
+            return Err(Failure {
+                status: Some(response.status),
+                message: format!("Server did not resume at byte {} as asked", expected),
+            });
+        }
+        let mut body = std::mem::take(&mut *received.lock().unwrap());
+        body.append(&mut response.body);
+        response.body = body;
+    }
+    if let Some((tls, raw)) = protocol {
+        eprintln!("Protocol: {} (ALPN: {})", trace::version_str(response.version), alpn(&response, tls, raw));
+    }
+
+    if let Some(trace) = trace.as_mut() {
+        if let Err(err) = trace.response(response.version, response.status, &response.headers, &response.body) {
+            eprintln!("Unable to write trace: {}", err);
+        }
+    }
+
+    if let Some(command) = matches.get_one::<String>("post-response-cmd") {
+        hook::post_response(command, report::json(&response, started.elapsed(), true))
+            .await
+            .map_err(|message| Failure {
+                status: Some(response.status),
+                message,
+            })?;
+    }
+
+    // Print just the requested header, failing if the server didn't send it
+    if let Some(name) = matches.get_one::<String>("header-out") {
+        let values = response.headers.get_all(name.as_str());
+        if values.iter().next().is_none() {
+            return Err(Failure {
+                status: Some(response.status),
+                message: format!("Header \"{}\" not found in response", name),
+            });
+        }
+        for value in values {
+            println!("{}", String::from_utf8_lossy(value.as_bytes()));
+        }
+        return Ok(response.status);
+    }
+
+    // Everything from here on, output and assertions alike, sees the canonical
+    // form of a JSON body
+    if matches.get_flag("json-normalize") {
+        let content_type = response.headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok());
+        if let Some(text) = canonical::is_json(content_type).then(|| canonical::json(&response.body)).flatten() {
+            response.body = text.into_bytes();
+        }
+    }
+
+    // Check the response status against --expect-status, or 2xx by default. The
+    // body of a failed response is only written out with --fail-with-body.
+    let failed = |message| Failure {
+        status: Some(response.status),
+        message,
+    };
+    let status_check = expectations.check_status(response.status);
+    // Every expectation at once, the status included, for the failure report
+    let checked = expectations.check(&response);
+
+    // The body of a 4xx/5xx response goes to --output-error-to instead of -o
+    // or stdout. This takes precedence over --fail-with-body: the body is saved
+    // whether or not the status counts as a failure, and the request still
+    // fails when it does.
+    if let Some(path) = target.error_output.as_ref().filter(|_| is_error(response.status)) {
+        tokio::fs::write(path, &response.body)
+            .await
+            .map_err(|err| format!("Unable to write error output file \"{}\": {}", path, err))?;
+        eprintln!("Saved the {} response body to \"{}\"", response.status, path);
+        checked.map_err(failed)?;
+        return Ok(response.status);
+    }
+
+    if status_check.is_err() && !fail_with_body {
+        return Err(failed(checked.err().unwrap_or_default()));
+    }
+
+    // -J names the file after Content-Disposition when the response suggests a
+    // usable name. The name comes from the server, so unlike -O it never
+    // replaces an existing file.
+    let header_name = matches
+        .get_flag("remote-header-name")
+        .then(|| response.headers.get(CONTENT_DISPOSITION)?.to_str().ok())
+        .flatten()
+        .and_then(filename::from_content_disposition);
+    if let Some(name) = &header_name {
+        if Path::new(name).exists() {
+            return Err(failed(format!("Refusing to overwrite \"{}\" named by Content-Disposition", name)));
+        }
+    }
+
+    if let Some(path) = header_name.as_ref().or(target.output.as_ref()) {
+        // --append adds each response to the end of the file instead of
+        // replacing it. An interrupted append still leaves a file -C can
+        // resume, or that holds earlier responses, so only a rewritten file is
+        // removed. That is the -o/-O file, so a -J file is never removed.
+        let append = resumed || matches.get_flag("append");
+        writing_output.set(!append && header_name.is_none());
+        let written = if append {
+            append_file(path, &response.body).await
+        } else {
+            tokio::fs::write(path, &response.body).await
+        };
+        written.map_err(|err| format!("Unable to write output file \"{}\": {}", path, err))?;
+        if header_name.is_some() {
+            eprintln!("Saved \"{}\" (named by Content-Disposition)", path);
+        }
+        // A response reused under --dedup arrives whole, so it is echoed whole
+        if tee && !streamed {
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(&response.body);
+            let _ = stdout.flush();
+        }
+        if matches.get_flag("compressed-output") {
+            match response.headers.get(CONTENT_ENCODING) {
+                Some(encoding) => eprintln!(
+                    "Saved \"{}\" with Content-Encoding: {}",
+                    path,
+                    String::from_utf8_lossy(encoding.as_bytes())
+                ),
+                None => eprintln!("Saved \"{}\" (response was not encoded)", path),
+            }
+        }
+        if json {
+            println!("{}", report::json(&response, started.elapsed(), false));
+        }
+    } else if json {
+        let include_body = !matches.get_flag("no-body");
+        println!("{}", report::json(&response, started.elapsed(), include_body));
+    } else if raw_output {
+        // A streamed body is already out
+        if !streamed {
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(&response.body);
+            let _ = stdout.flush();
+        }
+    } else if streamed {
+        // Already written out chunk by chunk
+        println!();
+    } else if let Some(mut stream) = pretty_stream {
+        // Already formatted chunk by chunk; a body that turned out not to be
+        // JSON was passed through from where it went wrong
+        match stream.finish() {
+            Some(rest) => println!("{}", rest),
+            None => println!(),
+        }
+    } else {
+        let content_type = response.headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok());
+        match pretty.then(|| pretty::format(content_type, &response.body, color::enabled(matches))).flatten() {
+            Some(formatted) => println!("Response:\n{}", formatted),
+            None => println!("Response: {}", String::from_utf8_lossy(&response.body)),
+        }
+    }
+
+    checked.map_err(failed)?;
+    if let Some(path) = matches.get_one::<String>("diff-against") {
+        let normalize = matches.get_flag("diff-normalize") || matches.get_flag("json-normalize");
+        let content_type = response.headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok());
+        diff::check(path, content_type, &response.body, normalize).map_err(failed)?;
+    }
+    Ok(response.status)
+}
+
+// Percent-encodes what --url-encode covers in the path and query of a URL as
+// typed: anything outside printable ASCII, characters that are never valid
+// there (space, quotes, <>, \, ^, `, {|}), and a "%" that doesn't start an
+// escape. Existing escapes and reserved characters are left alone, so
+// encoding an already encoded URL changes nothing.
+fn percent_encode_url(url: &str) -> String {
+    // The scheme and authority end at the first "/", "?" or "#" after "//"
+    let start = url.find("://").map_or(0, |i| i + 3);
+    let start = url[start..].find(['/', '?', '#']).map_or(url.len(), |i| start + i);
+    let end = url[start..].find('#').map_or(url.len(), |i| start + i);
+
+    let (head, rest) = url.split_at(start);
+    let (middle, fragment) = rest.split_at(end - start);
+    let mut encoded = head.to_string();
+    let bytes = middle.as_bytes();
+    for (i, &byte) in bytes.iter().enumerate() {
+        let escape = byte == b'%'
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_hexdigit)
+            && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit);
+        let unsafe_byte = !byte.is_ascii_graphic() || b"\"<>\\^`{|}".contains(&byte);
+        if unsafe_byte || (byte == b'%' && !escape) {
+            encoded += &format!("%{:02X}", byte);
+        } else {
+            encoded.push(byte as char);
+        }
+    }
+    encoded + fragment
+}
+
+// Asks before the -o/-O files that already exist are overwritten, each file
+// once however many requests write to it. Without a terminal to ask on
+// (stdin and stderr), an existing file is an error. --force, --append and -C
+// skip the question: they overwrite, or add to, the file on purpose.
+fn confirm_overwrites(urls: &[Target], matches: &ArgMatches) -> Result<(), String> {
+    if matches.get_flag("force") || matches.get_flag("append") || matches.contains_id("continue-at") {
+        return Ok(());
+    }
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    let mut asked: Vec<&str> = Vec::new();
+    for path in urls.iter().filter_map(|target| target.output.as_deref()) {
+        if asked.contains(&path) || !Path::new(path).exists() {
+            continue;
+        }
+        asked.push(path);
+        if !interactive {
+            return Err(format!("Refusing to overwrite \"{}\", pass --force to replace it", path));
+        }
+        eprint!("Overwrite \"{}\"? [y/N] ", path);
+        let mut answer = String::new();
+        std::io::stdin()
+            .read_line(&mut answer)
+            .map_err(|err| format!("Unable to read the answer: {}", err))?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            return Err(format!("Not overwriting \"{}\"", path));
+        }
+    }
+    Ok(())
+}
+
+async fn append_file(path: &str, bytes: &[u8]) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+    let mut file = tokio::fs::OpenOptions::new().append(true).create(true).open(path).await?;
+    file.write_all(bytes).await
+}
+
+fn is_error(status: StatusCode) -> bool {
+    status.is_client_error() || status.is_server_error()
+}
+
+// What ALPN settled on for a response. reqwest offers "h2" and "http/1.1" over
+// TLS, so an HTTP/2 response means h2 was chosen; an HTTP/1.1 one means the
+// server picked http/1.1 or ignored ALPN. The raw connection offers none.
+fn alpn(response: &Exchange, tls: bool, raw: bool) -> &'static str {
+    match (tls, raw, response.version) {
+        (false, _, _) => "none, cleartext",
+        (true, true, _) => "not offered",
+        (true, false, Version::HTTP_2) => "h2",
+        (true, false, Version::HTTP_3) => "h3",
+        (true, false, _) => "http/1.1",
+    }
+}
+
+// Reports a Ctrl-C and exits with the conventional 128 + SIGINT status. An
+// output file that was being written is removed rather than left truncated.
+fn interrupted(target: &Target, transport: &Transport, writing_output: bool) -> ! {
+    if transport.progress == ProgressMode::Bar {
+        eprintln!();
+    }
+    eprintln!("Interrupted");
+    if let Some(path) = target.output.as_ref().filter(|_| writing_output) {
+        if let Err(err) = fs::remove_file(path) {
+            eprintln!("Unable to remove partial output file \"{}\": {}", path, err);
+        }
+    }
+    std::process::exit(130);
+}
+
+// Redirects are followed as reqwest does by default, up to 10, but each one
+// is reported in verbose mode. With --location-trusted the transport follows
+// them itself, so reqwest mustn't.
+fn redirect_policy(matches: &ArgMatches) -> Option<redirect::Policy> {
+    if matches.get_flag("location-trusted") {
+        return Some(redirect::Policy::none());
+    }
+    matches.get_flag("verbose").then(|| {
+        redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("too many redirects");
+            }
+            let from = attempt.previous().last().map_or_else(String::new, Url::to_string);
+            trace::verbose(&format!("Redirect {} from {} to {}", attempt.status(), from, attempt.url()));
+            attempt.follow()
+        })
+    })
+}
+
+fn retry_policy(matches: &ArgMatches) -> Option<RetryPolicy> {
+    let retries = *matches.get_one::<u32>("retry").unwrap();
+    (retries > 0).then(|| RetryPolicy {
+        retries,
+        honor_retry_after: matches.get_flag("retry-after"),
+        max_wait: Duration::from_secs(*matches.get_one::<u64>("retry-max-wait").unwrap()),
+        jitter: Jitter::parse(matches.get_one::<String>("retry-jitter").unwrap()).expect("checked by clap"),
+        rng: Box::new(Rng::from_clock()),
+    })
+}
+
+// --speed-limit and --speed-time, each defaulting like curl when only the other is given
+fn speed_limit(matches: &ArgMatches) -> Option<SpeedLimit> {
+    let min_rate = matches.get_one::<u64>("speed-limit").copied();
+    let window = matches.get_one::<f64>("speed-time").map(|secs| Duration::from_secs_f64(*secs));
+    if min_rate.is_none() && window.is_none() {
+        return None;
+    }
+    Some(SpeedLimit {
+        min_rate: min_rate.unwrap_or(1),
+        window: window.unwrap_or(Duration::from_secs(30)),
+    })
+}
+
+fn gzip(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+// Prints a URL/status/time table for a multi-URL run
+fn print_summary(outcomes: &[Outcome]) {
+    let width = outcomes.iter().map(|outcome| outcome.url.len()).max().unwrap_or(0).max(3);
+    eprintln!();
+    eprintln!("{:<width$}  {:<6}  {:>8}", "URL", "STATUS", "TIME", width = width);
+    for outcome in outcomes {
+        let status = match outcome.status {
+            Some(status) => status.as_u16().to_string(),
+            None => "error".to_string(),
+        };
+        eprintln!(
+            "{:<width$}  {:<6}  {:>6}ms",
+            outcome.url,
+            status,
+            outcome.elapsed.as_millis(),
+            width = width
+        );
+    }
+    let failed = outcomes.iter().filter(|outcome| outcome.failed).count();
+    eprintln!("{} of {} requests failed", failed, outcomes.len());
+}
+
+// Prints the method, URL, headers, and body of a request without sending it
+fn print_request(request: &Request, url: &Url, redactor: &Redactor) {
+    println!("{} {} {}", request.method(), url, trace::version_str(request.version()));
+    for (name, value) in request.headers() {
+        println!("{}: {}", name, redactor.value(name, value));
+    }
+    if let Some(body) = request.body() {
+        println!();
+        match body.as_bytes() {
+            Some(bytes) => println!("{}", String::from_utf8_lossy(bytes)),
+            None => println!("<streamed body>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Url;
+
+    fn exchange(version: Version) -> Exchange {
+        Exchange {
+            url: Url::parse("https://host/").unwrap(),
+            version,
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn alpn_follows_the_version_over_tls() {
+        assert_eq!(alpn(&exchange(Version::HTTP_2), true, false), "h2");
+        assert_eq!(alpn(&exchange(Version::HTTP_3), true, false), "h3");
+        assert_eq!(alpn(&exchange(Version::HTTP_11), true, false), "http/1.1");
+        assert_eq!(alpn(&exchange(Version::HTTP_11), true, true), "not offered");
+        assert_eq!(alpn(&exchange(Version::HTTP_2), false, false), "none, cleartext");
+    }
+
+    #[test]
+    fn url_encode_spaces_and_unsafe_characters() {
+        assert_eq!(
+            percent_encode_url("http://host/a b/c?q=x y&r=\"<>\""),
+            "http://host/a%20b/c?q=x%20y&r=%22%3C%3E%22"
+        );
+        // A "%" that isn't an escape is encoded itself
+        assert_eq!(percent_encode_url("http://host/100%?p=5%zz"), "http://host/100%25?p=5%25zz");
+    }
+
+    #[test]
+    fn url_encode_unicode_path() {
+        assert_eq!(percent_encode_url("https://host/café/日本"), "https://host/caf%C3%A9/%E6%97%A5%E6%9C%AC");
+    }
+
+    // Escapes, reserved characters, the authority and the fragment are left alone
+    #[test]
+    fn url_encode_leaves_escapes_alone() {
+        let encoded = "http://user:pw@host:8080/a%20b/c;d?x=%2F&y=1+2#frag ment";
+        assert_eq!(percent_encode_url(encoded), encoded);
+        assert_eq!(percent_encode_url(&percent_encode_url("http://host/a b")), "http://host/a%20b");
+        assert_eq!(percent_encode_url("http://host"), "http://host");
+    }
+}
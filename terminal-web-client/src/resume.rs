@@ -0,0 +1,26 @@
+use reqwest::header::{HeaderMap, CONTENT_RANGE};
+
+// Where -C/--continue-at picks a download up: a byte offset, or "-" for the
+// size of the -o file already on disk
+#[derive(Clone, Copy)]
+pub enum ContinueAt {
+    Offset(u64),
+    OutputSize,
+}
+
+pub fn parse(value: &str) -> Result<ContinueAt, String> {
+    if value == "-" {
+        return Ok(ContinueAt::OutputSize);
+    }
+    value
+        .parse()
+        .map(ContinueAt::Offset)
+        .map_err(|_| format!("expected a byte offset or \"-\", found \"{}\"", value))
+}
+
+// The first byte of a 206 response's "Content-Range: bytes first-last/length"
+pub fn range_start(headers: &HeaderMap) -> Option<u64> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.trim().strip_prefix("bytes ")?;
+    range.split_once('-')?.0.trim().parse().ok()
+}
@@ -0,0 +1,254 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// The wait before the first retry; it doubles for each one after that
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+
+// When and how long to wait before resending a request the server turned away
+// with 429 Too Many Requests or 503 Service Unavailable (--retry)
+pub struct RetryPolicy {
+    pub retries: u32,
+    // Wait as long as the response's Retry-After says instead of backing off (--retry-after)
+    pub honor_retry_after: bool,
+    // Neither the backoff nor Retry-After waits longer than this (--retry-max-wait)
+    pub max_wait: Duration,
+    // How much of the backoff is randomized (--retry-jitter)
+    pub jitter: Jitter,
+    // Where the jitter comes from, Rng::from_clock outside of tests
+    pub rng: Box<dyn Random + Send + Sync>,
+}
+
+// Randomizes the backoff so many clients turned away at the same moment don't
+// all come back at the same moment too
+#[derive(Clone, Copy)]
+pub enum Jitter {
+    // Exactly the backoff
+    None,
+    // Anywhere from nothing to the backoff
+    Full,
+    // At least half the backoff, the other half random
+    Equal,
+}
+
+impl Jitter {
+    pub fn parse(value: &str) -> Option<Jitter> {
+        match value {
+            "none" => Some(Jitter::None),
+            "full" => Some(Jitter::Full),
+            "equal" => Some(Jitter::Equal),
+            _ => None,
+        }
+    }
+
+    fn apply(self, backoff: Duration, rng: &dyn Random) -> Duration {
+        match self {
+            Jitter::None => backoff,
+            Jitter::Full => backoff.mul_f64(rng.next_f64()),
+            Jitter::Equal => backoff / 2 + (backoff / 2).mul_f64(rng.next_f64()),
+        }
+    }
+}
+
+// A source of random numbers for the jitter
+pub trait Random {
+    // Uniform in [0, 1]
+    fn next_f64(&self) -> f64;
+}
+
+// A small xorshift generator; retry timing doesn't need anything stronger
+pub struct Rng(AtomicU64);
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Rng {
+        // Zero is the one state xorshift never leaves
+        Rng(AtomicU64::new(seed.max(1)))
+    }
+
+    // Seeded from the clock and process ID, so concurrent clients differ
+    pub fn from_clock() -> Rng {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        Rng::seeded(nanos as u64 ^ ((std::process::id() as u64) << 32))
+    }
+}
+
+impl Random for Rng {
+    fn next_f64(&self) -> f64 {
+        let mut x = self.0.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0.store(x, Ordering::Relaxed);
+        (x >> 11) as f64 / (u64::MAX >> 11) as f64
+    }
+}
+
+impl RetryPolicy {
+    pub fn should_retry(&self, status: StatusCode) -> bool {
+        matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    // How long to wait before retry number `retry` (counting from 0) of a
+    // request whose response had `headers`
+    pub fn delay(&self, retry: u32, headers: &HeaderMap) -> Duration {
+        let retry_after = headers
+            .get(RETRY_AFTER)
+            .filter(|_| self.honor_retry_after)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, SystemTime::now()));
+        // Retry-After is the server's own schedule, so only the backoff is randomized
+        match retry_after {
+            Some(wait) => wait.min(self.max_wait),
+            None => {
+                let backoff = FIRST_BACKOFF.saturating_mul(2u32.saturating_pow(retry));
+                self.jitter.apply(backoff.min(self.max_wait), &*self.rng)
+            }
+        }
+    }
+}
+
+// A Retry-After value, either delay-seconds ("120") or an HTTP-date
+// ("Wed, 21 Oct 2015 07:28:00 GMT"), as the time left from `now`. A date in
+// the past means no wait at all.
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = UNIX_EPOCH + Duration::from_secs(parse_http_date(value)?);
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+// Seconds since the epoch for an IMF-fixdate, the only HTTP-date form
+// servers are allowed to send: "Sun, 06 Nov 1994 08:49:37 GMT"
+fn parse_http_date(value: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    let (_weekday, rest) = value.split_once(", ")?;
+    let [day, month, year, time, "GMT"] = rest.split(' ').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let day: u64 = day.parse().ok().filter(|day| (1..=31).contains(day))?;
+    let month = MONTHS.iter().position(|name| *name == month)? as u64 + 1;
+    let year: u64 = year.parse().ok().filter(|year| *year >= 1970)?;
+    let [hours, minutes, seconds] = time.split(':').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let hours: u64 = hours.parse().ok().filter(|hours| *hours < 24)?;
+    let minutes: u64 = minutes.parse().ok().filter(|minutes| *minutes < 60)?;
+    let seconds: u64 = seconds.parse().ok().filter(|seconds| *seconds < 61)?;
+
+    // A civil date to days since 1970-01-01 (Howard Hinnant's algorithm)
+    let (year, month) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Some(days * 86_400 + hours * 3_600 + minutes * 60 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    // Always draws the same number
+    struct Fixed(f64);
+
+    impl Random for Fixed {
+        fn next_f64(&self) -> f64 {
+            self.0
+        }
+    }
+
+    fn policy(jitter: Jitter, max_wait: Duration, rng: impl Random + Send + Sync + 'static) -> RetryPolicy {
+        RetryPolicy {
+            retries: 10,
+            honor_retry_after: true,
+            max_wait,
+            jitter,
+            rng: Box::new(rng),
+        }
+    }
+
+    // The range jitter may pick from for a retry: the backoff (capped at
+    // max_wait) or some share of it
+    fn bounds(jitter: Jitter, retry: u32, max_wait: Duration) -> (Duration, Duration) {
+        let cap = FIRST_BACKOFF.saturating_mul(2u32.saturating_pow(retry)).min(max_wait);
+        match jitter {
+            Jitter::None => (cap, cap),
+            Jitter::Full => (Duration::ZERO, cap),
+            Jitter::Equal => (cap / 2, cap),
+        }
+    }
+
+    #[test]
+    fn seeded_delays_stay_in_bounds() {
+        let max_wait = Duration::from_secs(30);
+        for jitter in [Jitter::None, Jitter::Full, Jitter::Equal] {
+            let policy = policy(jitter, max_wait, Rng::seeded(0x5eed));
+            for _ in 0..100 {
+                for retry in 0..40 {
+                    let (low, high) = bounds(jitter, retry, max_wait);
+                    let delay = policy.delay(retry, &HeaderMap::new());
+                    assert!(low <= delay && delay <= high, "retry {}: {:?} outside {:?}..={:?}", retry, delay, low, high);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn jitter_reaches_its_bounds() {
+        let max_wait = Duration::from_secs(30);
+        for jitter in [Jitter::None, Jitter::Full, Jitter::Equal] {
+            for retry in [0, 3, 10] {
+                let (low, high) = bounds(jitter, retry, max_wait);
+                assert_eq!(policy(jitter, max_wait, Fixed(0_f64)).delay(retry, &HeaderMap::new()), low);
+                assert_eq!(policy(jitter, max_wait, Fixed(1_f64)).delay(retry, &HeaderMap::new()), high);
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_rngs_repeat() {
+        let (a, b) = (Rng::seeded(7), Rng::seeded(7));
+        for _ in 0..1_000 {
+            let draw = a.next_f64();
+            assert!((0_f64..=1_f64).contains(&draw));
+            assert_eq!(draw, b.next_f64());
+        }
+        // A zero seed would leave xorshift stuck at zero forever
+        let zero = Rng::seeded(0);
+        assert!((0..10).any(|_| zero.next_f64() > 0_f64));
+    }
+
+    // Retry-After is neither jittered nor allowed past max_wait
+    #[test]
+    fn retry_after_is_capped_not_jittered() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("5"));
+        let policy = policy(Jitter::Full, Duration::from_secs(30), Fixed(0_f64));
+        assert_eq!(policy.delay(0, &headers), Duration::from_secs(5));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(policy.delay(0, &headers), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        let now = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(parse_retry_after(" 120 ", now), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:47 GMT", now),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(parse_retry_after("Sun, 06 Nov 1994 08:49:27 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("Sunday, 06-Nov-94 08:49:37 GMT", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
@@ -0,0 +1,82 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+use std::sync::Mutex;
+
+// reqwest doesn't say whether a request went over a pooled connection, but
+// with connection_verbose it logs every read and write at TRACE level,
+// prefixed with a random id for the connection. Watching those ids tells a
+// connection seen on an earlier request (reused) from a new one (--verbose).
+const TARGET: &str = "reqwest::connect::verbose";
+
+struct Connections {
+    seen: BTreeSet<u32>,
+    // The connection the request in flight went out on, once known
+    current: Option<u32>,
+}
+
+static CONNECTIONS: Mutex<Connections> = Mutex::new(Connections {
+    seen: BTreeSet::new(),
+    current: None,
+});
+
+struct Watcher;
+
+static WATCHER: Watcher = Watcher;
+
+impl Log for Watcher {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() == Level::Trace && metadata.target() == TARGET
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // Only the leading hex id is read; the rest is the escaped bytes
+        // read or written, which are never formatted
+        let mut id = Prefix(String::new());
+        let _ = write!(id, "{}", record.args());
+        let Ok(id) = u32::from_str_radix(&id.0, 16) else {
+            return;
+        };
+        let mut connections = CONNECTIONS.lock().unwrap();
+        connections.current.get_or_insert(id);
+    }
+
+    fn flush(&self) {}
+}
+
+// Collects the first 8 characters written, then stops the formatting
+struct Prefix(String);
+
+impl Write for Prefix {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.0.extend(text.chars().take(8 - self.0.len()));
+        match self.0.len() {
+            8 => Err(fmt::Error),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Starts watching; the client must be built with connection_verbose(true)
+pub fn install() {
+    if log::set_logger(&WATCHER).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+}
+
+// Forgets the previous request's connection before the next one is sent
+pub fn begin() {
+    CONNECTIONS.lock().unwrap().current = None;
+}
+
+// Whether the request since begin() reused a connection an earlier one
+// opened, with that connection's id; None if nothing was seen (the raw
+// path and QUIC aren't logged)
+pub fn finish() -> Option<(bool, u32)> {
+    let mut connections = CONNECTIONS.lock().unwrap();
+    let id = connections.current.take()?;
+    Some((!connections.seen.insert(id), id))
+}
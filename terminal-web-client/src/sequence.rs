@@ -0,0 +1,386 @@
+use clap::ArgMatches;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, Method, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+// One step of a --from-file sequence. The file has one JSON object per line:
+//   {"method":"POST","url":"https://host/login","headers":{"Content-Type":"application/json"},"body":"...",
+//    "capture":{"token":"$.data.token"},"expect_status":[200,201]}
+// `method` defaults to GET, the other fields are optional. Blank lines and
+// lines starting with # are skipped. A step fails when its status isn't one of
+// `expect_status`, or isn't 2xx when that is left out.
+//
+// `capture` names values to take from the response: "$.a.b[0]" selects from a
+// JSON body, "header:Name" a response header and "status" the status code.
+// Later steps use them as ${name} in their URL, header values and body.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Step {
+    #[serde(default = "default_method")]
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    body: Option<String>,
+    #[serde(default)]
+    capture: BTreeMap<String, String>,
+    #[serde(default)]
+    expect_status: Vec<u16>,
+}
+
+impl Step {
+    fn check_status(&self, status: StatusCode) -> Result<(), String> {
+        if (self.expect_status.is_empty() && status.is_success()) || self.expect_status.contains(&status.as_u16()) {
+            return Ok(());
+        }
+        let expected = match &self.expect_status[..] {
+            [] => "2xx".to_string(),
+            codes => codes.iter().map(u16::to_string).collect::<Vec<_>>().join(" or "),
+        };
+        Err(format!("expected status {}, got {}", expected, status))
+    }
+}
+
+// A response as far as captures are concerned
+struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: String,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+// The `run` subcommand: sends the steps of a sequence file in order over one
+// client, so cookies set by one response (a login, say) go out with the next
+// requests. A step fails on an error, an unexpected status or a failed
+// capture. Every step is run regardless, so one run shows every failure,
+// unless --fail-early stops at the first, for flows where later steps depend
+// on earlier ones. Either way the failed steps are listed at the end with the
+// reason.
+pub async fn run(matches: &ArgMatches) {
+    let path = matches.get_one::<String>("from-file").unwrap();
+    let steps = match load(path) {
+        Ok(steps) => steps,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let client = match Client::builder().cookie_store(true).build() {
+        Ok(client) => client,
+        Err(err) => {
+            eprintln!("Failed to build the HTTP client: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let outcome = run_steps(&client, path, &steps, matches.get_flag("fail-early")).await;
+    if outcome.failures.is_empty() {
+        return;
+    }
+    eprintln!();
+    for (label, reason) in &outcome.failures {
+        eprintln!("Failed {}: {}", label, reason);
+    }
+    if outcome.ran < steps.len() {
+        eprintln!("Stopped early, {} of {} steps were not run", steps.len() - outcome.ran, steps.len());
+    } else {
+        eprintln!("{} of {} steps failed", outcome.failures.len(), steps.len());
+    }
+    std::process::exit(1);
+}
+
+// What a run came to: each failed step's label and reason, and how many steps
+// were sent
+struct Outcome {
+    failures: Vec<(String, String)>,
+    ran: usize,
+}
+
+async fn run_steps(client: &Client, path: &str, steps: &[(usize, Step)], fail_early: bool) -> Outcome {
+    let mut variables = HashMap::new();
+    let mut outcome = Outcome {
+        failures: Vec::new(),
+        ran: 0,
+    };
+    for (number, (line, step)) in steps.iter().enumerate() {
+        let label = format!("step {} ({}:{} {} {})", number + 1, path, line, step.method, step.url);
+        outcome.ran += 1;
+        let result = match send(client, step, &variables).await {
+            Ok(response) => {
+                eprintln!("{} -> {}", label, response.status.as_u16());
+                println!("Response: {}", response.body);
+                step.check_status(response.status)
+                    .and_then(|()| capture(step, &response, &mut variables))
+            }
+            Err(err) => Err(err),
+        };
+        let Err(reason) = result else {
+            continue;
+        };
+        eprintln!("{} -> {}", label, reason);
+        outcome.failures.push((label, reason));
+        if fail_early {
+            break;
+        }
+    }
+    outcome
+}
+
+// Reads and checks every step up front, so a typo on a late line doesn't
+// surface after the earlier requests have already been sent
+fn load(path: &str) -> Result<Vec<(usize, Step)>, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Unable to read \"{}\": {}", path, err))?;
+    let mut steps = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let step: Step =
+            serde_json::from_str(line).map_err(|err| format!("{}:{}: invalid step: {}", path, index + 1, err))?;
+        Method::from_bytes(step.method.to_uppercase().as_bytes())
+            .map_err(|_| format!("{}:{}: invalid method \"{}\"", path, index + 1, step.method))?;
+        // URLs with ${variables} can only be checked once they are filled in
+        if !step.url.contains("${") {
+            reqwest::Url::parse(&step.url)
+                .map_err(|err| format!("{}:{}: invalid URL \"{}\": {}", path, index + 1, step.url, err))?;
+        }
+        steps.push((index + 1, step));
+    }
+    Ok(steps)
+}
+
+async fn send(client: &Client, step: &Step, variables: &HashMap<String, String>) -> Result<Response, String> {
+    let method = Method::from_bytes(step.method.to_uppercase().as_bytes()).expect("checked on load");
+    let url = substitute(&step.url, variables)?;
+    let mut request = client.request(method, &url);
+    for (name, value) in &step.headers {
+        request = request.header(name, substitute(value, variables)?);
+    }
+    if let Some(body) = &step.body {
+        request = request.body(substitute(body, variables)?);
+    }
+
+    let response = request.send().await.map_err(|err| format!("Request failed: {}", err))?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response
+        .text()
+        .await
+        .map_err(|err| format!("Unable to read response body: {}", err))?;
+    Ok(Response { status, headers, body })
+}
+
+// Replaces each ${name} with a captured value
+fn substitute(text: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unterminated \"${{\" in \"{}\"", text))?;
+        let name = &rest[start + 2..start + end];
+        let value = variables
+            .get(name)
+            .ok_or_else(|| format!("Variable \"{}\" has not been captured", name))?;
+        out.push_str(&rest[..start]);
+        out.push_str(value);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+// Stores the values a step captures from its response
+fn capture(step: &Step, response: &Response, variables: &mut HashMap<String, String>) -> Result<(), String> {
+    let mut json = None;
+    for (name, selector) in &step.capture {
+        let value = if selector == "status" {
+            Some(response.status.as_u16().to_string())
+        } else if let Some(header) = selector.strip_prefix("header:") {
+            response
+                .headers
+                .get(header.trim())
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+        } else {
+            // Parsed on first use, so steps that only capture headers accept any body
+            if json.is_none() {
+                let parsed: Value = serde_json::from_str(&response.body)
+                    .map_err(|err| format!("Can't capture \"{}\": response is not JSON ({})", name, err))?;
+                json = Some(parsed);
+            }
+            select(json.as_ref().unwrap(), selector)?.map(|value| match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+        };
+        let value = value.ok_or_else(|| format!("Can't capture \"{}\": \"{}\" matched nothing", name, selector))?;
+        variables.insert(name.clone(), value);
+    }
+    Ok(())
+}
+
+// Follows a "$.field.list[2].field" path into a JSON value
+fn select<'a>(value: &'a Value, selector: &str) -> Result<Option<&'a Value>, String> {
+    let path = selector
+        .strip_prefix('$')
+        .ok_or_else(|| format!("Unknown capture selector \"{}\" (use $.path, header:Name or status)", selector))?;
+
+    let mut current = value;
+    let mut rest = path;
+    while !rest.is_empty() {
+        let next = if let Some(field) = rest.strip_prefix('.') {
+            let end = field.find(['.', '[']).unwrap_or(field.len());
+            rest = &field[end..];
+            current.get(&field[..end])
+        } else if let Some(index) = rest.strip_prefix('[') {
+            let end = index
+                .find(']')
+                .ok_or_else(|| format!("Unterminated \"[\" in selector \"{}\"", selector))?;
+            let position = index[..end]
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid index in selector \"{}\"", selector))?;
+            rest = &index[end + 1..];
+            current.get(position)
+        } else {
+            return Err(format!("Invalid selector \"{}\"", selector));
+        };
+        match next {
+            Some(value) => current = value,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, Part};
+
+    fn client() -> Client {
+        Client::builder().no_proxy().build().unwrap()
+    }
+
+    fn step(json: &str) -> Step {
+        serde_json::from_str(json).unwrap()
+    }
+
+    // A server that answers /two with 500 and anything else with 200
+    async fn flaky_server() -> mock::MockServer {
+        mock::serve(|request| {
+            let two = request.head.starts_with("GET /two ");
+            let status = if two { "500 Internal Server Error" } else { "200 OK" };
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 2\r\n\r\nok", status);
+            vec![Part::Send(response.into_bytes())]
+        })
+        .await
+    }
+
+    // The paths the server was sent, in order
+    fn paths(server: &mock::MockServer) -> Vec<String> {
+        let received = server.received();
+        received.iter().map(|request| request.head.split(' ').nth(1).unwrap().to_string()).collect()
+    }
+
+    fn three_steps(server: &mock::MockServer) -> Vec<(usize, Step)> {
+        ["one", "two", "three"]
+            .iter()
+            .enumerate()
+            .map(|(index, path)| (index + 1, step(&format!(r#"{{"url":"{}"}}"#, server.at(path)))))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn runs_every_step_by_default() {
+        let server = flaky_server().await;
+        let outcome = run_steps(&client(), "flow", &three_steps(&server), false).await;
+        assert_eq!(outcome.ran, 3);
+        assert_eq!(paths(&server), ["/one", "/two", "/three"]);
+        let [(label, reason)] = &outcome.failures[..] else {
+            panic!("expected one failure, got {:?}", outcome.failures);
+        };
+        assert!(label.starts_with("step 2 (flow:2 GET "), "{}", label);
+        assert_eq!(reason, "expected status 2xx, got 500 Internal Server Error");
+    }
+
+    #[tokio::test]
+    async fn fail_early_stops_at_the_failed_step() {
+        let server = flaky_server().await;
+        let outcome = run_steps(&client(), "flow", &three_steps(&server), true).await;
+        assert_eq!(outcome.ran, 2);
+        assert_eq!(outcome.failures.len(), 1);
+        assert_eq!(paths(&server), ["/one", "/two"]);
+    }
+
+    // A step that expects the status passes
+    #[tokio::test]
+    async fn expected_status_passes() {
+        let server = flaky_server().await;
+        let steps = [(1, step(&format!(r#"{{"url":"{}","expect_status":[500]}}"#, server.at("two"))))];
+        let outcome = run_steps(&client(), "flow", &steps, true).await;
+        assert!(outcome.failures.is_empty());
+    }
+
+    // Step 1 logs in and captures the token from its JSON; step 2 sends it
+    #[tokio::test]
+    async fn login_flow_passes_a_captured_token() {
+        let server = mock::serve(|request| {
+            let (status, body) = match request.head.lines().next().unwrap_or_default() {
+                line if line.starts_with("POST /login ") => ("200 OK", r#"{"data":{"token":"t0k3n","roles":["a","b"]}}"#),
+                line if line.starts_with("GET /me/b ") && request.header("authorization") == Some("Bearer t0k3n") => {
+                    ("200 OK", "{}")
+                }
+                _ => ("401 Unauthorized", "{}"),
+            };
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n{}", status, body.len(), body);
+            vec![Part::Send(response.into_bytes())]
+        })
+        .await;
+        let steps = [
+            step(&format!(
+                r#"{{"method":"POST","url":"{}","body":"{{}}","capture":{{"token":"$.data.token","role":"$.data.roles[1]"}}}}"#,
+                server.at("login")
+            )),
+            step(&format!(
+                r#"{{"url":"{}me/${{role}}","headers":{{"Authorization":"Bearer ${{token}}"}}}}"#,
+                server.url
+            )),
+        ];
+        let steps: Vec<_> = steps.into_iter().enumerate().map(|(index, step)| (index + 1, step)).collect();
+        let outcome = run_steps(&client(), "flow", &steps, true).await;
+        assert!(outcome.failures.is_empty(), "{:?}", outcome.failures);
+        assert_eq!(outcome.ran, 2);
+    }
+
+    #[test]
+    fn selects_json_paths() {
+        let value: Value = serde_json::from_str(r#"{"a":{"b":[10,{"c":"x"}]}}"#).unwrap();
+        assert_eq!(select(&value, "$.a.b[1].c").unwrap(), Some(&Value::from("x")));
+        assert_eq!(select(&value, "$.a.b[0]").unwrap(), Some(&Value::from(10)));
+        assert_eq!(select(&value, "$").unwrap(), Some(&value));
+        assert_eq!(select(&value, "$.a.missing").unwrap(), None);
+        assert_eq!(select(&value, "$.a.b[5]").unwrap(), None);
+        for selector in ["a.b", "$.a.b[x]", "$.a.b[1", "$a"] {
+            assert!(select(&value, selector).is_err(), "{}", selector);
+        }
+    }
+
+    #[test]
+    fn substitutes_captured_variables() {
+        let variables = HashMap::from([("id".to_string(), "7".to_string())]);
+        assert_eq!(substitute("/users/${id}/${id}", &variables).unwrap(), "/users/7/7");
+        assert_eq!(
+            substitute("/${other}", &variables).unwrap_err(),
+            "Variable \"other\" has not been captured"
+        );
+        assert!(substitute("/${id", &variables).is_err());
+    }
+}
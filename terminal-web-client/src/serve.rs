@@ -0,0 +1,71 @@
+use crate::json;
+use clap::ArgMatches;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+// The `serve` subcommand: a local HTTP server that answers every request with
+// a JSON echo of it, for trying the client out without a real API:
+//   {"method":"POST","path":"/a","query":"b=1"|null,"version":"HTTP/1.1",
+//    "headers":{"name":"value",...},"body":"..."}
+// Repeated headers are joined with ", " and the body is decoded lossily as UTF-8.
+pub async fn run(matches: &ArgMatches) {
+    let port = *matches.get_one::<u16>("port").unwrap();
+    let status = StatusCode::from_u16(*matches.get_one::<u16>("status").unwrap()).expect("range checked by clap");
+    let bind = *matches.get_one::<IpAddr>("bind").unwrap();
+
+    let make_service = make_service_fn(move |_| async move {
+        Ok::<_, Infallible>(service_fn(move |request| echo(request, status)))
+    });
+    let server = match Server::try_bind(&SocketAddr::new(bind, port)) {
+        Ok(builder) => builder.serve(make_service),
+        Err(err) => {
+            eprintln!("Failed to listen on {}:{}: {}", bind, port, err);
+            return;
+        }
+    };
+    eprintln!("Echoing requests on http://{}", server.local_addr());
+
+    let shutdown = server.with_graceful_shutdown(async {
+        let _ = tokio::signal::ctrl_c().await;
+    });
+    if let Err(err) = shutdown.await {
+        eprintln!("Server error: {}", err);
+    }
+}
+
+async fn echo(request: Request<Body>, status: StatusCode) -> Result<Response<Body>, Infallible> {
+    let (parts, body) = request.into_parts();
+    let body = hyper::body::to_bytes(body).await.unwrap_or_default();
+    eprintln!("{} {}", parts.method, parts.uri);
+
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for (name, value) in &parts.headers {
+        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+        match headers.iter_mut().find(|(existing, _)| existing == name.as_str()) {
+            Some((_, joined)) => {
+                joined.push_str(", ");
+                joined.push_str(&value);
+            }
+            None => headers.push((name.to_string(), value)),
+        }
+    }
+    let headers = json::object(headers.iter().map(|(name, value)| (name.as_str(), json::string(value))));
+
+    let echo = json::object([
+        ("method", json::string(parts.method.as_str())),
+        ("path", json::string(parts.uri.path())),
+        ("query", parts.uri.query().map_or("null".to_string(), json::string)),
+        ("version", json::string(&format!("{:?}", parts.version))),
+        ("headers", headers),
+        ("body", json::string(&String::from_utf8_lossy(&body))),
+    ]);
+
+    let response = Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(echo + "\n"))
+        .expect("valid response");
+    Ok(response)
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+
+// clap value parser for --var, "name=value"
+pub fn parse_var(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err("expected name=value".to_string()),
+    }
+}
+
+// Reads a --body-template file and fills in each ${NAME}: from a --var of
+// that name if there is one, else from the environment. An unresolved
+// variable is an error unless `allow_missing`, in which case its placeholder
+// is sent as it is. All unresolved names are reported at once.
+pub fn render(path: &str, vars: &HashMap<String, String>, allow_missing: bool) -> Result<String, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Unable to read \"{}\": {}", path, err))?;
+
+    let mut out = String::with_capacity(text.len());
+    let mut missing: Vec<&str> = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unterminated \"${{\" in \"{}\"", path))?;
+        let name = &rest[start + 2..start + end];
+        let placeholder = &rest[start..=start + end];
+        out.push_str(&rest[..start]);
+        match vars.get(name).cloned().or_else(|| env::var(name).ok()) {
+            Some(value) => out.push_str(&value),
+            None => {
+                if !missing.contains(&name) {
+                    missing.push(name);
+                }
+                out.push_str(placeholder);
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+
+    if !missing.is_empty() && !allow_missing {
+        return Err(format!(
+            "Unresolved variable(s) in \"{}\": {} (set them with --var or the environment, or pass --allow-missing-vars)",
+            path,
+            missing.join(", ")
+        ));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Renders `text` as a template file with the given --var values
+    fn render_text(text: &str, vars: &[(&str, &str)], allow_missing: bool) -> Result<String, String> {
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "terminal-web-client-template-{}-{}.txt",
+            std::process::id(),
+            FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&path, text).unwrap();
+        let vars = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        let rendered = render(path.to_str().unwrap(), &vars, allow_missing);
+        fs::remove_file(&path).unwrap();
+        rendered
+    }
+
+    #[test]
+    fn substitutes_vars() {
+        let template = r#"{"user":"${USER_NAME}","id":${ID},"again":"${USER_NAME}"}"#;
+        let rendered = render_text(template, &[("USER_NAME", "ann"), ("ID", "7")], false);
+        assert_eq!(rendered.unwrap(), r#"{"user":"ann","id":7,"again":"ann"}"#);
+    }
+
+    #[test]
+    fn missing_vars_fail_together() {
+        let template = "${TWC_TEST_UNSET_A} ${TWC_TEST_UNSET_B} ${TWC_TEST_UNSET_A}";
+        let err = render_text(template, &[], false).unwrap_err();
+        assert!(err.contains(": TWC_TEST_UNSET_A, TWC_TEST_UNSET_B (set them"), "{}", err);
+    }
+
+    #[test]
+    fn allow_missing_leaves_the_placeholder() {
+        let rendered = render_text("a=${A} b=${TWC_TEST_UNSET_B}", &[("A", "1")], true);
+        assert_eq!(rendered.unwrap(), "a=1 b=${TWC_TEST_UNSET_B}");
+    }
+
+    #[test]
+    fn unterminated_placeholder_fails() {
+        let err = render_text("x ${A", &[("A", "1")], true).unwrap_err();
+        assert!(err.starts_with("Unterminated \"${\""), "{}", err);
+    }
+
+    #[test]
+    fn parses_vars() {
+        assert_eq!(parse_var("a=b=c").unwrap(), ("a".to_string(), "b=c".to_string()));
+        assert!(parse_var("=b").is_err() && parse_var("ab").is_err());
+    }
+}
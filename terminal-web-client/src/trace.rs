@@ -0,0 +1,129 @@
+use crate::redact::Redactor;
+use reqwest::header::HeaderMap;
+use reqwest::{Request, StatusCode, Version};
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+// How --trace-time stamps each trace and verbose line: milliseconds since the
+// run started, or the UTC time of day (curl's format, to the microsecond)
+#[derive(Clone, Copy)]
+pub enum TimeFormat {
+    Relative(Instant),
+    Wall,
+}
+
+static TIME_FORMAT: OnceLock<TimeFormat> = OnceLock::new();
+
+// Turns on --trace-time for the rest of the run; "relative" or "wall"
+pub fn set_time_format(format: &str) {
+    let format = match format {
+        "relative" => TimeFormat::Relative(Instant::now()),
+        _ => TimeFormat::Wall,
+    };
+    let _ = TIME_FORMAT.set(format);
+}
+
+// The prefix for a trace or verbose line: empty unless --trace-time is on
+pub fn stamp() -> String {
+    match TIME_FORMAT.get() {
+        None => String::new(),
+        Some(TimeFormat::Relative(start)) => format!("+{:.3}ms ", start.elapsed().as_secs_f64() * 1000.0),
+        Some(TimeFormat::Wall) => {
+            let since = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            let secs = since.as_secs() % 86400;
+            format!(
+                "{:02}:{:02}:{:02}.{:06} ",
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60,
+                since.subsec_micros()
+            )
+        }
+    }
+}
+
+// Prints a --verbose line to stderr, stamped if --trace-time is on
+pub fn verbose(line: &str) {
+    eprintln!("{}{}", stamp(), line);
+}
+
+// Writes a hex+ascii dump of the exchange, in the spirit of curl's --trace-ascii.
+// reqwest doesn't expose the raw socket bytes, so the request and response are
+// reconstructed from what we hand to reqwest and what it hands back to us.
+pub struct Trace {
+    out: Box<dyn Write>,
+    redactor: Redactor,
+}
+
+impl Trace {
+    // "-" means stderr, anything else is a file path (truncated if it exists)
+    pub fn open(path: &str, redactor: Redactor) -> io::Result<Self> {
+        let out: Box<dyn Write> = if path == "-" {
+            Box::new(io::stderr())
+        } else {
+            Box::new(File::create(path)?)
+        };
+        Ok(Trace { out, redactor })
+    }
+
+    pub fn request(&mut self, request: &Request, target: &str) -> io::Result<()> {
+        let mut head = format!("{} {} {}\r\n", request.method(), target, version_str(request.version()));
+        head.push_str(&headers_str(request.headers(), &self.redactor));
+        head.push_str("\r\n");
+        self.dump("=> Send header", head.as_bytes())?;
+
+        if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+            self.dump("=> Send data", body)?;
+        }
+        Ok(())
+    }
+
+    pub fn response(
+        &mut self,
+        version: Version,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> io::Result<()> {
+        let mut head = format!("{} {}\r\n", version_str(version), status);
+        head.push_str(&headers_str(headers, &self.redactor));
+        head.push_str("\r\n");
+        self.dump("<= Recv header", head.as_bytes())?;
+        self.dump("<= Recv data", body)?;
+        self.out.flush()
+    }
+
+    fn dump(&mut self, marker: &str, data: &[u8]) -> io::Result<()> {
+        let stamp = stamp();
+        writeln!(self.out, "{}{}, {} bytes (0x{:x})", stamp, marker, data.len(), data.len())?;
+
+        for (line, chunk) in data.chunks(16).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            writeln!(self.out, "{}{:04x}: {:<47} {}", stamp, line * 16, hex.join(" "), ascii)?;
+        }
+        Ok(())
+    }
+}
+
+fn headers_str(headers: &HeaderMap, redactor: &Redactor) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, redactor.value(name, value)))
+        .collect()
+}
+
+pub fn version_str(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_2 => "HTTP/2",
+        Version::HTTP_3 => "HTTP/3",
+        _ => "HTTP/1.1",
+    }
+}
@@ -0,0 +1,114 @@
+use crate::dns;
+use crate::json;
+use crate::trace;
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// Hard ceiling on the proxy's answer to CONNECT
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+// A --proxy-tunnel: the connection goes to the proxy, which is asked with
+// CONNECT to open a TCP tunnel to the real host. Everything after that,
+// including TLS, runs end to end through the tunnel, so the proxy never sees
+// the request itself (unlike a forwarding HTTP proxy).
+#[derive(Clone, Debug)]
+pub struct ProxyTunnel {
+    pub host: String,
+    pub port: u16,
+    // The Proxy-Authorization value sent with CONNECT (--proxy-user)
+    pub authorization: Option<String>,
+    // Print the proxy's answer to CONNECT (--verbose)
+    pub verbose: bool,
+}
+
+// clap value parser for --proxy-tunnel and --socks5(-hostname), "host:port"
+// (IPv6 hosts in brackets)
+pub fn parse_address(value: &str) -> Result<(String, u16), String> {
+    let (host, port) = value
+        .rsplit_once(':')
+        .ok_or_else(|| format!("expected host:port, found \"{}\"", value))?;
+    let host = host.trim_matches(['[', ']']);
+    if host.is_empty() {
+        return Err(format!("expected host:port, found \"{}\"", value));
+    }
+    let port = port.parse::<u16>().map_err(|_| format!("invalid port \"{}\"", port))?;
+    Ok((host.to_string(), port))
+}
+
+// clap value parser for --proxy-user, "user:password"
+pub fn parse_user(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((user, password)) if !user.is_empty() => Ok((user.to_string(), password.to_string())),
+        _ => Err("expected user:password".to_string()),
+    }
+}
+
+// The Proxy-Authorization value for a user and password
+pub fn basic(user: &str, password: &str) -> String {
+    format!("Basic {}", json::base64(format!("{}:{}", user, password).as_bytes()))
+}
+
+impl ProxyTunnel {
+    // Connects to the proxy and asks it for a tunnel to `host`:`port`. Any
+    // 2xx answer opens the tunnel; 407 means the proxy wants (other)
+    // credentials, and anything else is the proxy refusing.
+    pub async fn open(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+        let mut tcp = dns::connect(&self.host, self.port).await?;
+        let authority = match host.contains(':') {
+            true => format!("[{}]:{}", host, port),
+            false => format!("{}:{}", host, port),
+        };
+        let mut head = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+        if let Some(authorization) = &self.authorization {
+            head.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+        }
+        head.push_str("\r\n");
+        tcp.write_all(head.as_bytes()).await?;
+
+        let status_line = read_head(&mut tcp).await?;
+        if self.verbose {
+            trace::verbose(&format!(
+                "Proxy {}:{} answered CONNECT {}: {}",
+                self.host, self.port, authority, status_line
+            ));
+        }
+        let status = status_line
+            .split(' ')
+            .nth(1)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| invalid(format!("Malformed CONNECT response \"{}\"", status_line)))?;
+        match status {
+            200..=299 => Ok(tcp),
+            407 if self.authorization.is_some() => Err(invalid(format!(
+                "Proxy rejected the --proxy-user credentials: {}",
+                status_line
+            ))),
+            407 => Err(invalid(format!(
+                "Proxy requires authentication, use --proxy-user: {}",
+                status_line
+            ))),
+            _ => Err(invalid(format!("Proxy refused the tunnel: {}", status_line))),
+        }
+    }
+}
+
+// Reads the proxy's response head a byte at a time, so nothing that follows it
+// in the tunnel is consumed, and returns its status line. A CONNECT response
+// has no body worth reading: on success the tunnel starts right after the
+// head, and on failure the connection is dropped.
+async fn read_head(tcp: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") && !head.ends_with(b"\n\n") {
+        if head.len() >= MAX_HEAD_BYTES {
+            return Err(invalid("Proxy response to CONNECT is too large".to_string()));
+        }
+        head.push(tcp.read_u8().await?);
+    }
+    let head = String::from_utf8_lossy(&head);
+    Ok(head.lines().next().unwrap_or_default().trim_end().to_string())
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,529 @@
+// Runs the built client the way a user or a CI job would, and checks what it
+// prints and the status it exits with
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+// The unit tests' mock server, of which each test uses only some parts
+#[allow(dead_code)]
+#[path = "../src/mock.rs"]
+mod mock;
+use mock::Part;
+
+// Nothing listens here; tests using it fail before anything is sent
+const UNUSED_URL: &str = "http://127.0.0.1:9/";
+
+fn client(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_terminal-web-client"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// Runs the client on a blocking thread, so that a mock server on the test's
+// runtime goes on answering it
+async fn client_async(args: &[&str]) -> Output {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    tokio::task::spawn_blocking(move || client(&args.iter().map(String::as_str).collect::<Vec<_>>()))
+        .await
+        .unwrap()
+}
+
+// A server answering every request with `body` as JSON
+async fn json_server(body: &'static str) -> mock::MockServer {
+    mock::serve(move |_| {
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        );
+        vec![Part::Send(head.into_bytes()), Part::Send(body.as_bytes().to_vec())]
+    })
+    .await
+}
+
+// A server answering every request with 200 and `body` as plain text
+async fn text_server(body: &'static str) -> mock::MockServer {
+    status_server("200 OK", body).await
+}
+
+// A server answering every request with `status` and `body` as plain text
+async fn status_server(status: &'static str, body: &'static str) -> mock::MockServer {
+    mock::serve(move |_| {
+        let response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n{}", status, body.len(), body);
+        vec![Part::Send(response.into_bytes())]
+    })
+    .await
+}
+
+// A file in the temp directory, removed when dropped
+struct TempFile(std::path::PathBuf);
+
+impl TempFile {
+    fn new(name: &str, contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("terminal-web-client-cli-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        TempFile(path)
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn invalid_body_pattern_fails() {
+    let output = client(&["request", "--method", "GET", "--expect-body-matches", "(", UNUSED_URL]);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+    assert!(stderr(&output).contains("regex"), "{}", stderr(&output));
+}
+
+#[test]
+fn repeating_a_streamed_body_fails() {
+    let output = Command::new(env!("CARGO_BIN_EXE_terminal-web-client"))
+        .args(["request", "--method", "POST", "--body", "@-", "--repeat", "2", UNUSED_URL])
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+    assert!(stderr(&output).contains("streamed body"), "{}", stderr(&output));
+}
+
+#[test]
+fn malformed_header_fails() {
+    for subcommand in ["request", "bench"] {
+        let output = client(&[subcommand, "--method", "GET", "--headers", "nocolon", UNUSED_URL]);
+        assert_eq!(output.status.code(), Some(2), "{}: {}", subcommand, stderr(&output));
+        assert!(stderr(&output).contains("Invalid --headers value"), "{}", stderr(&output));
+    }
+    let output = client(&["request", "--method", "GET", "--headers", "Bad Name: x", UNUSED_URL]);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn diff_against_a_matching_golden_file_passes() {
+    let server = json_server(r#"{"a":1,"b":[1,2]}"#).await;
+    let golden = TempFile::new("golden-match.json", r#"{"a":1,"b":[1,2]}"#);
+    let url = server.at("/").to_string();
+    let output = client_async(&["request", "--method", "GET", "--diff-against", golden.path(), &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(!stderr(&output).contains("differs"), "{}", stderr(&output));
+
+    // Key order and whitespace only count without --diff-normalize
+    let reordered = TempFile::new("golden-reordered.json", "{\"b\": [1, 2], \"a\": 1}\n");
+    let args = ["request", "--method", "GET", "--diff-against", reordered.path(), "--diff-normalize", &url];
+    let output = client_async(&args).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn diff_against_a_different_golden_file_fails() {
+    let server = json_server("{\"a\":2}\n").await;
+    let golden = TempFile::new("golden-differs.json", "{\"a\":1}\n");
+    let url = server.at("/").to_string();
+    let output = client_async(&["request", "--method", "GET", "--diff-against", golden.path(), &url]).await;
+    assert_eq!(output.status.code(), Some(1), "{}", stdout(&output));
+    let stderr = stderr(&output);
+    assert!(stderr.contains("@@ -1 +1 @@\n-{\"a\":1}\n+{\"a\":2}"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn host_header_overrides_the_url_host() {
+    let server = text_server("ok").await;
+    let url = server.at("/").to_string();
+    let output = client_async(&["request", "--method", "GET", "--host-header", "vhost.test", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    // It also wins over a Host given with --headers
+    let output = client_async(&[
+        "request", "--method", "GET", "--headers", "Host: other.test", "--host-header", "vhost.test:8443", &url,
+    ])
+    .await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+
+    let received = server.received();
+    let hosts: Vec<_> = received.iter().map(|request| request.header("host")).collect();
+    assert_eq!(hosts, [Some("vhost.test"), Some("vhost.test:8443")]);
+    // Sent once, not alongside the URL's own
+    for request in &received {
+        let host_lines = request.head.lines().filter(|line| line.to_ascii_lowercase().starts_with("host:"));
+        assert_eq!(host_lines.count(), 1, "{}", request.head);
+    }
+}
+
+#[tokio::test]
+async fn compressed_request_sends_a_gzip_body() {
+    let server = text_server("ok").await;
+    let body = format!("{{\"items\":[{}]}}", vec!["\"same text again\""; 200].join(","));
+    let url = server.at("/upload").to_string();
+    let args = ["request", "--method", "POST", "--body", &body, "--compressed-request", &url];
+    let output = client_async(&args).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+
+    let received = server.received();
+    let request = &received[0];
+    assert_eq!(request.header("content-encoding"), Some("gzip"));
+    assert_eq!(request.header("content-length"), Some(request.body.len().to_string().as_str()));
+    assert!(request.body.len() < body.len() / 10, "{} bytes", request.body.len());
+    let mut decompressed = String::new();
+    std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&request.body[..]), &mut decompressed).unwrap();
+    assert_eq!(decompressed, body);
+}
+
+#[test]
+fn compressed_request_needs_a_body() {
+    let output = client(&["request", "--method", "POST", "--compressed-request", UNUSED_URL]);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+    assert!(stderr(&output).contains("--compressed-request needs a request body"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn connect_to_keeps_the_url_host() {
+    let server = text_server("ok").await;
+    let rule = format!("example.test:80:127.0.0.1:{}", server.url.port().unwrap());
+    let output = client_async(&["request", "--method", "GET", "--connect-to", &rule, "http://example.test/page"]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Response: ok"), "{}", stdout(&output));
+
+    let received = server.received();
+    assert!(received[0].head.starts_with("GET /page HTTP/1.1"), "{}", received[0].head);
+    assert_eq!(received[0].header("host"), Some("example.test"));
+}
+
+// The connection each request the server received came on
+fn connections(server: &mock::MockServer) -> Vec<usize> {
+    server.received().iter().map(|request| request.connection).collect()
+}
+
+// The value of header `name` in each request the server received
+fn headers(server: &mock::MockServer, name: &str) -> Vec<Option<String>> {
+    server.received().iter().map(|request| request.header(name).map(String::from)).collect()
+}
+
+#[tokio::test]
+async fn no_keepalive_opens_a_connection_per_request() {
+    let server = text_server("ok").await;
+    let url = server.at("/").to_string();
+    let output = client_async(&["request", "--method", "GET", "--repeat", "3", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(connections(&server), [0, 0, 0]);
+
+    let server = text_server("ok").await;
+    let url = server.at("/").to_string();
+    let output = client_async(&["request", "--method", "GET", "--repeat", "3", "--no-keepalive", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(connections(&server), [0, 1, 2]);
+}
+
+// A cleartext HTTP/2 server (prior knowledge, no upgrade) answering "ok"
+async fn h2_server() -> String {
+    use hyper::service::{make_service_fn, service_fn};
+    let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+        .http2_only(true)
+        .serve(make_service_fn(|_| async {
+            Ok::<_, std::convert::Infallible>(service_fn(|_| async {
+                Ok::<_, std::convert::Infallible>(hyper::Response::new(hyper::Body::from("ok")))
+            }))
+        }));
+    let url = format!("http://{}/", server.local_addr());
+    tokio::spawn(server);
+    url
+}
+
+#[tokio::test]
+async fn show_protocol_reports_forced_http2() {
+    let url = h2_server().await;
+    let output = client_async(&["request", "--method", "GET", "--http2", "--show-protocol", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Protocol: HTTP/2 (ALPN: none, cleartext)"), "{}", stderr(&output));
+
+    let server = text_server("ok").await;
+    let url = server.at("/").to_string();
+    let output = client_async(&["request", "--method", "GET", "--show-protocol", &url]).await;
+    assert!(stderr(&output).contains("Protocol: HTTP/1.1 (ALPN: none, cleartext)"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn fail_with_body_prints_the_error_body() {
+    let server = status_server("404 Not Found", "no such widget").await;
+    let url = server.at("/widgets/9").to_string();
+
+    // Without it the request fails and the body isn't printed
+    let output = client_async(&["request", "--method", "GET", &url]).await;
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!stdout(&output).contains("no such widget"), "{}", stdout(&output));
+
+    let output = client_async(&["request", "--method", "GET", "--fail-with-body", &url]).await;
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Response: no such widget"), "{}", stdout(&output));
+    assert!(stderr(&output).contains("404"), "{}", stderr(&output));
+}
+
+// Runs the client until `expected` appears on its stdout, returning how long
+// that took and everything it printed in the end
+async fn time_until_printed(args: &[&str], expected: &'static str) -> (Duration, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_terminal-web-client"))
+        .args(args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    tokio::task::spawn_blocking(move || {
+        let started = Instant::now();
+        let mut stdout = child.stdout.take().unwrap();
+        let mut printed = Vec::new();
+        let mut byte = [0];
+        while !String::from_utf8_lossy(&printed).contains(expected) {
+            assert_eq!(stdout.read(&mut byte).unwrap(), 1, "{}", String::from_utf8_lossy(&printed));
+            printed.push(byte[0]);
+        }
+        let elapsed = started.elapsed();
+        stdout.read_to_end(&mut printed).unwrap();
+        assert!(child.wait().unwrap().success());
+        (elapsed, String::from_utf8(printed).unwrap())
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn no_buffer_prints_each_chunk_as_it_arrives() {
+    let server = mock::serve(|_| {
+        vec![
+            Part::Send(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nfirst \r\n".to_vec()),
+            Part::Wait(Duration::from_millis(1500)),
+            Part::Send(b"6\r\nsecond\r\n0\r\n\r\n".to_vec()),
+        ]
+    })
+    .await;
+    let url = server.at("/stream").to_string();
+
+    let (elapsed, printed) = time_until_printed(&["request", "--method", "GET", "--no-buffer", &url], "first").await;
+    assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+    assert_eq!(printed.trim_end(), "Response: first second");
+
+    // Without it nothing is printed until the body is complete
+    let (elapsed, _) = time_until_printed(&["request", "--method", "GET", &url], "first").await;
+    assert!(elapsed >= Duration::from_millis(1500), "{:?}", elapsed);
+}
+
+#[tokio::test]
+async fn continue_resumes_a_broken_transfer() {
+    const BODY: &str = "0123456789abcdefghij";
+    let server = mock::serve(|request| match request.header("range") {
+        // The first attempt breaks off after half the body
+        None => vec![
+            Part::Send(format!("HTTP/1.1 200 OK\r\nContent-Length: 20\r\n\r\n{}", &BODY[..10]).into_bytes()),
+            Part::Close,
+        ],
+        Some(_) => {
+            let head = "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 10-19/20\r\nContent-Length: 10\r\n\r\n";
+            vec![Part::Send(format!("{}{}", head, &BODY[10..]).into_bytes())]
+        }
+    })
+    .await;
+    let url = server.at("/file").to_string();
+    let args = ["request", "--method", "GET", "--retry", "2", "--retry-max-wait", "0", "--continue", &url];
+    let output = client_async(&args).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output).trim_end(), format!("Response: {}", BODY));
+    assert!(stderr(&output).contains("resuming at byte 10"), "{}", stderr(&output));
+
+    let ranges: Vec<_> = server.received().iter().map(|request| request.header("range").map(String::from)).collect();
+    assert_eq!(ranges, [None, Some("bytes=10-".to_string())]);
+}
+
+#[tokio::test]
+async fn append_adds_each_response_to_the_output_file() {
+    let server = mock::serve(|request| {
+        let body = if request.head.starts_with("GET /one ") { "first\n" } else { "second\n" };
+        let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+        vec![Part::Send(response.into_bytes())]
+    })
+    .await;
+    let file = TempFile::new("append.txt", "existing\n");
+    for path in ["/one", "/two"] {
+        let url = server.at(path).to_string();
+        let output = client_async(&["request", "--method", "GET", "-o", file.path(), "--append", &url]).await;
+        assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    }
+    assert_eq!(std::fs::read_to_string(file.path()).unwrap(), "existing\nfirst\nsecond\n");
+}
+
+#[tokio::test]
+async fn compressed_decodes_a_zstd_response() {
+    let server = mock::serve(|request| {
+        let body = match request.header("accept-encoding") {
+            Some(accepted) if accepted.contains("zstd") => zstd::encode_all(&b"{\"packed\":true}"[..], 0).unwrap(),
+            _ => b"not asked for zstd".to_vec(),
+        };
+        let head = format!("HTTP/1.1 200 OK\r\nContent-Encoding: zstd\r\nContent-Length: {}\r\n\r\n", body.len());
+        vec![Part::Send(head.into_bytes()), Part::Send(body)]
+    })
+    .await;
+    let url = server.at("/").to_string();
+    let output = client_async(&["request", "--method", "GET", "--compressed", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output).trim_end(), "Response: {\"packed\":true}");
+    assert_eq!(server.received()[0].header("accept-encoding"), Some("gzip, br, zstd"));
+}
+
+#[tokio::test]
+async fn location_trusted_keeps_authorization_across_hosts() {
+    let target = text_server("landed").await;
+    let location = target.at("/landing").to_string();
+    // Another port is another origin, as far as credentials go
+    let origin = mock::serve(move |_| {
+        let response = format!("HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n", location);
+        vec![Part::Send(response.into_bytes())]
+    })
+    .await;
+    let url = origin.at("/start").to_string();
+    let auth = "Authorization: Bearer secret";
+
+    let output = client_async(&["request", "--method", "GET", "--headers", auth, &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Response: landed"), "{}", stdout(&output));
+    let output = client_async(&["request", "--method", "GET", "--headers", auth, "--location-trusted", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Response: landed"), "{}", stdout(&output));
+
+    let secret = Some("Bearer secret".to_string());
+    assert_eq!(headers(&origin, "authorization"), [secret.clone(), secret.clone()]);
+    assert_eq!(headers(&target, "authorization"), [None, secret]);
+}
+
+#[tokio::test]
+async fn path_as_is_sends_dot_segments() {
+    let server = text_server("ok").await;
+    let url = format!("{}a/../b", server.url);
+    let output = client_async(&["request", "--method", "GET", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    let output = client_async(&["request", "--method", "GET", "--path-as-is", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+
+    let received = server.received();
+    let lines: Vec<_> = received.iter().map(|request| request.head.lines().next().unwrap()).collect();
+    assert_eq!(lines, ["GET /b HTTP/1.1", "GET /a/../b HTTP/1.1"]);
+}
+
+#[tokio::test]
+async fn stdout_tee_prints_what_is_saved() {
+    let server = mock::serve(|_| {
+        let head = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n";
+        vec![
+            Part::Send(head.as_bytes().to_vec()),
+            Part::Send(b"5\r\nline \r\n".to_vec()),
+            Part::Send(b"5\r\none\n\xff\r\n0\r\n\r\n".to_vec()),
+        ]
+    })
+    .await;
+    let url = server.at("/").to_string();
+    let file = TempFile::new("tee.bin", "");
+    let args = ["request", "--method", "GET", "-o", file.path(), "--force", "--stdout-tee", &url];
+    let output = client_async(&args).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    let saved = std::fs::read(file.path()).unwrap();
+    assert_eq!(saved, b"line one\n\xff");
+    assert_eq!(output.stdout, saved);
+}
+
+#[tokio::test]
+async fn verbose_reports_connection_reuse() {
+    let server = text_server("ok").await;
+    let url = server.at("/").to_string();
+    let output = client_async(&["request", "--method", "GET", "--repeat", "3", "--verbose", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    let stderr = stderr(&output);
+    let reports: Vec<_> = stderr
+        .lines()
+        .filter(|line| line.contains("new connection") || line.contains("Re-using existing connection"))
+        .collect();
+    assert_eq!(reports.len(), 3, "{}", stderr);
+    // The same connection each time, by the id the first line gives it
+    let peer = format!("{}:{}", server.url.host_str().unwrap(), server.url.port().unwrap());
+    let id = reports[0].split('#').nth(1).unwrap().trim_end_matches(')');
+    assert_eq!(reports[0], format!("Connected to {} (new connection #{})", peer, id));
+    for line in &reports[1..] {
+        assert_eq!(*line, format!("Re-using existing connection #{} to {}", id, peer));
+    }
+    assert_eq!(connections(&server), [0, 0, 0]);
+}
+
+#[tokio::test]
+async fn verbose_reports_the_decoded_size() {
+    let body = "gzip me ".repeat(500);
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut encoder, body.as_bytes()).unwrap();
+    let encoded = encoder.finish().unwrap();
+    let expected = format!("Content-Encoding: gzip, decoded {} bytes to {}", encoded.len(), body.len());
+    let server = mock::serve(move |_| {
+        let head = format!("HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n", encoded.len());
+        vec![Part::Send(head.into_bytes()), Part::Send(encoded.clone())]
+    })
+    .await;
+    let url = server.at("/").to_string();
+
+    let output = client_async(&["request", "--method", "GET", "--compressed", "--verbose", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stderr(&output).lines().any(|line| line == expected), "{}", stderr(&output));
+    assert_eq!(stdout(&output).trim_end(), format!("Response: {}", body.trim_end()));
+
+    // Nothing to report when no encoding was asked for
+    let output = client_async(&["request", "--method", "GET", "--verbose", &url]).await;
+    assert!(!stderr(&output).contains("Content-Encoding"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn existing_output_file_needs_force() {
+    let server = text_server("new contents").await;
+    let url = server.at("/").to_string();
+    let file = TempFile::new("overwrite.txt", "old contents");
+
+    // Without a terminal to ask on, the file is left alone and nothing is sent
+    let output = client_async(&["request", "--method", "GET", "-o", file.path(), &url]).await;
+    assert_eq!(output.status.code(), Some(1));
+    let expected = format!("Refusing to overwrite \"{}\", pass --force to replace it", file.path());
+    assert!(stderr(&output).contains(&expected), "{}", stderr(&output));
+    assert_eq!(std::fs::read_to_string(file.path()).unwrap(), "old contents");
+    assert!(server.received().is_empty());
+
+    let output = client_async(&["request", "--method", "GET", "-o", file.path(), "--force", &url]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(std::fs::read_to_string(file.path()).unwrap(), "new contents");
+}
+
+#[tokio::test]
+async fn repeat_interval_recovers_from_a_closed_idle_connection() {
+    // Each connection is answered once, then closed when it is reused after
+    // the idle gap
+    let answered = std::sync::Mutex::new(Vec::new());
+    let server = mock::serve(move |request| {
+        let mut answered = answered.lock().unwrap();
+        if answered.contains(&request.connection) {
+            return vec![Part::Close];
+        }
+        answered.push(request.connection);
+        vec![Part::Send(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_vec())]
+    })
+    .await;
+    let url = server.at("/poll").to_string();
+    let args = ["request", "--method", "GET", "--repeat", "3", "--repeat-interval", "0.2", "--verbose", &url];
+    let output = client_async(&args).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output).matches("Response: ok").count(), 3, "{}", stdout(&output));
+    let retries = stderr(&output).matches("retrying on a new one").count();
+    assert_eq!(retries, 2, "{}", stderr(&output));
+    assert_eq!(connections(&server), [0, 0, 1, 1, 2]);
+}